@@ -3,5 +3,5 @@ use novigrad::{datasets::addition_perceptron::load_addition_perceptron, train_mo
 fn main() {
     let device = Device::default();
     let details = load_addition_perceptron(&device).unwrap();
-    train_model::<f32>(details).unwrap();
+    train_model(details).unwrap();
 }
@@ -0,0 +1,7 @@
+use novigrad::{datasets::geoffroy_hinton_lstm::load_geoffroy_hinton_lstm, train_model, Device};
+
+fn main() {
+    let device = Device::default();
+    let details = load_geoffroy_hinton_lstm(&device).unwrap();
+    train_model(details).unwrap();
+}
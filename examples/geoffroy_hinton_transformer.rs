@@ -5,5 +5,5 @@ use novigrad::{
 fn main() {
     let device = Device::default();
     let details = load_geoffroy_hinton_transformer(&device).unwrap();
-    train_model::<f32>(details).unwrap();
+    train_model(details).unwrap();
 }
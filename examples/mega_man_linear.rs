@@ -3,5 +3,5 @@ use novigrad::{datasets::mega_man_linear::load_mega_man_linear, train_model, Dev
 fn main() {
     let device = Device::default();
     let details = load_mega_man_linear(&device).unwrap();
-    train_model::<f32>(details).unwrap();
+    train_model(details).unwrap();
 }
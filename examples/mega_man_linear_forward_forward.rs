@@ -0,0 +1,9 @@
+use novigrad::{
+    datasets::mega_man_linear::load_mega_man_linear_forward_forward, train_forward_forward, Device,
+};
+
+fn main() {
+    let device = Device::default();
+    let details = load_mega_man_linear_forward_forward(&device).unwrap();
+    train_forward_forward(details).unwrap();
+}
@@ -6,5 +6,5 @@ use novigrad::{
 fn main() {
     let device = Device::default();
     let details = load_mega_man_multi_head_attention(&device).unwrap();
-    train_model::<f32>(details).unwrap();
+    train_model(details).unwrap();
 }
@@ -1,48 +1,58 @@
 use novigrad::{
     batch::make_batches,
-    datasets::into_one_hot_encoded_rows,
+    datasets::into_token_id_rows,
     error, get_row_argmax,
     neural_program::NeuralProgram,
     schedulers::DefaultStreamScheduler,
     tensor::{Error, ErrorEnum, Tensor},
+    transformer::TransformerConfig,
     transformer_model::TransformerModel,
-    Adam, Device, NeuralMachine, SoftmaxCrossEntropyLoss, TensorWithGrad, Tokenizer,
+    Adam, Device, NeuralMachine, OnlineLearning, SparseCrossEntropyAttributes,
+    SparseSoftmaxCrossEntropyLoss, SpecialToken, StreamingDecoder, TensorWithGrad, Tokenizer,
     TokenizerTrait,
 };
-use std::{fs::read_to_string, io};
+use std::{
+    fs::read_to_string,
+    io::{self, Write},
+    time::Duration,
+};
 
 fn main() -> Result<(), Error> {
     let device = Device::default();
-    let mut tokenizer = Tokenizer::ascii_tokenizer();
+    let tokenizer = Tokenizer::ascii_tokenizer();
     let context_length = 32; //256;
-    let padding_token = 0;
+    let padding_token = tokenizer.special_token(SpecialToken::Pad);
     let layers = 1;
-    let num_heads = 12;
-    let dropout_probability = 0.1;
     let n_embd = 768;
     let vocab_size = tokenizer.vocab_size();
-    let causal_mask = true;
-    let model = TransformerModel::new(
+    let config = TransformerConfig {
+        num_kv_heads: 4,
+        dropout_probability: 0.1,
+        tied_embedding: true,
+        ..TransformerConfig::new(12)
+    };
+    let model =
+        TransformerModel::new(&device, layers, n_embd, context_length, vocab_size, &config)?;
+
+    // Padding is not learned.
+    let loss_operator = SparseSoftmaxCrossEntropyLoss::try_new_with_attributes(
         &device,
-        layers,
-        num_heads,
-        dropout_probability,
-        n_embd,
-        context_length,
-        vocab_size,
-        causal_mask,
+        SparseCrossEntropyAttributes {
+            ignore_index: Some(padding_token as i32),
+            ..Default::default()
+        },
     )?;
-
-    let loss_operator = SoftmaxCrossEntropyLoss::new(&device);
     let batch_size = 32;
-    let clipped_gradient_norm = true;
+    let max_gradient_norm = Some(1.0);
     let optimizer = Adam::try_new(0.2, 0.9, 0.999, 1e-8, 0.0)?;
+    let parameter_groups = vec![];
     let program = NeuralProgram::try_new(
         &device,
         &model,
         &loss_operator,
         &optimizer,
-        clipped_gradient_norm,
+        max_gradient_norm,
+        &parameter_groups,
     )?;
 
     let maximum_device_streams = 16;
@@ -58,7 +68,7 @@ fn main() -> Result<(), Error> {
     println!("-------------------------------------------------------------------");
     println!("This is a Novigrad-powered chatbot");
     println!("A forward pass is all you need");
-    println!("The chatbot knows nothing and will learn as you interact with it. (TODO)");
+    println!("The chatbot knows nothing and will learn as you interact with it.");
     println!("-------------------------------------------------------------------");
 
     //let train_corpus_path = "data/arc-prize-2024-3aa6fb7a-train-examples.txt";
@@ -73,7 +83,9 @@ fn main() -> Result<(), Error> {
 
     let train_examples = train_examples
         .iter()
-        .map(|example| generate_examples(example, &mut tokenizer, context_length, &device))
+        .map(|example| {
+            generate_examples(example, &tokenizer, context_length, padding_token, &device)
+        })
         .collect::<Result<Vec<_>, _>>()
         .unwrap()
         .concat();
@@ -83,19 +95,19 @@ fn main() -> Result<(), Error> {
     for epoch in 0..epochs {
         println!("Epoch: {}", epoch);
 
-        let batches = make_batches(&indices, shuffle_examples, batch_size);
+        let batches = make_batches(&indices, shuffle_examples, batch_size, &mut *device.rng());
         let mut total_loss = 0.0;
 
         for batch in batches.iter() {
             for i in batch.iter() {
-                let (input_one_hot, expected_output_one_hot) = &train_examples[*i];
-                let _actual_output_one_hot = neural_machine.infer(&input_one_hot)?;
-                let loss = neural_machine.loss(&expected_output_one_hot)?;
+                let (input_token_ids, expected_output_token_ids) = &train_examples[*i];
+                let _actual_output_one_hot = neural_machine.infer(&input_token_ids)?;
+                let loss = neural_machine.loss(&expected_output_token_ids)?;
                 let loss: &Tensor = &loss.tensor();
                 let loss: f32 = loss.try_into()?;
                 /*
                 let expected_next_token =
-                    get_row_argmax(&expected_output_one_hot.tensor(), context_length - 1)?;
+                    expected_output_token_ids.tensor().get_values()?[context_length - 1] as usize;
                 let actual_next_token =
                     get_row_argmax(&actual_output_one_hot.tensor(), context_length - 1)?;
                 println!(
@@ -114,18 +126,51 @@ fn main() -> Result<(), Error> {
         println!("Prompt:  {}", prompt);
         let prompt_tokens = tokenizer.encode(prompt);
         let max_len = 60;
-        let auto_regressive_tokens = auto_regressive_inference(
+        print!("Chatbot: {}", prompt);
+        auto_regressive_inference(
             &mut neural_machine,
             &device,
+            &tokenizer,
             &prompt_tokens,
             context_length,
-            vocab_size,
             max_len,
             padding_token,
         )?;
-        let actual_output = tokenizer.decode(&auto_regressive_tokens)?;
+    }
+
+    // Learn from every user message.
+    neural_machine.set_online_learning(Some(OnlineLearning {
+        minimum_update_interval: Duration::from_millis(100),
+        ..OnlineLearning::new(4, padding_token)
+    }));
+
+    println!("Type a message and press enter. An empty message exits.");
+    loop {
+        let prompt = read_prompt()?;
+        let prompt = prompt.trim_end();
+        if prompt.is_empty() {
+            break;
+        }
 
-        println!("Chatbot: {}", actual_output);
+        // In online learning mode, the inference learns the next tokens of the message.
+        let prompt_tokens = tokenizer.encode(prompt);
+        for input_token_ids in
+            generate_inputs(&prompt_tokens, context_length, padding_token, &device)?
+        {
+            neural_machine.infer(&input_token_ids)?;
+        }
+
+        let max_len = prompt_tokens.len() + 40;
+        print!("Chatbot: ");
+        auto_regressive_inference(
+            &mut neural_machine,
+            &device,
+            &tokenizer,
+            &prompt_tokens,
+            context_length,
+            max_len,
+            padding_token,
+        )?;
     }
 
     Ok(())
@@ -137,7 +182,7 @@ pub fn add_padding(tokens: &mut Vec<usize>, context_length: usize, padding_token
     }
 }
 
-fn _read_prompt() -> Result<String, Error> {
+fn read_prompt() -> Result<String, Error> {
     let mut prompt = String::new();
     let stdin = io::stdin();
     match stdin.read_line(&mut prompt) {
@@ -146,16 +191,19 @@ fn _read_prompt() -> Result<String, Error> {
     }
 }
 
+/// Print the generated text as the tokens are produced.
 fn auto_regressive_inference(
     neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
     device: &Device,
+    tokenizer: &Tokenizer,
     prompt_tokens: &[usize],
     context_length: usize,
-    vocab_size: usize,
     max_len: usize,
     padding_token: usize,
-) -> Result<Vec<usize>, Error> {
+) -> Result<(), Error> {
     let mut auto_regressive_tokens = prompt_tokens.to_owned();
+    let mut decoder = StreamingDecoder::default();
+    neural_machine.set_training(false)?;
 
     // TODO implement another stopping criterion.
     while auto_regressive_tokens.len() < max_len {
@@ -169,9 +217,9 @@ fn auto_regressive_inference(
             auto_regressive_tokens[(auto_regressive_tokens.len() - context_length)..].to_owned()
         };
 
-        let input_one_hot = into_one_hot_encoded_rows(device, &input_tokens, vocab_size)?;
+        let input_token_ids = into_token_id_rows(device, &input_tokens)?;
 
-        let actual_output_one_hot = neural_machine.infer(&input_one_hot)?;
+        let actual_output_one_hot = neural_machine.infer(&input_token_ids)?;
         let last_row = if auto_regressive_tokens.len() <= context_length {
             auto_regressive_tokens.len() - 1
         } else {
@@ -180,8 +228,14 @@ fn auto_regressive_inference(
         let predicted_next_token = get_row_argmax(&actual_output_one_hot.tensor(), last_row)?;
         //println!("predicted next token: {}", predicted_next_token);
         auto_regressive_tokens.push(predicted_next_token);
+        print!("{}", decoder.decode(tokenizer, &[predicted_next_token]));
+        io::stdout()
+            .flush()
+            .map_err(|_| error!(ErrorEnum::InputOutputError))?;
     }
-    Ok(auto_regressive_tokens)
+    println!("{}", decoder.finish());
+    neural_machine.set_training(true)?;
+    Ok(())
 }
 
 fn read_text_examples(corpus: &str) -> Vec<String> {
@@ -214,23 +268,39 @@ fn read_text_examples(corpus: &str) -> Vec<String> {
 
 fn generate_examples(
     example: &str,
-    tokenizer: &mut Tokenizer,
+    tokenizer: &Tokenizer,
     context_length: usize,
+    padding_token: usize,
     device: &Device,
 ) -> Result<Vec<(TensorWithGrad, TensorWithGrad)>, Error> {
-    let vocab_size = tokenizer.vocab_size();
-    let tokens = tokenizer.encode(example);
+    let mut tokens = tokenizer.encode(example);
+    // Short examples, like user messages, are padded.
+    add_padding(&mut tokens, context_length + 1, padding_token);
     let mut examples = vec![];
     for i in 0..(tokens.len() - context_length) {
         let input_tokens = &tokens[i..i + context_length];
-        let input_one_hot = into_one_hot_encoded_rows(&device, &input_tokens, vocab_size)?;
+        let input_token_ids = into_token_id_rows(&device, &input_tokens)?;
 
         let output_tokens = &tokens[i + 1..i + context_length + 1];
-        let output_one_hot = into_one_hot_encoded_rows(&device, &output_tokens, vocab_size)?;
+        let output_token_ids = into_token_id_rows(&device, &output_tokens)?;
 
         //println!("in {:?}", input_tokens);
         //println!("out {:?}", output_tokens);
-        examples.push((input_one_hot, output_one_hot));
+        examples.push((input_token_ids, output_token_ids));
     }
     Ok(examples)
 }
+
+/// Inputs of the windows of the tokens. Short messages are padded.
+fn generate_inputs(
+    tokens: &[usize],
+    context_length: usize,
+    padding_token: usize,
+    device: &Device,
+) -> Result<Vec<TensorWithGrad>, Error> {
+    let mut tokens = tokens.to_owned();
+    add_padding(&mut tokens, context_length, padding_token);
+    (0..=(tokens.len() - context_length))
+        .map(|i| into_token_id_rows(device, &tokens[i..i + context_length]))
+        .collect()
+}
@@ -35,14 +35,18 @@ pub fn load_addition_perceptron(
     let details = DatasetDetails {
         device: device.clone(),
         train_examples: examples,
+        validation_examples: vec![],
         test_examples: vec![],
         model,
         loss_operator,
         optimizer,
+        parameter_groups: vec![],
         epochs: 100,
         progress: 10,
+        early_stopping_patience: None,
+        restore_best_parameters: false,
         shuffle_examples: false,
-        clip_gradient_norm: true,
+        max_gradient_norm: Some(1.0),
         initial_metrics_min: Metrics {
             total_loss: 0.1,
             total_next_token_perplexity: f32::NAN,
@@ -53,6 +57,8 @@ pub fn load_addition_perceptron(
         },
         maximum_incorrect_predicted_next_tokens: 0,
         printer: RawPrinter::default(),
+        metrics_sinks: vec![],
+        callbacks: vec![],
         batch_size: 1,
     };
     Ok(details)
@@ -7,11 +7,12 @@ use crate::{
     display::BoardPrinter,
     error,
     tensor::{Error, ErrorEnum},
+    transformer::TransformerConfig,
     transformer_model::TransformerModel,
-    Adam, Device, Metrics, SoftmaxCrossEntropyLoss, TensorWithGrad,
+    Adam, Device, Metrics, SparseSoftmaxCrossEntropyLoss, TensorWithGrad,
 };
 
-use super::{into_one_hot_encoded_rows, DatasetDetails};
+use super::{into_token_id_rows, DatasetDetails};
 
 #[derive(Serialize, Deserialize)]
 struct Problem {
@@ -30,7 +31,6 @@ fn load_examples(
     problem_id: &str,
     train_or_test: &str,
     device: &Device,
-    vocab_size: usize,
 ) -> Result<Vec<(TensorWithGrad, TensorWithGrad)>, Error> {
     let file_path =
         format!("/home/sebhtml/projects/ARC-AGI/data/{training_or_evaluation}/{problem_id}.json");
@@ -52,9 +52,9 @@ fn load_examples(
     examples
         .into_iter()
         .map(|example| {
-            let one_hot_encoded_input = into_one_hot_encoded_rows(device, &example.0, vocab_size);
-            let one_hot_encoded_output = into_one_hot_encoded_rows(device, &example.1, vocab_size);
-            (one_hot_encoded_input, one_hot_encoded_output)
+            let input = into_token_id_rows(device, &example.0);
+            let output = into_token_id_rows(device, &example.1);
+            (input, output)
         })
         .try_fold(vec![], |mut acc, item| match item {
             (Ok(a), Ok(b)) => {
@@ -67,40 +67,43 @@ fn load_examples(
 
 pub fn load_arc_prize_2024(
     device: &Device,
-) -> Result<DatasetDetails<TransformerModel, SoftmaxCrossEntropyLoss, Adam, BoardPrinter>, Error> {
+) -> Result<
+    DatasetDetails<TransformerModel, SparseSoftmaxCrossEntropyLoss, Adam, BoardPrinter>,
+    Error,
+> {
     let vocab_size = 10;
     let sequence_length = 7 * 7;
-    let training_examples = load_examples("training", "3aa6fb7a", "train", device, vocab_size)?;
-    let test_examples = load_examples("training", "3aa6fb7a", "test", device, vocab_size)?;
+    let training_examples = load_examples("training", "3aa6fb7a", "train", device)?;
+    let test_examples = load_examples("training", "3aa6fb7a", "test", device)?;
 
-    let loss_operator = SoftmaxCrossEntropyLoss::new(device);
+    // The expected outputs are the colors of the cells.
+    let loss_operator = SparseSoftmaxCrossEntropyLoss::new(device);
     let optimizer = Adam::try_new(0.05, 0.9, 0.999, 1e-8, 0.0)?;
     let layers = 2;
     let num_heads = 12;
-    let dropout_probability = 0.1;
     let n_embd = 768;
-    let causal_mask = false;
-    let model = TransformerModel::new(
-        device,
-        layers,
-        num_heads,
-        dropout_probability,
-        n_embd,
-        sequence_length,
-        vocab_size,
-        causal_mask,
-    )?;
+    let config = TransformerConfig {
+        causal_mask: false,
+        dropout_probability: 0.1,
+        ..TransformerConfig::new(num_heads)
+    };
+    let model =
+        TransformerModel::new(device, layers, n_embd, sequence_length, vocab_size, &config)?;
     let details = DatasetDetails {
         device: device.clone(),
         train_examples: training_examples,
+        validation_examples: vec![],
         test_examples,
         model,
         loss_operator,
         optimizer,
+        parameter_groups: vec![],
         epochs: 500,
         progress: 100,
+        early_stopping_patience: None,
+        restore_best_parameters: false,
         shuffle_examples: true,
-        clip_gradient_norm: true,
+        max_gradient_norm: Some(1.0),
         initial_metrics_min: Metrics {
             total_loss: 5.0,
             total_next_token_perplexity: 200.0,
@@ -111,6 +114,8 @@ pub fn load_arc_prize_2024(
         },
         maximum_incorrect_predicted_next_tokens: 0,
         printer: BoardPrinter::default(),
+        metrics_sinks: vec![],
+        callbacks: vec![],
         batch_size: 1,
     };
     Ok(details)
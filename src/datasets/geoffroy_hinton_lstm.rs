@@ -0,0 +1,84 @@
+use crate::{
+    adam_w::AdamW,
+    display::NextTokenPredictionPrinter,
+    recurrent_model::{Recurrence, RecurrentModel},
+    tensor::Error,
+    Device, Metrics, SoftmaxCrossEntropyLoss, Tokenizer, TokenizerTrait,
+};
+
+use super::{load_examples, split_validation_examples, DatasetDetails};
+
+/// Recurrent baseline for load_geoffroy_hinton_transformer.
+pub fn load_geoffroy_hinton_lstm(
+    device: &Device,
+) -> Result<
+    DatasetDetails<RecurrentModel, SoftmaxCrossEntropyLoss, AdamW, NextTokenPredictionPrinter>,
+    Error,
+> {
+    let file_path = "data/Geoffrey_Hinton.txt";
+    let max_chars = None;
+    let max_number_of_examples = 36;
+    let validation_ratio = 1.0 / 6.0;
+    let tokenizer = Tokenizer::ascii_tokenizer();
+    let context_length = 32;
+
+    let input_sequence_length = context_length;
+    let output_sequence_length = context_length;
+    let mut train_examples = load_examples(
+        device,
+        file_path,
+        max_chars,
+        max_number_of_examples,
+        input_sequence_length,
+        output_sequence_length,
+        &tokenizer,
+    )?;
+    let validation_examples = split_validation_examples(&mut train_examples, validation_ratio)?;
+
+    let vocab_size = tokenizer.vocab_size();
+    let layers = 1;
+    let n_embd = 64;
+    let hidden_size = 128;
+    let model = RecurrentModel::new(
+        device,
+        Recurrence::Lstm,
+        layers,
+        n_embd,
+        hidden_size,
+        context_length,
+        vocab_size,
+    )?;
+
+    let loss_operator = SoftmaxCrossEntropyLoss::new(device);
+    let optimizer = AdamW::try_new(0.01, 0.9, 0.999, 1e-7, 0.01)?;
+    let details = DatasetDetails {
+        device: device.clone(),
+        train_examples,
+        validation_examples,
+        test_examples: vec![],
+        model,
+        loss_operator,
+        optimizer,
+        parameter_groups: vec![],
+        epochs: 50,
+        progress: 10,
+        early_stopping_patience: Some(2),
+        restore_best_parameters: true,
+        shuffle_examples: true,
+        max_gradient_norm: Some(1.0),
+        initial_metrics_min: Metrics {
+            total_loss: 2000.0,
+            total_next_token_perplexity: 5.0,
+        },
+        final_metrics_max: Metrics {
+            total_loss: 450.0,
+            total_next_token_perplexity: 45.0,
+        },
+        maximum_incorrect_predicted_next_tokens: 3,
+        printer: NextTokenPredictionPrinter::new(tokenizer),
+        metrics_sinks: vec![],
+        callbacks: vec![],
+        batch_size: 1,
+    };
+    Ok(details)
+}
@@ -1,10 +1,10 @@
 use crate::{
     adam_w::AdamW, display::NextTokenPredictionPrinter, tensor::Error,
-    transformer_model::TransformerModel, Device, Metrics, SoftmaxCrossEntropyLoss, Tokenizer,
-    TokenizerTrait,
+    transformer::TransformerConfig, transformer_model::TransformerModel, Device, Metrics,
+    SoftmaxCrossEntropyLoss, Tokenizer, TokenizerTrait,
 };
 
-use super::{load_examples, DatasetDetails};
+use super::{load_token_id_examples, DatasetDetails};
 
 pub fn load_geoffroy_hinton_transformer(
     device: &Device,
@@ -15,51 +15,48 @@ pub fn load_geoffroy_hinton_transformer(
     let file_path = "data/Geoffrey_Hinton.txt";
     let max_chars = None;
     let max_number_of_examples = 30;
-    let mut tokenizer = Tokenizer::ascii_tokenizer();
+    let tokenizer = Tokenizer::ascii_tokenizer();
     let context_length = 64;
 
     let input_sequence_length = context_length;
     let output_sequence_length = context_length;
-    let examples = load_examples(
+    let examples = load_token_id_examples(
         device,
         file_path,
         max_chars,
         max_number_of_examples,
         input_sequence_length,
         output_sequence_length,
-        &mut tokenizer,
+        &tokenizer,
     )?;
 
     let vocab_size = tokenizer.vocab_size();
     let layers = 2;
-    let causal_mask = true;
     let num_heads = 12;
-    let dropout_probability = 0.1;
     let n_embd = 768;
-    let model = TransformerModel::new(
-        device,
-        layers,
-        num_heads,
-        dropout_probability,
-        n_embd,
-        context_length,
-        vocab_size,
-        causal_mask,
-    )?;
+    let config = TransformerConfig {
+        dropout_probability: 0.1,
+        ..TransformerConfig::new(num_heads)
+    };
+    let model = TransformerModel::new(device, layers, n_embd, context_length, vocab_size, &config)?;
 
     let loss_operator = SoftmaxCrossEntropyLoss::new(device);
     let optimizer = AdamW::try_new(0.05, 0.9, 0.999, 1e-7, 0.01)?;
     let details = DatasetDetails {
         device: device.clone(),
         train_examples: examples,
+        validation_examples: vec![],
         test_examples: vec![],
         model,
         loss_operator,
         optimizer,
+        parameter_groups: vec![],
         epochs: 50,
         progress: 10,
+        early_stopping_patience: None,
+        restore_best_parameters: false,
         shuffle_examples: true,
-        clip_gradient_norm: true,
+        max_gradient_norm: Some(1.0),
         initial_metrics_min: Metrics {
             total_loss: 4000.0,
             total_next_token_perplexity: 5.0,
@@ -70,6 +67,8 @@ pub fn load_geoffroy_hinton_transformer(
         },
         maximum_incorrect_predicted_next_tokens: 3,
         printer: NextTokenPredictionPrinter::new(tokenizer),
+        metrics_sinks: vec![],
+        callbacks: vec![],
         batch_size: 1,
     };
     Ok(details)
@@ -14,7 +14,7 @@ pub fn load_mega_man_attention_head(
     let file_path = "data/Mega_Man.txt";
     let max_chars = None;
     let max_number_of_examples = 1;
-    let mut tokenizer = Tokenizer::ascii_tokenizer();
+    let tokenizer = Tokenizer::ascii_tokenizer();
     let sequence_length = 32;
 
     let input_sequence_length = sequence_length;
@@ -26,7 +26,7 @@ pub fn load_mega_man_attention_head(
         max_number_of_examples,
         input_sequence_length,
         output_sequence_length,
-        &mut tokenizer,
+        &tokenizer,
     )?;
 
     let vocab_size = tokenizer.vocab_size();
@@ -47,14 +47,18 @@ pub fn load_mega_man_attention_head(
     let details = DatasetDetails {
         device: device.clone(),
         train_examples: examples,
+        validation_examples: vec![],
         test_examples: vec![],
         model,
         loss_operator,
         optimizer,
+        parameter_groups: vec![],
         epochs: 200,
         progress: 10,
+        early_stopping_patience: None,
+        restore_best_parameters: false,
         shuffle_examples: true,
-        clip_gradient_norm: true,
+        max_gradient_norm: Some(1.0),
         initial_metrics_min: Metrics {
             total_loss: 100.0,
             total_next_token_perplexity: 5.0,
@@ -65,6 +69,8 @@ pub fn load_mega_man_attention_head(
         },
         maximum_incorrect_predicted_next_tokens: 0,
         printer: NextTokenPredictionPrinter::new(tokenizer),
+        metrics_sinks: vec![],
+        callbacks: vec![],
         batch_size: 1,
     };
     Ok(details)
@@ -1,9 +1,11 @@
 use crate::{
-    display::NextTokenPredictionPrinter, mega_man::MegaManModel, tensor::Error, Device,
-    GradientDescent, Metrics, SoftmaxCrossEntropyLoss, Tokenizer, TokenizerTrait,
+    display::NextTokenPredictionPrinter, mega_man::MegaManModel,
+    multi_layer_perceptron::MultiLayerPerceptronModel, tensor::Error, Device,
+    ForwardForwardDetails, GradientDescent, Metrics, SoftmaxCrossEntropyLoss, Tokenizer,
+    TokenizerTrait,
 };
 
-use super::{load_examples, DatasetDetails};
+use super::{into_one_hot_encoded_rows, load_examples, load_token_sequences, DatasetDetails};
 
 pub fn load_mega_man_linear(
     device: &Device,
@@ -19,7 +21,7 @@ pub fn load_mega_man_linear(
     let file_path = "data/Mega_Man.txt";
     let max_chars = None;
     let max_number_of_examples = 1000;
-    let mut tokenizer = Tokenizer::ascii_tokenizer();
+    let tokenizer = Tokenizer::ascii_tokenizer();
     let sequence_length = 32;
     let input_sequence_length = sequence_length;
     let output_sequence_length = 1;
@@ -30,7 +32,7 @@ pub fn load_mega_man_linear(
         max_number_of_examples,
         input_sequence_length,
         output_sequence_length,
-        &mut tokenizer,
+        &tokenizer,
     )?;
     let vocab_size = tokenizer.vocab_size();
     let model = MegaManModel::new(device, sequence_length, vocab_size)?;
@@ -39,14 +41,18 @@ pub fn load_mega_man_linear(
     let details = DatasetDetails {
         device: device.clone(),
         train_examples: examples,
+        validation_examples: vec![],
         test_examples: vec![],
         model,
         loss_operator,
         optimizer,
+        parameter_groups: vec![],
         epochs: 100,
         progress: 10,
+        early_stopping_patience: None,
+        restore_best_parameters: false,
         shuffle_examples: true,
-        clip_gradient_norm: true,
+        max_gradient_norm: Some(1.0),
         initial_metrics_min: Metrics {
             total_loss: 5500.0,
             total_next_token_perplexity: 250000.0,
@@ -57,7 +63,59 @@ pub fn load_mega_man_linear(
         },
         maximum_incorrect_predicted_next_tokens: 0,
         printer: NextTokenPredictionPrinter::new(tokenizer),
+        metrics_sinks: vec![],
+        callbacks: vec![],
         batch_size: 64,
     };
     Ok(details)
 }
+
+pub fn load_mega_man_linear_forward_forward(
+    device: &Device,
+) -> Result<ForwardForwardDetails<MultiLayerPerceptronModel, GradientDescent>, Error> {
+    let file_path = "data/Mega_Man.txt";
+    let max_chars = None;
+    let number_of_train_examples = 100;
+    let number_of_test_examples = 20;
+    let tokenizer = Tokenizer::ascii_tokenizer();
+    let sequence_length = 8;
+    let vocab_size = tokenizer.vocab_size();
+    // The test examples start after the last token of the train examples.
+    let max_number_of_examples =
+        number_of_train_examples + sequence_length + number_of_test_examples;
+    let mut train_examples = load_token_sequences(
+        file_path,
+        max_chars,
+        max_number_of_examples,
+        sequence_length,
+        sequence_length,
+        &tokenizer,
+    )?
+    .into_iter()
+    .map(|(input_tokens, output_tokens)| {
+        // The output is the input shifted by one token,
+        // so the label is the token after the input.
+        let label = output_tokens[sequence_length - 1];
+        let input = into_one_hot_encoded_rows(device, &input_tokens, vocab_size)?;
+        let expected_output = into_one_hot_encoded_rows(device, &[label], vocab_size)?;
+        Ok((input, expected_output))
+    })
+    .collect::<Result<Vec<_>, Error>>()?;
+    let test_examples = train_examples.split_off(number_of_train_examples + sequence_length);
+    train_examples.truncate(number_of_train_examples);
+    // The label is appended to the flattened input.
+    let input_size = (sequence_length + 1) * vocab_size;
+    let model = MultiLayerPerceptronModel::new(device, &[input_size, 128, 128])?;
+    let optimizer = GradientDescent::new(0.03);
+    let details = ForwardForwardDetails {
+        device: device.clone(),
+        train_examples,
+        test_examples,
+        model,
+        optimizer,
+        threshold: 32.0,
+        epochs: 20,
+        progress: 1,
+    };
+    Ok(details)
+}
@@ -20,7 +20,7 @@ pub fn load_mega_man_multi_head_attention(
     let file_path = "data/Mega_Man.txt";
     let max_chars = None;
     let max_number_of_examples = 100;
-    let mut tokenizer = Tokenizer::ascii_tokenizer();
+    let tokenizer = Tokenizer::ascii_tokenizer();
     let sequence_length = 32;
 
     let input_sequence_length = sequence_length;
@@ -32,7 +32,7 @@ pub fn load_mega_man_multi_head_attention(
         max_number_of_examples,
         input_sequence_length,
         output_sequence_length,
-        &mut tokenizer,
+        &tokenizer,
     )?;
 
     let vocab_size = tokenizer.vocab_size();
@@ -43,14 +43,18 @@ pub fn load_mega_man_multi_head_attention(
     let details = DatasetDetails {
         device: device.clone(),
         train_examples: examples,
+        validation_examples: vec![],
         test_examples: vec![],
         model,
         loss_operator,
         optimizer,
+        parameter_groups: vec![],
         epochs: 200,
         progress: 10,
+        early_stopping_patience: None,
+        restore_best_parameters: false,
         shuffle_examples: true,
-        clip_gradient_norm: true,
+        max_gradient_norm: Some(1.0),
         initial_metrics_min: Metrics {
             total_loss: 4000.0,
             total_next_token_perplexity: 100.0,
@@ -61,6 +65,8 @@ pub fn load_mega_man_multi_head_attention(
         },
         maximum_incorrect_predicted_next_tokens: 30,
         printer: NextTokenPredictionPrinter::new(tokenizer),
+        metrics_sinks: vec![],
+        callbacks: vec![],
         batch_size: 1,
     };
     Ok(details)
@@ -73,13 +79,13 @@ pub fn get_multi_head_attention_model_instructions(
     let model = details.model;
     let loss_operator = details.loss_operator;
     let optimizer = details.optimizer;
-    let clipped_gradient_norm = true;
     let program = NeuralProgram::try_new(
         device,
         &model,
         &loss_operator,
         &optimizer,
-        clipped_gradient_norm,
+        details.max_gradient_norm,
+        &details.parameter_groups,
     )?;
     let instructions = program.instructions;
     Ok(instructions)
@@ -6,18 +6,22 @@ use crate::{
     display::TensorPrinter,
     error, new_tensor, new_tensor_with_grad,
     tensor::{Error, ErrorEnum},
-    BinaryOperator, Device, Metrics, OptimizerTrait, TensorWithGrad, Tokenizer, TokenizerTrait,
-    UnaryModel,
+    BinaryOperator, Device, Metrics, MetricsSink, OptimizerTrait, ParameterGroup, TensorWithGrad,
+    Tokenizer, TokenizerTrait, TrainingCallback, UnaryModel,
 };
 
 pub mod addition_perceptron;
 pub mod arc_prize_2024;
+pub mod geoffroy_hinton_lstm;
 pub mod geoffroy_hinton_transformer;
 pub mod mega_man_attention_head;
 pub mod mega_man_linear;
 pub mod mega_man_multi_head_attention;
 pub mod simple;
 
+#[cfg(test)]
+mod tests;
+
 pub struct DatasetDetails<Model, LossOperator, Optimizer, Printer>
 where
     Model: UnaryModel,
@@ -27,19 +31,50 @@ where
 {
     pub device: Device,
     pub train_examples: Vec<(TensorWithGrad, TensorWithGrad)>,
+    pub validation_examples: Vec<(TensorWithGrad, TensorWithGrad)>,
     pub test_examples: Vec<(TensorWithGrad, TensorWithGrad)>,
     pub model: Model,
     pub loss_operator: LossOperator,
     pub optimizer: Optimizer,
+    pub parameter_groups: Vec<ParameterGroup>,
     pub batch_size: usize,
     pub shuffle_examples: bool,
-    pub clip_gradient_norm: bool,
+    pub max_gradient_norm: Option<f32>,
     pub epochs: usize,
     pub progress: usize,
+    /// Stop training when the validation loss did not improve
+    /// for this number of consecutive validations.
+    pub early_stopping_patience: Option<usize>,
+    /// Restore the parameters with the lowest validation loss at the end of training.
+    pub restore_best_parameters: bool,
     pub initial_metrics_min: Metrics,
     pub final_metrics_max: Metrics,
     pub maximum_incorrect_predicted_next_tokens: usize,
     pub printer: Printer,
+    pub metrics_sinks: Vec<Box<dyn MetricsSink>>,
+    pub callbacks: Vec<Box<dyn TrainingCallback>>,
+}
+
+/// Remove the last validation_ratio of the training examples and return them
+/// as validation examples.
+/// validation_ratio must be between 0 and 1.
+pub fn split_validation_examples(
+    train_examples: &mut Vec<(TensorWithGrad, TensorWithGrad)>,
+    validation_ratio: f32,
+) -> Result<Vec<(TensorWithGrad, TensorWithGrad)>, Error> {
+    if !(0.0..=1.0).contains(&validation_ratio) {
+        return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+    }
+    let validation_len = (train_examples.len() as f32 * validation_ratio) as usize;
+    let train_len = train_examples.len() - validation_len;
+    Ok(train_examples.split_off(train_len))
+}
+
+/// One row per token. The only column is the token id.
+/// See SparseEmbedding.
+pub fn into_token_id_rows(device: &Device, tokens: &[usize]) -> Result<TensorWithGrad, Error> {
+    let values = tokens.iter().map(|token| *token as f32).collect();
+    new_tensor_with_grad!(device, tokens.len(), 1, values, &[], false, false)
 }
 
 pub fn into_one_hot_encoded_rows(
@@ -77,8 +112,66 @@ pub fn load_examples(
     max_number_of_examples: usize,
     input_sequence_length: usize,
     output_sequence_length: usize,
-    tokenizer: &mut Tokenizer,
+    tokenizer: &Tokenizer,
 ) -> Result<Vec<(TensorWithGrad, TensorWithGrad)>, Error> {
+    let vocab_size = tokenizer.vocab_size();
+    load_token_sequences(
+        file_path,
+        max_chars,
+        max_number_of_examples,
+        input_sequence_length,
+        output_sequence_length,
+        tokenizer,
+    )?
+    .into_iter()
+    .map(|(input_tokens, output_tokens)| {
+        let one_hot_encoded_tokens = into_one_hot_encoded_rows(device, &input_tokens, vocab_size)?;
+        let output_multiclass = into_one_hot_encoded_rows(device, &output_tokens, vocab_size)?;
+        Ok((one_hot_encoded_tokens, output_multiclass))
+    })
+    .collect()
+}
+
+/// Like load_examples, but the inputs are token ids, see into_token_id_rows.
+pub fn load_token_id_examples(
+    device: &Device,
+    file_path: &str,
+    max_chars: Option<usize>,
+    max_number_of_examples: usize,
+    input_sequence_length: usize,
+    output_sequence_length: usize,
+    tokenizer: &Tokenizer,
+) -> Result<Vec<(TensorWithGrad, TensorWithGrad)>, Error> {
+    let vocab_size = tokenizer.vocab_size();
+    load_token_sequences(
+        file_path,
+        max_chars,
+        max_number_of_examples,
+        input_sequence_length,
+        output_sequence_length,
+        tokenizer,
+    )?
+    .into_iter()
+    .map(|(input_tokens, output_tokens)| {
+        let token_ids = into_token_id_rows(device, &input_tokens)?;
+        let output_multiclass = into_one_hot_encoded_rows(device, &output_tokens, vocab_size)?;
+        Ok((token_ids, output_multiclass))
+    })
+    .collect()
+}
+
+/// Input tokens and output tokens of an example.
+type TokenSequences = (Vec<usize>, Vec<usize>);
+
+/// The output of an example is its input shifted by one token.
+fn load_token_sequences(
+    file_path: &str,
+    max_chars: Option<usize>,
+    max_number_of_examples: usize,
+    input_sequence_length: usize,
+    output_sequence_length: usize,
+    tokenizer: &Tokenizer,
+) -> Result<Vec<TokenSequences>, Error> {
     let mut examples = Vec::new();
     let mut text =
         fs::read_to_string(file_path).map_err(|_| error!(ErrorEnum::IncompatibleTensorShapes))?;
@@ -86,23 +179,16 @@ pub fn load_examples(
         text = text[0..max_chars].to_owned();
     }
     let tokens: Vec<usize> = tokenizer.encode(&text);
-    let vocab_size = tokenizer.vocab_size();
     let mut i = 0;
     while i + input_sequence_length < tokens.len() && examples.len() < max_number_of_examples {
         let input_begin = i;
         let input_end = input_begin + input_sequence_length;
-        let input_tokens = &tokens[input_begin..input_end];
-        let one_hot_encoded_tokens = into_one_hot_encoded_rows(device, input_tokens, vocab_size)?;
+        let input_tokens = tokens[input_begin..input_end].to_vec();
         let output_begin = input_begin + 1;
         let output_end = output_begin + output_sequence_length;
-        let output_tokens = &tokens[output_begin..output_end];
-        let output_multiclass = into_one_hot_encoded_rows(device, output_tokens, vocab_size)?;
+        let output_tokens = tokens[output_begin..output_end].to_vec();
 
-        examples.push((
-            //
-            one_hot_encoded_tokens, //
-            output_multiclass,
-        ));
+        examples.push((input_tokens, output_tokens));
         i += 1;
     }
     Ok(examples)
@@ -11,7 +11,7 @@ use super::{into_one_hot_encoded_rows, DatasetDetails};
 
 fn load_examples(
     device: &Device,
-    tokenizer: &mut Tokenizer,
+    tokenizer: &Tokenizer,
 ) -> Result<Vec<(TensorWithGrad, TensorWithGrad)>, Error> {
     let examples: Vec<_> = ["quizzed", "fuzzing"]
         .iter()
@@ -52,25 +52,29 @@ pub fn load_simple(
     >,
     Error,
 > {
-    let mut tokenizer = Tokenizer::ascii_tokenizer();
+    let tokenizer = Tokenizer::ascii_tokenizer();
     let sequence_length = 6;
-    let examples = load_examples(device, &mut tokenizer)?;
+    let examples = load_examples(device, &tokenizer)?;
 
     let loss_operator = SoftmaxCrossEntropyLoss::new(device);
     let vocab_size = tokenizer.vocab_size();
     let model = SimpleModel::new(device, sequence_length, vocab_size)?;
-    let optimizer = GradientDescent::new(0.5);
+    let optimizer = GradientDescent::new(2.0);
     let details = DatasetDetails {
         device: device.clone(),
         train_examples: examples,
+        validation_examples: vec![],
         test_examples: vec![],
         model,
         loss_operator,
         optimizer,
+        parameter_groups: vec![],
         epochs: 500,
         progress: 100,
+        early_stopping_patience: None,
+        restore_best_parameters: false,
         shuffle_examples: true,
-        clip_gradient_norm: true,
+        max_gradient_norm: Some(1.0),
         initial_metrics_min: Metrics {
             total_loss: 5.0,
             total_next_token_perplexity: 200.0,
@@ -81,6 +85,8 @@ pub fn load_simple(
         },
         maximum_incorrect_predicted_next_tokens: 0,
         printer: NextTokenPredictionPrinter::new(tokenizer),
+        metrics_sinks: vec![],
+        callbacks: vec![],
         batch_size: 1,
     };
     Ok(details)
@@ -0,0 +1,83 @@
+use std::fs;
+
+use crate::{
+    datasets::{mega_man_linear::load_mega_man_linear_forward_forward, split_validation_examples},
+    get_row_argmax, new_tensor_with_grad, Device, TensorWithGrad, Tokenizer, TokenizerTrait,
+};
+
+fn examples(device: &Device, len: usize) -> Vec<(TensorWithGrad, TensorWithGrad)> {
+    (0..len)
+        .map(|i| {
+            let input = new_tensor_with_grad!(device, 1, 1, vec![i as f32], &[], false, false);
+            let output = new_tensor_with_grad!(device, 1, 1, vec![i as f32], &[], false, false);
+            (input.unwrap(), output.unwrap())
+        })
+        .collect()
+}
+
+#[test]
+fn split_the_last_examples() {
+    let device = Device::default();
+    let mut train_examples = examples(&device, 10);
+    let validation_examples = split_validation_examples(&mut train_examples, 0.2).unwrap();
+    assert_eq!(train_examples.len(), 8);
+    let values: Vec<f32> = validation_examples
+        .iter()
+        .map(|(input, _)| input.tensor().get_values().unwrap()[0])
+        .collect();
+    assert_eq!(values, vec![8.0, 9.0]);
+
+    let mut train_examples = examples(&device, 10);
+    let validation_examples = split_validation_examples(&mut train_examples, 1.0).unwrap();
+    assert_eq!(train_examples.len(), 0);
+    assert_eq!(validation_examples.len(), 10);
+}
+
+#[test]
+fn reject_incorrect_validation_ratios() {
+    let device = Device::default();
+    for validation_ratio in [-0.1, 1.5, f32::NAN] {
+        let mut train_examples = examples(&device, 10);
+        assert!(split_validation_examples(&mut train_examples, validation_ratio).is_err());
+        assert_eq!(train_examples.len(), 10);
+    }
+}
+
+#[test]
+fn forward_forward_labels_follow_the_input() {
+    let device = Device::default();
+    let details = load_mega_man_linear_forward_forward(&device).unwrap();
+    let text = fs::read_to_string("data/Mega_Man.txt").unwrap();
+    let tokens = Tokenizer::ascii_tokenizer().encode(&text);
+    let sequence_length = 8;
+    let tokens_of = |examples: &[(TensorWithGrad, TensorWithGrad)]| -> Vec<(Vec<usize>, usize)> {
+        examples
+            .iter()
+            .map(|(input, expected_output)| {
+                let input = &input.tensor();
+                let input_tokens = (0..input.rows())
+                    .map(|row| get_row_argmax(input, row).unwrap())
+                    .collect();
+                let label = get_row_argmax(&expected_output.tensor(), 0).unwrap();
+                (input_tokens, label)
+            })
+            .collect()
+    };
+
+    // The label is the token after the input.
+    let train_examples = tokens_of(&details.train_examples);
+    for (i, (input_tokens, label)) in train_examples.iter().enumerate() {
+        assert_eq!(input_tokens, &tokens[i..i + sequence_length]);
+        assert_eq!(*label, tokens[i + sequence_length]);
+    }
+
+    // The test examples are held out: none of their tokens is in a train example.
+    let first_test_token = train_examples.len() + sequence_length;
+    let test_examples = tokens_of(&details.test_examples);
+    assert!(!test_examples.is_empty());
+    for (i, (input_tokens, label)) in test_examples.iter().enumerate() {
+        let begin = first_test_token + i;
+        assert_eq!(input_tokens, &tokens[begin..begin + sequence_length]);
+        assert_eq!(*label, tokens[begin + sequence_length]);
+    }
+}
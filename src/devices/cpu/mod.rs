@@ -1,14 +1,24 @@
-use std::f32::consts::E;
+use std::{
+    collections::HashMap,
+    f32::consts::E,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
 pub mod slice;
 use cblas::{Layout, Transpose};
-use rand::{distributions::Uniform, thread_rng, Rng};
 extern crate cblas_sys as ffi;
 use crate::{
+    analysis::{arg_max::ArgReduce, top_k::TopKAttributes},
+    elementwise::ActivationFunction,
     error,
+    gather::{gather, scatter_add},
     slice::DeviceSlice,
+    statistics::normalization::NormalizationAttributes,
     stream::{DeviceStream, DeviceStreamEnum},
     tensor::{Error, ErrorEnum, Tensor},
-    EPSILON,
+    GatingAttributes, LossFunction, SlidingWindow, SparseCrossEntropyAttributes, EPSILON,
 };
 
 use self::slice::CpuDevSlice;
@@ -20,7 +30,28 @@ extern crate blas_src;
 mod tests;
 
 #[derive(Debug, Default)]
-pub struct CpuDevice {}
+pub struct CpuDevice {
+    /// Seed of the counter-based random number generator of Bernoulli.
+    seed: AtomicU64,
+    /// Number of Bernoulli trials drawn for each output tensor.
+    /// The trials of a tensor do not depend on the order in which the streams are executed.
+    bernoulli_counters: Mutex<HashMap<usize, u64>>,
+}
+
+/// SplitMix64
+/// See https://prng.di.unimi.it/splitmix64.c
+fn split_mix_64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// Uniform value in [0, 1) that only depends on the seed, the key and the counter.
+fn counter_based_uniform(seed: u64, key: u64, counter: u64) -> f32 {
+    let bits = split_mix_64(split_mix_64(seed ^ split_mix_64(key)).wrapping_add(counter));
+    (bits >> 40) as f32 / (1u64 << 24) as f32
+}
 
 impl DeviceTrait for CpuDevice {
     fn gemm(
@@ -183,6 +214,7 @@ impl DeviceTrait for CpuDevice {
 
     fn standardization(
         &self,
+        epsilon: f32,
         input: &Tensor,
         output: &Tensor,
         _device_stream: &DeviceStream,
@@ -224,7 +256,7 @@ impl DeviceTrait for CpuDevice {
                 let x = unsafe { *input.add(row * cols + col) };
                 debug_assert!(!x.is_nan());
                 debug_assert_ne!(0.0, sum);
-                let y = (x - mean) / (stddev + EPSILON);
+                let y = (x - mean) / (stddev + epsilon);
                 debug_assert!(!y.is_nan());
                 unsafe { *output.add(row * cols + col) = y };
                 col += 1;
@@ -457,6 +489,106 @@ impl DeviceTrait for CpuDevice {
         Ok(())
     }
 
+    fn sparse_cross_entropy_loss(
+        &self,
+        attributes: &SparseCrossEntropyAttributes,
+        targets: &Tensor,
+        actual: &Tensor,
+        loss: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let classes = actual.cols();
+        let targets = unsafe { std::slice::from_raw_parts(targets.as_ptr(), targets.len()) };
+        let actual = unsafe { std::slice::from_raw_parts(actual.as_ptr(), actual.len()) };
+        let loss = unsafe { std::slice::from_raw_parts_mut(loss.as_mut_ptr(), loss.len()) };
+        attributes.loss(classes, targets, actual, loss)
+    }
+
+    fn sparse_cross_entropy_loss_gradient(
+        &self,
+        attributes: &SparseCrossEntropyAttributes,
+        targets: &Tensor,
+        actual: &Tensor,
+        actual_gradient: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let classes = actual.cols();
+        let targets = unsafe { std::slice::from_raw_parts(targets.as_ptr(), targets.len()) };
+        let actual = unsafe { std::slice::from_raw_parts(actual.as_ptr(), actual.len()) };
+        let actual_gradient = unsafe {
+            std::slice::from_raw_parts_mut(actual_gradient.as_mut_ptr(), actual_gradient.len())
+        };
+        attributes.loss_gradient(classes, targets, actual, actual_gradient)
+    }
+
+    fn loss(
+        &self,
+        function: &LossFunction,
+        expected: &Tensor,
+        actual: &Tensor,
+        loss: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cols = actual.cols();
+        let expected = unsafe { std::slice::from_raw_parts(expected.as_ptr(), expected.len()) };
+        let actual = unsafe { std::slice::from_raw_parts(actual.as_ptr(), actual.len()) };
+        let loss = unsafe { std::slice::from_raw_parts_mut(loss.as_mut_ptr(), loss.len()) };
+        loss[0] = function.loss(cols, expected, actual);
+        Ok(())
+    }
+
+    fn loss_gradient(
+        &self,
+        function: &LossFunction,
+        expected: &Tensor,
+        actual: &Tensor,
+        actual_gradient: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cols = actual.cols();
+        let expected = unsafe { std::slice::from_raw_parts(expected.as_ptr(), expected.len()) };
+        let actual = unsafe { std::slice::from_raw_parts(actual.as_ptr(), actual.len()) };
+        let actual_gradient = unsafe {
+            std::slice::from_raw_parts_mut(actual_gradient.as_mut_ptr(), actual_gradient.len())
+        };
+        function.loss_gradient(cols, expected, actual, actual_gradient);
+        Ok(())
+    }
+
+    fn top_k_gating(
+        &self,
+        attributes: &GatingAttributes,
+        logits: &Tensor,
+        gates: &Tensor,
+        aux_loss: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let experts = logits.cols();
+        let logits = unsafe { std::slice::from_raw_parts(logits.as_ptr(), logits.len()) };
+        let gates = unsafe { std::slice::from_raw_parts_mut(gates.as_mut_ptr(), gates.len()) };
+        let aux_loss =
+            unsafe { std::slice::from_raw_parts_mut(aux_loss.as_mut_ptr(), aux_loss.len()) };
+        attributes.gating(experts, logits, gates, aux_loss)
+    }
+
+    fn top_k_gating_gradient(
+        &self,
+        attributes: &GatingAttributes,
+        logits: &Tensor,
+        gates_gradient: &Tensor,
+        logits_gradient: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let experts = logits.cols();
+        let logits = unsafe { std::slice::from_raw_parts(logits.as_ptr(), logits.len()) };
+        let gates_gradient =
+            unsafe { std::slice::from_raw_parts(gates_gradient.as_ptr(), gates_gradient.len()) };
+        let logits_gradient = unsafe {
+            std::slice::from_raw_parts_mut(logits_gradient.as_mut_ptr(), logits_gradient.len())
+        };
+        attributes.gating_gradient(experts, logits, gates_gradient, logits_gradient)
+    }
+
     fn reduce_sum_square(
         &self,
         expected: &Tensor,
@@ -506,6 +638,271 @@ impl DeviceTrait for CpuDevice {
         Ok(())
     }
 
+    fn arg_max(
+        &self,
+        attributes: &ArgReduce,
+        input: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let rows = input.rows();
+        let cols = input.cols();
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        attributes.arg_max(rows, cols, input, output);
+        Ok(())
+    }
+
+    fn arg_min(
+        &self,
+        attributes: &ArgReduce,
+        input: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let rows = input.rows();
+        let cols = input.cols();
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        attributes.arg_min(rows, cols, input, output);
+        Ok(())
+    }
+
+    fn top_k(
+        &self,
+        attributes: &TopKAttributes,
+        input: &Tensor,
+        values: &Tensor,
+        indices: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let rows = input.rows();
+        let cols = input.cols();
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let values = unsafe { std::slice::from_raw_parts_mut(values.as_mut_ptr(), values.len()) };
+        let indices =
+            unsafe { std::slice::from_raw_parts_mut(indices.as_mut_ptr(), indices.len()) };
+        attributes.top_k(rows, cols, input, values, indices);
+        Ok(())
+    }
+
+    fn im2col(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        window.im2col(input, output);
+        Ok(())
+    }
+
+    fn col2im(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        window.col2im(input, output);
+        Ok(())
+    }
+
+    fn max_pool(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        window.max_pool(input, output);
+        Ok(())
+    }
+
+    fn max_pool_gradient(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output_gradient =
+            unsafe { std::slice::from_raw_parts(output_gradient.as_ptr(), output_gradient.len()) };
+        let input_gradient = unsafe {
+            std::slice::from_raw_parts_mut(input_gradient.as_mut_ptr(), input_gradient.len())
+        };
+        window.max_pool_gradient(input, output_gradient, input_gradient);
+        Ok(())
+    }
+
+    fn average_pool(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        window.average_pool(input, output);
+        Ok(())
+    }
+
+    fn average_pool_gradient(
+        &self,
+        window: &SlidingWindow,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let output_gradient =
+            unsafe { std::slice::from_raw_parts(output_gradient.as_ptr(), output_gradient.len()) };
+        let input_gradient = unsafe {
+            std::slice::from_raw_parts_mut(input_gradient.as_mut_ptr(), input_gradient.len())
+        };
+        window.average_pool_gradient(output_gradient, input_gradient);
+        Ok(())
+    }
+
+    fn rms_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cols = input.cols();
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        attributes.rms_standardization(cols, input, output);
+        Ok(())
+    }
+
+    fn rms_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cols = input.cols();
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output_gradient =
+            unsafe { std::slice::from_raw_parts(output_gradient.as_ptr(), output_gradient.len()) };
+        let input_gradient = unsafe {
+            std::slice::from_raw_parts_mut(input_gradient.as_mut_ptr(), input_gradient.len())
+        };
+        attributes.rms_standardization_gradient(cols, input, output_gradient, input_gradient);
+        Ok(())
+    }
+
+    fn group_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cols = input.cols();
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        attributes.group_standardization(cols, input, output);
+        Ok(())
+    }
+
+    fn group_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cols = input.cols();
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output_gradient =
+            unsafe { std::slice::from_raw_parts(output_gradient.as_ptr(), output_gradient.len()) };
+        let input_gradient = unsafe {
+            std::slice::from_raw_parts_mut(input_gradient.as_mut_ptr(), input_gradient.len())
+        };
+        attributes.group_standardization_gradient(cols, input, output_gradient, input_gradient);
+        Ok(())
+    }
+
+    fn batch_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        training: &Tensor,
+        input: &Tensor,
+        running_mean: &Tensor,
+        running_var: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let rows = input.rows();
+        let cols = input.cols();
+        let training = unsafe { *training.as_ptr() } != 0.0;
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let running_mean = unsafe {
+            std::slice::from_raw_parts_mut(running_mean.as_mut_ptr(), running_mean.len())
+        };
+        let running_var =
+            unsafe { std::slice::from_raw_parts_mut(running_var.as_mut_ptr(), running_var.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        attributes.batch_standardization(
+            rows,
+            cols,
+            training,
+            input,
+            running_mean,
+            running_var,
+            output,
+        );
+        Ok(())
+    }
+
+    fn batch_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        training: &Tensor,
+        input: &Tensor,
+        running_var: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let rows = input.rows();
+        let cols = input.cols();
+        let training = unsafe { *training.as_ptr() } != 0.0;
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let running_var =
+            unsafe { std::slice::from_raw_parts(running_var.as_ptr(), running_var.len()) };
+        let output_gradient =
+            unsafe { std::slice::from_raw_parts(output_gradient.as_ptr(), output_gradient.len()) };
+        let input_gradient = unsafe {
+            std::slice::from_raw_parts_mut(input_gradient.as_mut_ptr(), input_gradient.len())
+        };
+        attributes.batch_standardization_gradient(
+            rows,
+            cols,
+            training,
+            input,
+            running_var,
+            output_gradient,
+            input_gradient,
+        );
+        Ok(())
+    }
+
     fn bernoulli(
         &self,
         input: &Tensor,
@@ -514,17 +911,25 @@ impl DeviceTrait for CpuDevice {
     ) -> Result<(), Error> {
         let len = input.len();
         let output_ptr = output.as_mut_ptr();
-        let mut rng = thread_rng();
-        let uniform = Uniform::new(0.0, 1.0);
+        let seed = self.seed.load(Ordering::Relaxed);
+        let key = output.name() as u64;
+        let offset = {
+            let mut counters = self.bernoulli_counters.lock().unwrap();
+            let counter = counters.entry(output.name()).or_default();
+            let offset = *counter;
+            *counter += len as u64;
+            offset
+        };
 
         let input_ptr = input.as_ptr();
         for i in 0..len {
             let probability = unsafe { *input_ptr.add(i) };
-            let random_number = if rng.sample(uniform) <= probability {
-                1.0
-            } else {
-                0.0
-            };
+            let random_number =
+                if counter_based_uniform(seed, key, offset + i as u64) <= probability {
+                    1.0
+                } else {
+                    0.0
+                };
             unsafe { *output_ptr.add(i) = random_number };
         }
         Ok(())
@@ -534,6 +939,11 @@ impl DeviceTrait for CpuDevice {
         Ok(DeviceStreamEnum::CpuDeviceStream)
     }
 
+    fn set_bernoulli_seed(&self, seed: u64) {
+        self.seed.store(seed, Ordering::Relaxed);
+        self.bernoulli_counters.lock().unwrap().clear();
+    }
+
     fn min(
         &self,
         input1: &Tensor,
@@ -618,6 +1028,60 @@ impl DeviceTrait for CpuDevice {
 
         Ok(())
     }
+
+    fn activation(
+        &self,
+        function: &ActivationFunction,
+        input: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        function.activation(input, output);
+        Ok(())
+    }
+
+    fn activation_derivative(
+        &self,
+        function: &ActivationFunction,
+        input: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let input = unsafe { std::slice::from_raw_parts(input.as_ptr(), input.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        function.activation_derivative(input, output);
+        Ok(())
+    }
+
+    fn gather(
+        &self,
+        data: &Tensor,
+        indices: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cols = data.cols();
+        let data = unsafe { std::slice::from_raw_parts(data.as_ptr(), data.len()) };
+        let indices = unsafe { std::slice::from_raw_parts(indices.as_ptr(), indices.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        gather(cols, data, indices, output)
+    }
+
+    fn scatter_add(
+        &self,
+        indices: &Tensor,
+        updates: &Tensor,
+        output: &Tensor,
+        _device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cols = output.cols();
+        let indices = unsafe { std::slice::from_raw_parts(indices.as_ptr(), indices.len()) };
+        let updates = unsafe { std::slice::from_raw_parts(updates.as_ptr(), updates.len()) };
+        let output = unsafe { std::slice::from_raw_parts_mut(output.as_mut_ptr(), output.len()) };
+        scatter_add(cols, indices, updates, output)
+    }
 }
 
 impl CpuDevice {
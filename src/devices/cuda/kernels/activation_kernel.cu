@@ -0,0 +1,94 @@
+// function is the code of ActivationFunction.
+// alpha is the parameter of LeakyRelu and Elu.
+
+__device__ float sigmoid(float x)
+{
+    return 1.0 / (1.0 + expf(-x));
+}
+
+__device__ float gelu_tanh_inner(float x)
+{
+    return sqrtf(2.0 / M_PI) * (x + 0.044715 * x * x * x);
+}
+
+extern "C" __global__ void activation_kernel(float *input, float *output, int n, int function, float alpha)
+{
+    int i = blockIdx.x * blockDim.x + threadIdx.x;
+    if (i >= n)
+    {
+        return;
+    }
+    float x = input[i];
+    float y = 0.0;
+    switch (function)
+    {
+    case 0: // Relu
+        y = fmaxf(x, 0.0);
+        break;
+    case 1: // LeakyRelu
+        y = x >= 0.0 ? x : alpha * x;
+        break;
+    case 2: // Tanh
+        y = tanhf(x);
+        break;
+    case 3: // Silu
+        y = x * sigmoid(x);
+        break;
+    case 4: // Elu
+        y = x > 0.0 ? x : alpha * (expf(x) - 1.0);
+        break;
+    case 5: // Softplus
+        y = fmaxf(x, 0.0) + log1pf(expf(-fabsf(x)));
+        break;
+    case 6: // GeluTanh
+        y = 0.5 * x * (1.0 + tanhf(gelu_tanh_inner(x)));
+        break;
+    }
+    output[i] = y;
+}
+
+extern "C" __global__ void activation_derivative_kernel(float *input, float *output, int n, int function, float alpha)
+{
+    int i = blockIdx.x * blockDim.x + threadIdx.x;
+    if (i >= n)
+    {
+        return;
+    }
+    float x = input[i];
+    float y = 0.0;
+    switch (function)
+    {
+    case 0: // Relu
+        y = x > 0.0 ? 1.0 : 0.0;
+        break;
+    case 1: // LeakyRelu
+        y = x >= 0.0 ? 1.0 : alpha;
+        break;
+    case 2: // Tanh
+    {
+        float t = tanhf(x);
+        y = 1.0 - t * t;
+        break;
+    }
+    case 3: // Silu
+    {
+        float s = sigmoid(x);
+        y = s * (1.0 + x * (1.0 - s));
+        break;
+    }
+    case 4: // Elu
+        y = x > 0.0 ? 1.0 : alpha * expf(x);
+        break;
+    case 5: // Softplus
+        y = sigmoid(x);
+        break;
+    case 6: // GeluTanh
+    {
+        float t = tanhf(gelu_tanh_inner(x));
+        float inner_derivative = sqrtf(2.0 / M_PI) * (1.0 + 3.0 * 0.044715 * x * x);
+        y = 0.5 * (1.0 + t) + 0.5 * x * (1.0 - t * t) * inner_derivative;
+        break;
+    }
+    }
+    output[i] = y;
+}
@@ -0,0 +1,38 @@
+// One thread per reduced line.
+// The output is the index of the best value of the line.
+__device__ void arg_reduce(const float *input, float *output, int rows, int cols, int axis, int select_last_index, int largest)
+{
+    int line = blockIdx.x * blockDim.x + threadIdx.x;
+    int lines = axis == 0 ? cols : rows;
+    if (line >= lines)
+    {
+        return;
+    }
+    int len = axis == 0 ? rows : cols;
+    int stride = axis == 0 ? cols : 1;
+    int start = axis == 0 ? line : line * cols;
+
+    int best = 0;
+    float best_value = input[start];
+    for (int i = 1; i < len; i++)
+    {
+        float value = input[start + i * stride];
+        int is_better = largest ? value > best_value : value < best_value;
+        if (is_better || (select_last_index && value == best_value))
+        {
+            best = i;
+            best_value = value;
+        }
+    }
+    output[line] = (float)best;
+}
+
+extern "C" __global__ void arg_max_kernel(const float *input, float *output, int rows, int cols, int axis, int select_last_index)
+{
+    arg_reduce(input, output, rows, cols, axis, select_last_index, 1);
+}
+
+extern "C" __global__ void arg_min_kernel(const float *input, float *output, int rows, int cols, int axis, int select_last_index)
+{
+    arg_reduce(input, output, rows, cols, axis, select_last_index, 0);
+}
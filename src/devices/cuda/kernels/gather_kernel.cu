@@ -0,0 +1,37 @@
+// indices are stored as float. The host rejects indices that are out of bounds before the launch,
+// like the CPU, so the bounds checks below only protect the memory.
+
+extern "C" __global__ void gather_kernel(float *data, float *indices, float *output, int data_rows, int rows, int cols)
+{
+    int idx = blockIdx.x * blockDim.x + threadIdx.x;
+    if (idx >= rows * cols)
+    {
+        return;
+    }
+    int row = idx / cols;
+    int col = idx % cols;
+    int data_row = (int)indices[row];
+    if (data_row < 0 || data_row >= data_rows)
+    {
+        return;
+    }
+    output[idx] = data[data_row * cols + col];
+}
+
+extern "C" __global__ void scatter_add_kernel(float *indices, float *updates, float *output, int output_rows, int rows, int cols)
+{
+    int idx = blockIdx.x * blockDim.x + threadIdx.x;
+    if (idx >= rows * cols)
+    {
+        return;
+    }
+    int row = idx / cols;
+    int col = idx % cols;
+    int output_row = (int)indices[row];
+    if (output_row < 0 || output_row >= output_rows)
+    {
+        return;
+    }
+    // Several rows can have the same index.
+    atomicAdd(&output[output_row * cols + col], updates[idx]);
+}
@@ -1,9 +1,6 @@
 // TODO use a smarter reduce instead of doing the same reduction in every thread !
-// TODO pass EPSILON as argument.
-extern "C" __global__ void standardization_kernel(float *input, float *output, int rows, int cols)
+extern "C" __global__ void standardization_kernel(float *input, float *output, int rows, int cols, float epsilon)
 {
-    const float EPSILON = 1e-8;
-
     int idx = blockIdx.x * blockDim.x + threadIdx.x;
     int n = rows * cols;
     if (idx >= n)
@@ -30,5 +27,5 @@ extern "C" __global__ void standardization_kernel(float *input, float *output, i
     float row_stddev = sqrt(row_diff_sum / cols);
 
     float x = input[row * cols + col];
-    output[row * cols + col] = (x - row_mean) / (row_stddev + EPSILON);
+    output[row * cols + col] = (x - row_mean) / (row_stddev + epsilon);
 }
@@ -0,0 +1,51 @@
+// One thread per line.
+// The element of rank r is the best element that comes after the element of rank r - 1
+// in the order (value, index).
+// TODO use a smarter selection for large k.
+extern "C" __global__ void top_k_kernel(const float *input, float *values, float *indices, int rows, int cols, int axis, int k, int largest)
+{
+    int line = blockIdx.x * blockDim.x + threadIdx.x;
+    int lines = axis == 0 ? cols : rows;
+    if (line >= lines)
+    {
+        return;
+    }
+    int len = axis == 0 ? rows : cols;
+    int stride = axis == 0 ? cols : 1;
+    int start = axis == 0 ? line : line * cols;
+    int output_line_stride = axis == 0 ? 1 : k;
+    int output_stride = axis == 0 ? cols : 1;
+
+    int previous = -1;
+    float previous_value = 0.0f;
+    for (int rank = 0; rank < k; rank++)
+    {
+        int best = -1;
+        float best_value = 0.0f;
+        for (int i = 0; i < len; i++)
+        {
+            float value = input[start + i * stride];
+            if (previous >= 0)
+            {
+                int after_previous = largest
+                                         ? (value < previous_value || (value == previous_value && i > previous))
+                                         : (value > previous_value || (value == previous_value && i > previous));
+                if (!after_previous)
+                {
+                    continue;
+                }
+            }
+            int is_better = largest ? value > best_value : value < best_value;
+            if (best < 0 || is_better)
+            {
+                best = i;
+                best_value = value;
+            }
+        }
+        int output_index = line * output_line_stride + rank * output_stride;
+        values[output_index] = best_value;
+        indices[output_index] = (float)best;
+        previous = best;
+        previous_value = best_value;
+    }
+}
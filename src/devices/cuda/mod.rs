@@ -1,4 +1,11 @@
-use std::{fs::File, io::Read, sync::Arc};
+use std::{
+    fs::File,
+    io::Read,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 pub mod slice;
 pub mod stream;
 #[cfg(test)]
@@ -14,11 +21,16 @@ use cudarc::{
 use stream::CudaDeviceStream;
 
 use crate::{
+    analysis::{arg_max::ArgReduce, top_k::TopKAttributes},
+    elementwise::ActivationFunction,
     error,
+    gather::check_indices,
     slice::DeviceSlice,
+    statistics::normalization::NormalizationAttributes,
     stream::{DeviceStream, DeviceStreamEnum},
     tensor::{Error, ErrorEnum, Tensor},
-    DeviceTrait, EPSILON,
+    DeviceTrait, GatingAttributes, LossFunction, SlidingWindow, SparseCrossEntropyAttributes,
+    EPSILON,
 };
 
 use self::slice::CudaDevSlice;
@@ -26,8 +38,12 @@ use self::slice::CudaDevSlice;
 #[derive(Debug)]
 pub struct CudaDev {
     pub dev: Arc<CudaDevice>,
+    /// Initial RNG state of the Bernoulli trials of the streams created from now on.
+    bernoulli_seed: AtomicU64,
 }
 
+const DEFAULT_BERNOULLI_SEED: u64 = 1337;
+
 impl CudaDev {
     pub fn try_default() -> Result<CudaDev, Error> {
         let dev = CudaDevice::new(0);
@@ -106,6 +122,93 @@ impl CudaDev {
         }
     }
 
+    fn launch_activation_kernel(
+        &self,
+        func_name: &str,
+        function: &ActivationFunction,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cuda_stream = get_cuda_stream(device_stream)?;
+        let kernel = self.get_func("activation_kernel_module", func_name)?;
+        let n = input.len();
+        let cfg = LaunchConfig::for_num_elems(n as u32);
+        let code = function.code();
+        let alpha = function.alpha();
+        let input = &input.device_slice().buffer;
+        let output = &output.device_slice().buffer;
+        match (input, output) {
+            (DeviceSlice::CudaDevSlice(input), DeviceSlice::CudaDevSlice(output)) => {
+                let result = unsafe {
+                    kernel.launch_on_stream(
+                        cuda_stream,
+                        cfg,
+                        (input.slice(), output.slice(), n, code, alpha),
+                    )
+                };
+                match result {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(error!(ErrorEnum::NvLaunchError)),
+                }
+            }
+            _ => Err(error!(ErrorEnum::NvLaunchError)),
+        }
+    }
+
+    /// Launch gather_kernel(data, indices, output) or scatter_add_kernel(indices, updates, output).
+    /// The kernels have one thread per element of the indexed rows.
+    fn launch_gather_kernel(
+        &self,
+        func_name: &str,
+        input_0: &Tensor,
+        input_1: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cuda_stream = get_cuda_stream(device_stream)?;
+        let kernel = self.get_func("gather_kernel_module", func_name)?;
+        // The table is data for gather_kernel and output for scatter_add_kernel.
+        let (table, rows) = match func_name {
+            "gather_kernel" => (input_0, output.rows()),
+            _ => (output, input_0.rows()),
+        };
+        let table_rows = table.rows() as i32;
+        let rows = rows as i32;
+        let cols = table.cols() as i32;
+        let cfg = LaunchConfig::for_num_elems((rows * cols) as u32);
+        let input_0 = &input_0.device_slice().buffer;
+        let input_1 = &input_1.device_slice().buffer;
+        let output = &output.device_slice().buffer;
+        match (input_0, input_1, output) {
+            (
+                DeviceSlice::CudaDevSlice(input_0),
+                DeviceSlice::CudaDevSlice(input_1),
+                DeviceSlice::CudaDevSlice(output),
+            ) => {
+                let result = unsafe {
+                    kernel.launch_on_stream(
+                        cuda_stream,
+                        cfg,
+                        (
+                            input_0.slice(),
+                            input_1.slice(),
+                            output.slice(),
+                            table_rows,
+                            rows,
+                            cols,
+                        ),
+                    )
+                };
+                match result {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(error!(ErrorEnum::NvLaunchError)),
+                }
+            }
+            _ => Err(error!(ErrorEnum::NvLaunchError)),
+        }
+    }
+
     fn launch_axis_kernel(
         &self,
         module_name: &str,
@@ -140,8 +243,54 @@ impl CudaDev {
         }
     }
 
+    fn launch_arg_reduce_kernel(
+        &self,
+        func_name: &str,
+        attributes: &ArgReduce,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cuda_stream = get_cuda_stream(device_stream)?;
+        let kernel = self.get_func("arg_max_kernel_module", func_name)?;
+        let rows = input.rows() as i32;
+        let cols = input.cols() as i32;
+        let lines = if attributes.axis == 0 { cols } else { rows };
+        let cfg = LaunchConfig::for_num_elems(lines as u32);
+        let axis = attributes.axis as i32;
+        let select_last_index = attributes.select_last_index as i32;
+        let input = &input.device_slice().buffer;
+        let output = &output.device_slice().buffer;
+        match (input, output) {
+            (DeviceSlice::CudaDevSlice(input), DeviceSlice::CudaDevSlice(output)) => {
+                let result = unsafe {
+                    kernel.launch_on_stream(
+                        cuda_stream,
+                        cfg,
+                        (
+                            input.slice(),
+                            output.slice(),
+                            rows,
+                            cols,
+                            axis,
+                            select_last_index,
+                        ),
+                    )
+                };
+                match result {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(error!(ErrorEnum::NvLaunchError)),
+                }
+            }
+            _ => Err(error!(ErrorEnum::NvLaunchError)),
+        }
+    }
+
     pub fn try_new(dev: Arc<driver::CudaDevice>) -> Result<Self, Error> {
-        let device = CudaDev { dev };
+        let device = CudaDev {
+            dev,
+            bernoulli_seed: AtomicU64::new(DEFAULT_BERNOULLI_SEED),
+        };
 
         device.load_module(
             "sin_kernel_module",
@@ -215,6 +364,18 @@ impl CudaDev {
             "./src/devices/cuda/kernels/gelu_kernel.cu",
         )?;
 
+        device.load_module(
+            "gather_kernel_module",
+            &["gather_kernel", "scatter_add_kernel"],
+            "./src/devices/cuda/kernels/gather_kernel.cu",
+        )?;
+
+        device.load_module(
+            "activation_kernel_module",
+            &["activation_kernel", "activation_derivative_kernel"],
+            "./src/devices/cuda/kernels/activation_kernel.cu",
+        )?;
+
         device.load_module(
             "gelu_derivative_kernel_module",
             &["gelu_derivative_kernel"],
@@ -245,6 +406,18 @@ impl CudaDev {
             "./src/devices/cuda/kernels/softmax_kernel.cu",
         )?;
 
+        device.load_module(
+            "arg_max_kernel_module",
+            &["arg_max_kernel", "arg_min_kernel"],
+            "./src/devices/cuda/kernels/arg_max_kernel.cu",
+        )?;
+
+        device.load_module(
+            "top_k_kernel_module",
+            &["top_k_kernel"],
+            "./src/devices/cuda/kernels/top_k_kernel.cu",
+        )?;
+
         device.load_module(
             "standardization_kernel_module",
             &["standardization_kernel"],
@@ -500,17 +673,35 @@ impl DeviceTrait for CudaDev {
 
     fn standardization(
         &self,
+        epsilon: f32,
         input: &Tensor,
         output: &Tensor,
         device_stream: &DeviceStream,
     ) -> Result<(), Error> {
-        self.launch_axis_kernel(
-            "standardization_kernel_module",
-            "standardization_kernel",
-            input,
-            output,
-            device_stream,
-        )
+        let cuda_stream = get_cuda_stream(device_stream)?;
+        let kernel = self.get_func("standardization_kernel_module", "standardization_kernel")?;
+        let rows = input.rows();
+        let cols = input.cols();
+        let n = input.len();
+        let cfg = LaunchConfig::for_num_elems(n as u32);
+        let input = &input.device_slice().buffer;
+        let output = &output.device_slice().buffer;
+        match (input, output) {
+            (DeviceSlice::CudaDevSlice(input), DeviceSlice::CudaDevSlice(output)) => {
+                let result = unsafe {
+                    kernel.launch_on_stream(
+                        cuda_stream,
+                        cfg,
+                        (input.slice(), output.slice(), rows, cols, epsilon),
+                    )
+                };
+                match result {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(error!(ErrorEnum::NvRtcLoadPtxError)),
+                }
+            }
+            _ => Err(error!(ErrorEnum::NvRtcLoadPtxError)),
+        }
     }
 
     fn reduce_sum(
@@ -622,6 +813,60 @@ impl DeviceTrait for CudaDev {
         )
     }
 
+    fn activation(
+        &self,
+        function: &ActivationFunction,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.launch_activation_kernel("activation_kernel", function, input, output, device_stream)
+    }
+
+    fn activation_derivative(
+        &self,
+        function: &ActivationFunction,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.launch_activation_kernel(
+            "activation_derivative_kernel",
+            function,
+            input,
+            output,
+            device_stream,
+        )
+    }
+
+    fn gather(
+        &self,
+        data: &Tensor,
+        indices: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        check_indices(&indices.get_values()?, data.rows())?;
+        self.launch_gather_kernel("gather_kernel", data, indices, output, device_stream)
+    }
+
+    fn scatter_add(
+        &self,
+        indices: &Tensor,
+        updates: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        check_indices(&indices.get_values()?, output.rows())?;
+        self.launch_gather_kernel(
+            "scatter_add_kernel",
+            indices,
+            updates,
+            output,
+            device_stream,
+        )
+    }
+
     fn div(
         &self,
         left: &Tensor,
@@ -751,6 +996,133 @@ impl DeviceTrait for CudaDev {
         }
     }
 
+    fn sparse_cross_entropy_loss(
+        &self,
+        attributes: &SparseCrossEntropyAttributes,
+        targets: &Tensor,
+        actual: &Tensor,
+        loss: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement sparse_cross_entropy_loss in CUDA.
+        let target_values = targets.get_values()?;
+        let actual_values = actual.get_values()?;
+        let mut loss_values = loss.get_values()?;
+        attributes.loss(
+            actual.cols(),
+            &target_values,
+            &actual_values,
+            &mut loss_values,
+        )?;
+        loss.set_values(loss_values)
+    }
+
+    fn sparse_cross_entropy_loss_gradient(
+        &self,
+        attributes: &SparseCrossEntropyAttributes,
+        targets: &Tensor,
+        actual: &Tensor,
+        actual_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement sparse_cross_entropy_loss_gradient in CUDA.
+        let target_values = targets.get_values()?;
+        let actual_values = actual.get_values()?;
+        let mut gradient_values = actual_gradient.get_values()?;
+        attributes.loss_gradient(
+            actual.cols(),
+            &target_values,
+            &actual_values,
+            &mut gradient_values,
+        )?;
+        actual_gradient.set_values(gradient_values)
+    }
+
+    fn loss(
+        &self,
+        function: &LossFunction,
+        expected: &Tensor,
+        actual: &Tensor,
+        loss: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement loss in CUDA.
+        let expected_values = expected.get_values()?;
+        let actual_values = actual.get_values()?;
+        let loss_value = function.loss(actual.cols(), &expected_values, &actual_values);
+        loss.set_values(vec![loss_value; 1])
+    }
+
+    fn loss_gradient(
+        &self,
+        function: &LossFunction,
+        expected: &Tensor,
+        actual: &Tensor,
+        actual_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement loss_gradient in CUDA.
+        let expected_values = expected.get_values()?;
+        let actual_values = actual.get_values()?;
+        let mut gradient_values = actual_gradient.get_values()?;
+        function.loss_gradient(
+            actual.cols(),
+            &expected_values,
+            &actual_values,
+            &mut gradient_values,
+        );
+        actual_gradient.set_values(gradient_values)
+    }
+
+    fn top_k_gating(
+        &self,
+        attributes: &GatingAttributes,
+        logits: &Tensor,
+        gates: &Tensor,
+        aux_loss: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement top_k_gating in CUDA.
+        let logits_values = logits.get_values()?;
+        let mut gates_values = gates.get_values()?;
+        let mut aux_loss_values = aux_loss.get_values()?;
+        attributes.gating(
+            logits.cols(),
+            &logits_values,
+            &mut gates_values,
+            &mut aux_loss_values,
+        )?;
+        gates.set_values(gates_values)?;
+        aux_loss.set_values(aux_loss_values)
+    }
+
+    fn top_k_gating_gradient(
+        &self,
+        attributes: &GatingAttributes,
+        logits: &Tensor,
+        gates_gradient: &Tensor,
+        logits_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement top_k_gating_gradient in CUDA.
+        let logits_values = logits.get_values()?;
+        let gates_gradient_values = gates_gradient.get_values()?;
+        let mut gradient_values = logits_gradient.get_values()?;
+        attributes.gating_gradient(
+            logits.cols(),
+            &logits_values,
+            &gates_gradient_values,
+            &mut gradient_values,
+        )?;
+        logits_gradient.set_values(gradient_values)
+    }
+
     fn reduce_sum_square(
         &self,
         expected: &Tensor,
@@ -802,6 +1174,307 @@ impl DeviceTrait for CudaDev {
         output.set_values(other_values)
     }
 
+    fn arg_max(
+        &self,
+        attributes: &ArgReduce,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.launch_arg_reduce_kernel("arg_max_kernel", attributes, input, output, device_stream)
+    }
+
+    fn arg_min(
+        &self,
+        attributes: &ArgReduce,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.launch_arg_reduce_kernel("arg_min_kernel", attributes, input, output, device_stream)
+    }
+
+    fn top_k(
+        &self,
+        attributes: &TopKAttributes,
+        input: &Tensor,
+        values: &Tensor,
+        indices: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let cuda_stream = get_cuda_stream(device_stream)?;
+        let kernel = self.get_func("top_k_kernel_module", "top_k_kernel")?;
+        let rows = input.rows() as i32;
+        let cols = input.cols() as i32;
+        let lines = if attributes.axis == 0 { cols } else { rows };
+        let cfg = LaunchConfig::for_num_elems(lines as u32);
+        let axis = attributes.axis as i32;
+        let k = attributes.k as i32;
+        let largest = attributes.largest as i32;
+        let input = &input.device_slice().buffer;
+        let values = &values.device_slice().buffer;
+        let indices = &indices.device_slice().buffer;
+        match (input, values, indices) {
+            (
+                DeviceSlice::CudaDevSlice(input),
+                DeviceSlice::CudaDevSlice(values),
+                DeviceSlice::CudaDevSlice(indices),
+            ) => {
+                let result = unsafe {
+                    kernel.launch_on_stream(
+                        cuda_stream,
+                        cfg,
+                        (
+                            input.slice(),
+                            values.slice(),
+                            indices.slice(),
+                            rows,
+                            cols,
+                            axis,
+                            k,
+                            largest,
+                        ),
+                    )
+                };
+                match result {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(error!(ErrorEnum::NvLaunchError)),
+                }
+            }
+            _ => Err(error!(ErrorEnum::NvLaunchError)),
+        }
+    }
+
+    fn im2col(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement im2col in CUDA.
+        let input_values = input.get_values()?;
+        let mut output_values = output.get_values()?;
+        window.im2col(&input_values, &mut output_values);
+        output.set_values(output_values)
+    }
+
+    fn col2im(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement col2im in CUDA.
+        let input_values = input.get_values()?;
+        let mut output_values = output.get_values()?;
+        window.col2im(&input_values, &mut output_values);
+        output.set_values(output_values)
+    }
+
+    fn max_pool(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement max_pool in CUDA.
+        let input_values = input.get_values()?;
+        let mut output_values = output.get_values()?;
+        window.max_pool(&input_values, &mut output_values);
+        output.set_values(output_values)
+    }
+
+    fn max_pool_gradient(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement max_pool_gradient in CUDA.
+        let input_values = input.get_values()?;
+        let output_gradient_values = output_gradient.get_values()?;
+        let mut input_gradient_values = input_gradient.get_values()?;
+        window.max_pool_gradient(
+            &input_values,
+            &output_gradient_values,
+            &mut input_gradient_values,
+        );
+        input_gradient.set_values(input_gradient_values)
+    }
+
+    fn average_pool(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement average_pool in CUDA.
+        let input_values = input.get_values()?;
+        let mut output_values = output.get_values()?;
+        window.average_pool(&input_values, &mut output_values);
+        output.set_values(output_values)
+    }
+
+    fn average_pool_gradient(
+        &self,
+        window: &SlidingWindow,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement average_pool_gradient in CUDA.
+        let output_gradient_values = output_gradient.get_values()?;
+        let mut input_gradient_values = input_gradient.get_values()?;
+        window.average_pool_gradient(&output_gradient_values, &mut input_gradient_values);
+        input_gradient.set_values(input_gradient_values)
+    }
+
+    fn rms_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement rms_standardization in CUDA.
+        let input_values = input.get_values()?;
+        let mut output_values = output.get_values()?;
+        attributes.rms_standardization(input.cols(), &input_values, &mut output_values);
+        output.set_values(output_values)
+    }
+
+    fn rms_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement rms_standardization_gradient in CUDA.
+        let input_values = input.get_values()?;
+        let output_gradient_values = output_gradient.get_values()?;
+        let mut input_gradient_values = input_gradient.get_values()?;
+        attributes.rms_standardization_gradient(
+            input.cols(),
+            &input_values,
+            &output_gradient_values,
+            &mut input_gradient_values,
+        );
+        input_gradient.set_values(input_gradient_values)
+    }
+
+    fn group_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement group_standardization in CUDA.
+        let input_values = input.get_values()?;
+        let mut output_values = output.get_values()?;
+        attributes.group_standardization(input.cols(), &input_values, &mut output_values);
+        output.set_values(output_values)
+    }
+
+    fn group_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement group_standardization_gradient in CUDA.
+        let input_values = input.get_values()?;
+        let output_gradient_values = output_gradient.get_values()?;
+        let mut input_gradient_values = input_gradient.get_values()?;
+        attributes.group_standardization_gradient(
+            input.cols(),
+            &input_values,
+            &output_gradient_values,
+            &mut input_gradient_values,
+        );
+        input_gradient.set_values(input_gradient_values)
+    }
+
+    fn batch_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        training: &Tensor,
+        input: &Tensor,
+        running_mean: &Tensor,
+        running_var: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement batch_standardization in CUDA.
+        let training = training.get_values()?[0] != 0.0;
+        let input_values = input.get_values()?;
+        let mut running_mean_values = running_mean.get_values()?;
+        let mut running_var_values = running_var.get_values()?;
+        let mut output_values = output.get_values()?;
+        attributes.batch_standardization(
+            input.rows(),
+            input.cols(),
+            training,
+            &input_values,
+            &mut running_mean_values,
+            &mut running_var_values,
+            &mut output_values,
+        );
+        running_mean.set_values(running_mean_values)?;
+        running_var.set_values(running_var_values)?;
+        output.set_values(output_values)
+    }
+
+    fn batch_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        training: &Tensor,
+        input: &Tensor,
+        running_var: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let _cuda_stream = get_cuda_stream(device_stream)?;
+        // TODO implement batch_standardization_gradient in CUDA.
+        let training = training.get_values()?[0] != 0.0;
+        let input_values = input.get_values()?;
+        let running_var_values = running_var.get_values()?;
+        let output_gradient_values = output_gradient.get_values()?;
+        let mut input_gradient_values = input_gradient.get_values()?;
+        attributes.batch_standardization_gradient(
+            input.rows(),
+            input.cols(),
+            training,
+            &input_values,
+            &running_var_values,
+            &output_gradient_values,
+            &mut input_gradient_values,
+        );
+        input_gradient.set_values(input_gradient_values)
+    }
+
     fn bernoulli(
         &self,
         input: &Tensor,
@@ -838,6 +1511,15 @@ impl DeviceTrait for CudaDev {
         }
     }
 
+    fn set_bernoulli_seed(&self, seed: u64) {
+        // Xorshift never leaves the zero state.
+        let seed = match seed {
+            0 => DEFAULT_BERNOULLI_SEED,
+            _ => seed,
+        };
+        self.bernoulli_seed.store(seed, Ordering::Relaxed);
+    }
+
     fn stream(&self) -> Result<DeviceStreamEnum, Error> {
         let stream = self
             .dev
@@ -845,7 +1527,7 @@ impl DeviceTrait for CudaDev {
             .map_err(|_| error!(ErrorEnum::UnsupportedOperation))?;
         let rng_state = self
             .dev
-            .htod_copy(vec![1337])
+            .htod_copy(vec![self.bernoulli_seed.load(Ordering::Relaxed)])
             .map_err(|_| error!(ErrorEnum::UnsupportedOperation))?;
         let cuda_blas =
             CudaBlas::new(self.dev.clone()).map_err(|_| error!(ErrorEnum::UnsupportedOperation))?;
@@ -388,3 +388,21 @@ fn cuda_cross_entropy_loss() {
 
     assert_eq!(expected, actual,);
 }
+
+#[test]
+fn cuda_gather_rejects_out_of_range_indices() {
+    use crate::devices::DeviceTrait;
+    use crate::Device;
+
+    let device = Device::cuda().unwrap();
+    let device_stream = device.new_stream().unwrap();
+    let data = new_tensor!(device, 2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    let indices = new_tensor!(device, 1, 1, vec![2.0]).unwrap();
+    let output = new_tensor!(device, 1, 2, vec![0.0; 2]).unwrap();
+    assert!(device
+        .gather(&data, &indices, &output, &device_stream)
+        .is_err());
+    assert!(device
+        .scatter_add(&indices, &output, &data, &device_stream)
+        .is_err());
+}
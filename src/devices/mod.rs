@@ -5,7 +5,7 @@ use std::{
     fmt,
     mem::swap,
     ops::Deref,
-    sync::{Arc, RwLock},
+    sync::{Arc, Mutex, MutexGuard, RwLock},
 };
 #[cfg(test)]
 mod tests;
@@ -15,9 +15,17 @@ pub use cpu::*;
 mod cuda;
 #[cfg(feature = "cuda")]
 pub use cuda::*;
+use rand::{rngs::StdRng, SeedableRng};
 use stream::{DeviceStream, DeviceStreamEnum};
 
-use crate::{tensor::Tensor, TensorWithGrad};
+use crate::{
+    analysis::{arg_max::ArgReduce, top_k::TopKAttributes},
+    elementwise::ActivationFunction,
+    statistics::normalization::NormalizationAttributes,
+    tensor::Tensor,
+    GatingAttributes, LoraAdapter, LoraConfig, LossFunction, SlidingWindow,
+    SparseCrossEntropyAttributes, TensorWithGrad,
+};
 pub mod slice;
 pub mod stream;
 use core::fmt::Debug;
@@ -203,8 +211,10 @@ pub trait DeviceTrait {
         device_stream: &DeviceStream,
     ) -> Result<(), Error>;
 
+    /// Standardize each row with (x - mean) / (stddev + epsilon).
     fn standardization(
         &self,
+        epsilon: f32,
         input: &Tensor,
         output: &Tensor,
         device_stream: &DeviceStream,
@@ -231,6 +241,40 @@ pub trait DeviceTrait {
         device_stream: &DeviceStream,
     ) -> Result<(), Error>;
 
+    fn activation(
+        &self,
+        function: &ActivationFunction,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn activation_derivative(
+        &self,
+        function: &ActivationFunction,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    /// output[i, :] = data[indices[i], :]
+    fn gather(
+        &self,
+        data: &Tensor,
+        indices: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    /// output[indices[i], :] += updates[i, :]
+    fn scatter_add(
+        &self,
+        indices: &Tensor,
+        updates: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
     fn bernoulli(
         &self,
         input: &Tensor,
@@ -238,6 +282,9 @@ pub trait DeviceTrait {
         device_stream: &DeviceStream,
     ) -> Result<(), Error>;
 
+    /// Seeds the random number generator of bernoulli.
+    fn set_bernoulli_seed(&self, seed: u64);
+
     fn sqrt(
         &self,
         input: &Tensor,
@@ -262,6 +309,62 @@ pub trait DeviceTrait {
         device_stream: &DeviceStream,
     ) -> Result<(), Error>;
 
+    /// Cross-entropy of the rows of actual with the class ids of targets.
+    fn sparse_cross_entropy_loss(
+        &self,
+        attributes: &SparseCrossEntropyAttributes,
+        targets: &Tensor,
+        actual: &Tensor,
+        loss: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn sparse_cross_entropy_loss_gradient(
+        &self,
+        attributes: &SparseCrossEntropyAttributes,
+        targets: &Tensor,
+        actual: &Tensor,
+        actual_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn loss(
+        &self,
+        function: &LossFunction,
+        expected: &Tensor,
+        actual: &Tensor,
+        loss: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn loss_gradient(
+        &self,
+        function: &LossFunction,
+        expected: &Tensor,
+        actual: &Tensor,
+        actual_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    /// Top-k softmax gating of the router logits of a mixture of experts.
+    fn top_k_gating(
+        &self,
+        attributes: &GatingAttributes,
+        logits: &Tensor,
+        gates: &Tensor,
+        aux_loss: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn top_k_gating_gradient(
+        &self,
+        attributes: &GatingAttributes,
+        logits: &Tensor,
+        gates_gradient: &Tensor,
+        logits_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
     /// RSS = Σ (y_i - f(x_i))^2
     fn reduce_sum_square(
         &self,
@@ -278,6 +381,143 @@ pub trait DeviceTrait {
         device_stream: &DeviceStream,
     ) -> Result<(), Error>;
 
+    fn arg_max(
+        &self,
+        attributes: &ArgReduce,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn arg_min(
+        &self,
+        attributes: &ArgReduce,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn top_k(
+        &self,
+        attributes: &TopKAttributes,
+        input: &Tensor,
+        values: &Tensor,
+        indices: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    /// Copy every window of an image into a column.
+    /// See SlidingWindow::im2col.
+    fn im2col(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    /// Sum every column back into its window of an image.
+    /// See SlidingWindow::col2im.
+    fn col2im(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn max_pool(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn max_pool_gradient(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn average_pool(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn average_pool_gradient(
+        &self,
+        window: &SlidingWindow,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn rms_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn rms_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn group_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    fn group_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    /// training is a 1x1 tensor. In training, running_mean and running_var are updated.
+    #[allow(clippy::too_many_arguments)]
+    fn batch_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        training: &Tensor,
+        input: &Tensor,
+        running_mean: &Tensor,
+        running_var: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
+    #[allow(clippy::too_many_arguments)]
+    fn batch_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        training: &Tensor,
+        input: &Tensor,
+        running_var: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error>;
+
     /// Allocate a slice on the device.
     fn slice(&self, n: i32) -> Result<DeviceSlice, Error>;
 
@@ -297,6 +537,10 @@ pub struct Device {
     tensors: Arc<RwLock<Vec<Tensor>>>,
     internal_tensors: Arc<RwLock<Vec<TensorWithGrad>>>,
     parameter_tensors: Arc<RwLock<Vec<TensorWithGrad>>>,
+    lora_config: Arc<RwLock<Option<LoraConfig>>>,
+    lora_adapters: Arc<RwLock<Vec<LoraAdapter>>>,
+    auxiliary_losses: Arc<RwLock<Vec<Tensor>>>,
+    rng: Arc<Mutex<StdRng>>,
     device: Arc<dyn DeviceTrait + Send + Sync>,
     available_buffers: Arc<RwLock<HashMap<usize, LinkedList<DevSlice>>>>,
 }
@@ -318,15 +562,21 @@ impl fmt::Debug for Device {
 
 impl Device {
     pub fn new(device: Arc<dyn DeviceTrait + Send + Sync>) -> Self {
-        Self {
+        let device = Self {
             next_name: Default::default(),
             used: Default::default(),
             tensors: Default::default(),
             internal_tensors: Default::default(),
             parameter_tensors: Default::default(),
+            lora_config: Default::default(),
+            lora_adapters: Default::default(),
+            auxiliary_losses: Default::default(),
+            rng: Arc::new(Mutex::new(StdRng::seed_from_u64(0))),
             device,
             available_buffers: Default::default(),
-        }
+        };
+        device.set_seed(rand::random());
+        device
     }
 
     pub fn new_stream(&self) -> Result<DeviceStream, Error> {
@@ -498,6 +748,51 @@ impl Device {
         self.parameter_tensors.read().unwrap()
     }
 
+    /// Seeds every random source: the initialization of weights and embeddings,
+    /// the shuffling of examples and the Bernoulli trials of Dropout.
+    /// With the same seed, training on the CPU is reproducible.
+    /// On CUDA, only the streams created afterwards start from the seed,
+    /// so the seed must be set before the NeuralMachine is created.
+    pub fn set_seed(&self, seed: u64) {
+        *self.rng.lock().unwrap() = StdRng::seed_from_u64(seed);
+        self.device.set_bernoulli_seed(seed);
+    }
+
+    pub fn rng(&self) -> MutexGuard<'_, StdRng> {
+        self.rng.lock().unwrap()
+    }
+
+    /// Linear layers created while a LoraConfig is set get a LoraAdapter.
+    pub fn set_lora_config(&self, lora_config: Option<LoraConfig>) {
+        *self.lora_config.write().unwrap() = lora_config;
+    }
+
+    pub fn lora_config(&self) -> Option<LoraConfig> {
+        *self.lora_config.read().unwrap()
+    }
+
+    pub(crate) fn add_lora_adapter(&self, adapter: &LoraAdapter) {
+        self.lora_adapters.write().unwrap().push(adapter.clone())
+    }
+
+    /// Adapters in creation order.
+    pub fn lora_adapters(&self) -> impl Deref<Target = Vec<LoraAdapter>> + '_ {
+        self.lora_adapters.read().unwrap()
+    }
+
+    pub(crate) fn add_auxiliary_loss(&self, auxiliary_loss: &Tensor) {
+        self.auxiliary_losses
+            .write()
+            .unwrap()
+            .push(auxiliary_loss.clone())
+    }
+
+    /// Losses computed by operators beside the loss of the model, in creation order.
+    /// Their gradient is already added by the operators, see TopKGating.
+    pub fn auxiliary_losses(&self) -> impl Deref<Target = Vec<Tensor>> + '_ {
+        self.auxiliary_losses.read().unwrap()
+    }
+
     pub fn buffer(&self, len: usize) -> DevSlice {
         let recycled = self
             .available_buffers
@@ -685,6 +980,49 @@ impl DeviceTrait for Device {
         self.device.gelu_derivative(input, output, device_stream)
     }
 
+    fn activation(
+        &self,
+        function: &ActivationFunction,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .activation(function, input, output, device_stream)
+    }
+
+    fn activation_derivative(
+        &self,
+        function: &ActivationFunction,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .activation_derivative(function, input, output, device_stream)
+    }
+
+    fn gather(
+        &self,
+        data: &Tensor,
+        indices: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.gather(data, indices, output, device_stream)
+    }
+
+    fn scatter_add(
+        &self,
+        indices: &Tensor,
+        updates: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .scatter_add(indices, updates, output, device_stream)
+    }
+
     fn sqrt(
         &self,
         input: &Tensor,
@@ -736,6 +1074,88 @@ impl DeviceTrait for Device {
             .cross_entropy_loss(expected, actual, loss, device_stream)
     }
 
+    fn sparse_cross_entropy_loss(
+        &self,
+        attributes: &SparseCrossEntropyAttributes,
+        targets: &Tensor,
+        actual: &Tensor,
+        loss: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .sparse_cross_entropy_loss(attributes, targets, actual, loss, device_stream)
+    }
+
+    fn sparse_cross_entropy_loss_gradient(
+        &self,
+        attributes: &SparseCrossEntropyAttributes,
+        targets: &Tensor,
+        actual: &Tensor,
+        actual_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.sparse_cross_entropy_loss_gradient(
+            attributes,
+            targets,
+            actual,
+            actual_gradient,
+            device_stream,
+        )
+    }
+
+    fn loss(
+        &self,
+        function: &LossFunction,
+        expected: &Tensor,
+        actual: &Tensor,
+        loss: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .loss(function, expected, actual, loss, device_stream)
+    }
+
+    fn loss_gradient(
+        &self,
+        function: &LossFunction,
+        expected: &Tensor,
+        actual: &Tensor,
+        actual_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .loss_gradient(function, expected, actual, actual_gradient, device_stream)
+    }
+
+    fn top_k_gating(
+        &self,
+        attributes: &GatingAttributes,
+        logits: &Tensor,
+        gates: &Tensor,
+        aux_loss: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .top_k_gating(attributes, logits, gates, aux_loss, device_stream)
+    }
+
+    fn top_k_gating_gradient(
+        &self,
+        attributes: &GatingAttributes,
+        logits: &Tensor,
+        gates_gradient: &Tensor,
+        logits_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.top_k_gating_gradient(
+            attributes,
+            logits,
+            gates_gradient,
+            logits_gradient,
+            device_stream,
+        )
+    }
+
     fn reduce_sum_square(
         &self,
         expected: &Tensor,
@@ -756,6 +1176,207 @@ impl DeviceTrait for Device {
         self.device.transpose(input, output, device_stream)
     }
 
+    fn arg_max(
+        &self,
+        attributes: &ArgReduce,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .arg_max(attributes, input, output, device_stream)
+    }
+
+    fn arg_min(
+        &self,
+        attributes: &ArgReduce,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .arg_min(attributes, input, output, device_stream)
+    }
+
+    fn top_k(
+        &self,
+        attributes: &TopKAttributes,
+        input: &Tensor,
+        values: &Tensor,
+        indices: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .top_k(attributes, input, values, indices, device_stream)
+    }
+
+    fn im2col(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.im2col(window, input, output, device_stream)
+    }
+
+    fn col2im(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.col2im(window, input, output, device_stream)
+    }
+
+    fn max_pool(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.max_pool(window, input, output, device_stream)
+    }
+
+    fn max_pool_gradient(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.max_pool_gradient(
+            window,
+            input,
+            output_gradient,
+            input_gradient,
+            device_stream,
+        )
+    }
+
+    fn average_pool(
+        &self,
+        window: &SlidingWindow,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .average_pool(window, input, output, device_stream)
+    }
+
+    fn average_pool_gradient(
+        &self,
+        window: &SlidingWindow,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .average_pool_gradient(window, output_gradient, input_gradient, device_stream)
+    }
+
+    fn rms_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .rms_standardization(attributes, input, output, device_stream)
+    }
+
+    fn rms_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.rms_standardization_gradient(
+            attributes,
+            input,
+            output_gradient,
+            input_gradient,
+            device_stream,
+        )
+    }
+
+    fn group_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device
+            .group_standardization(attributes, input, output, device_stream)
+    }
+
+    fn group_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        input: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.group_standardization_gradient(
+            attributes,
+            input,
+            output_gradient,
+            input_gradient,
+            device_stream,
+        )
+    }
+
+    fn batch_standardization(
+        &self,
+        attributes: &NormalizationAttributes,
+        training: &Tensor,
+        input: &Tensor,
+        running_mean: &Tensor,
+        running_var: &Tensor,
+        output: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.batch_standardization(
+            attributes,
+            training,
+            input,
+            running_mean,
+            running_var,
+            output,
+            device_stream,
+        )
+    }
+
+    fn batch_standardization_gradient(
+        &self,
+        attributes: &NormalizationAttributes,
+        training: &Tensor,
+        input: &Tensor,
+        running_var: &Tensor,
+        output_gradient: &Tensor,
+        input_gradient: &Tensor,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        self.device.batch_standardization_gradient(
+            attributes,
+            training,
+            input,
+            running_var,
+            output_gradient,
+            input_gradient,
+            device_stream,
+        )
+    }
+
     fn bernoulli(
         &self,
         input: &Tensor,
@@ -765,6 +1386,10 @@ impl DeviceTrait for Device {
         self.device.bernoulli(input, output, device_stream)
     }
 
+    fn set_bernoulli_seed(&self, seed: u64) {
+        self.device.set_bernoulli_seed(seed)
+    }
+
     fn slice(&self, n: i32) -> Result<DeviceSlice, Error> {
         self.device.slice(n)
     }
@@ -775,10 +1400,12 @@ impl DeviceTrait for Device {
 
     fn standardization(
         &self,
+        epsilon: f32,
         input: &Tensor,
         output: &Tensor,
         device_stream: &DeviceStream,
     ) -> Result<(), Error> {
-        self.device.standardization(input, output, device_stream)
+        self.device
+            .standardization(epsilon, input, output, device_stream)
     }
 }
@@ -2,7 +2,7 @@ use std::ops::Div;
 
 use more_asserts::{assert_ge, assert_le};
 
-use crate::{new_tensor, stream::StreamTrait, Device, DeviceTrait};
+use crate::{new_tensor, stream::StreamTrait, Device, DeviceTrait, EPSILON};
 
 #[test]
 fn clip_min() {
@@ -112,6 +112,29 @@ fn bernoulli() {
     assert_le!(70 - diff, zeroes);
 }
 
+#[test]
+fn seeded_bernoulli_is_reproducible() {
+    let trials = |seed| {
+        let device = Device::default();
+        device.set_seed(seed);
+        let input = new_tensor!(device, 1, 100, vec![0.5; 100]).unwrap();
+        let output = new_tensor!(device, 1, 100, vec![0.0; 100]).unwrap();
+        let device_stream = device.new_stream().unwrap();
+        let mut values = vec![];
+        for _ in 0..2 {
+            device.bernoulli(&input, &output, &device_stream).unwrap();
+            device_stream.wait_for().unwrap();
+            values.push(output.get_values().unwrap());
+        }
+        values
+    };
+    let first = trials(42);
+    assert_eq!(first, trials(42));
+    assert_ne!(first, trials(43));
+    // The counter advances, so consecutive trials differ.
+    assert_ne!(first[0], first[1]);
+}
+
 #[test]
 fn test_copy_1() {
     let device = Device::default();
@@ -219,7 +242,7 @@ fn standardization_output_data_with_mean_0_and_stddev_1_for_each_neuron() {
     let output = new_tensor!(device, neurons, features, vec![0.0; n]).unwrap();
     let device_stream = device.new_stream().unwrap();
     device
-        .standardization(&input, &output, &device_stream)
+        .standardization(EPSILON, &input, &output, &device_stream)
         .unwrap();
     device_stream.wait_for().unwrap();
     let elements = output.get_values().unwrap();
@@ -257,7 +280,7 @@ fn standardization_output_data_with_mean_0_and_stddev_1_not_for_all_neurons() {
     let output_2_neurons = new_tensor!(device, neurons, features, vec![0.0; n]).unwrap();
     let device_stream = device.new_stream().unwrap();
     device
-        .standardization(&input_2_neurons, &output_2_neurons, &device_stream)
+        .standardization(EPSILON, &input_2_neurons, &output_2_neurons, &device_stream)
         .unwrap();
     device_stream.wait_for().unwrap();
     let elements_2_neurons = output_2_neurons.get_values().unwrap();
@@ -267,7 +290,7 @@ fn standardization_output_data_with_mean_0_and_stddev_1_not_for_all_neurons() {
     let output_1_neurons = new_tensor!(device, 1, n, vec![0.0; n]).unwrap();
     let device_stream = device.new_stream().unwrap();
     device
-        .standardization(&input_1_neurons, &output_1_neurons, &device_stream)
+        .standardization(EPSILON, &input_1_neurons, &output_1_neurons, &device_stream)
         .unwrap();
     device_stream.wait_for().unwrap();
     let elements_1_neurons = output_1_neurons.get_values().unwrap();
@@ -298,7 +321,7 @@ fn standardization_is_deterministic() {
     let output1 = new_tensor!(device, neurons, features, vec![0.0; n]).unwrap();
     let device_stream1 = device.new_stream().unwrap();
     device
-        .standardization(&input, &output1, &device_stream1)
+        .standardization(EPSILON, &input, &output1, &device_stream1)
         .unwrap();
     device_stream1.wait_for().unwrap();
     let elements1 = output1.get_values().unwrap();
@@ -306,7 +329,7 @@ fn standardization_is_deterministic() {
     let output2 = new_tensor!(device, neurons, features, vec![0.0; n]).unwrap();
     let device_stream2 = device.new_stream().unwrap();
     device
-        .standardization(&input, &output2, &device_stream2)
+        .standardization(EPSILON, &input, &output2, &device_stream2)
         .unwrap();
     device_stream2.wait_for().unwrap();
     let elements2 = output2.get_values().unwrap();
@@ -22,6 +22,7 @@ impl MegaManModel {
         let n_embd = 384;
         let output_rows = 1;
 
+        let trainable = device.lora_config().is_none();
         let model = Self {
             input_shape: vec![sequence_length, vocab_size],
             output_shape: vec![output_rows, vocab_size],
@@ -31,8 +32,8 @@ impl MegaManModel {
                 n_embd,
                 vec![0.0; n_embd * n_embd],
                 &[],
-                true,
-                true,
+                trainable,
+                trainable,
             )?,
             embedding: Embedding::new(device, vocab_size, n_embd)?,
             matmul: MatMul::new(device, true),
@@ -3,6 +3,8 @@ pub use model::*;
 pub mod attention_head_model;
 pub mod mega_man;
 pub mod multi_head_attention_model;
+pub mod multi_layer_perceptron;
 pub mod perceptron;
+pub mod recurrent_model;
 pub mod simple;
 pub mod transformer_model;
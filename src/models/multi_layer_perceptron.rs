@@ -0,0 +1,66 @@
+use crate::{
+    tensor::Error, Device, ForwardForwardModel, Linear, Model, Sigmoid, TensorWithGrad, UnaryModel,
+    UnaryOperator, WeightsInitialization,
+};
+
+/// A stack of Linear layers, each followed by a Sigmoid.
+pub struct MultiLayerPerceptronModel {
+    layer_sizes: Vec<usize>,
+    linears: Vec<Linear>,
+    sigmoid: Sigmoid,
+}
+
+impl UnaryModel for MultiLayerPerceptronModel {}
+
+impl MultiLayerPerceptronModel {
+    /// layer_sizes contains the input size followed by the output size of each layer.
+    pub fn new(device: &Device, layer_sizes: &[usize]) -> Result<Self, Error> {
+        let mut linears = vec![];
+        for sizes in layer_sizes.windows(2) {
+            let linear = Linear::new(
+                device,
+                sizes[1],
+                sizes[0],
+                WeightsInitialization::Kaiming,
+                1,
+            )?;
+            linears.push(linear);
+        }
+        let model = Self {
+            layer_sizes: layer_sizes.to_owned(),
+            linears,
+            sigmoid: Sigmoid::new(device),
+        };
+        Ok(model)
+    }
+}
+
+impl UnaryOperator for MultiLayerPerceptronModel {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let mut state = input.clone();
+        for linear in self.linears.iter() {
+            state = linear.forward(&state)?;
+            state = self.sigmoid.forward(&state)?;
+        }
+        Ok(state)
+    }
+}
+
+impl Model for MultiLayerPerceptronModel {
+    fn input_size(&self) -> Vec<usize> {
+        vec![1, self.layer_sizes[0]]
+    }
+    fn output_size(&self) -> Vec<usize> {
+        vec![1, self.layer_sizes[self.layer_sizes.len() - 1]]
+    }
+}
+
+impl ForwardForwardModel for MultiLayerPerceptronModel {
+    fn layers(&self) -> Vec<(&dyn UnaryOperator, Vec<usize>)> {
+        self.linears
+            .iter()
+            .zip(self.layer_sizes.iter())
+            .map(|(linear, input_size)| (linear as &dyn UnaryOperator, vec![1, *input_size]))
+            .collect()
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
-    tensor::Error, Device, Linear, Model, TensorWithGrad, UnaryModel, UnaryOperator,
-    WeightsInitialization,
+    tensor::Error, Device, ForwardForwardModel, Linear, Model, TensorWithGrad, UnaryModel,
+    UnaryOperator, WeightsInitialization,
 };
 
 pub struct PerceptronModel {
@@ -31,3 +31,9 @@ impl Model for PerceptronModel {
         vec![1, 1]
     }
 }
+
+impl ForwardForwardModel for PerceptronModel {
+    fn layers(&self) -> Vec<(&dyn UnaryOperator, Vec<usize>)> {
+        vec![(&self.linear, self.input_size())]
+    }
+}
@@ -0,0 +1,113 @@
+use crate::tensor::Error;
+use crate::{Device, Gru, Lstm, UnaryModel, UnaryOperator, WeightsInitialization};
+use crate::{Embedding, Linear, Model, Softmax, TensorWithGrad};
+
+/// Recurrent layer of a RecurrentModel.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Recurrence {
+    #[default]
+    Lstm,
+    Gru,
+}
+
+impl Recurrence {
+    fn try_new_operator(
+        &self,
+        device: &Device,
+        sequence_length: usize,
+        input_size: usize,
+        hidden_size: usize,
+    ) -> Result<Box<dyn UnaryOperator>, Error> {
+        let operator: Box<dyn UnaryOperator> = match self {
+            Recurrence::Lstm => Box::new(Lstm::try_new(
+                device,
+                sequence_length,
+                input_size,
+                hidden_size,
+            )?),
+            Recurrence::Gru => Box::new(Gru::try_new(
+                device,
+                sequence_length,
+                input_size,
+                hidden_size,
+            )?),
+        };
+        Ok(operator)
+    }
+}
+
+/// Next-token prediction with stacked recurrent layers instead of transformer blocks.
+/// Like TransformerModel, the input and output rows are one-hot encoded tokens.
+pub struct RecurrentModel {
+    input_shape: Vec<usize>,
+    output_shape: Vec<usize>,
+    embedding: Embedding,
+    layers: Vec<Box<dyn UnaryOperator>>,
+    linear: Linear,
+    softmax: Softmax,
+}
+
+impl UnaryModel for RecurrentModel {}
+
+impl RecurrentModel {
+    pub fn new(
+        device: &Device,
+        recurrence: Recurrence,
+        layers: usize,
+        n_embd: usize,
+        hidden_size: usize,
+        context_length: usize,
+        vocab_size: usize,
+    ) -> Result<Self, Error> {
+        let embedding = Embedding::new(device, vocab_size, n_embd)?;
+        let layers = (0..layers)
+            .map(|layer| {
+                let input_size = match layer {
+                    0 => n_embd,
+                    _ => hidden_size,
+                };
+                recurrence.try_new_operator(device, context_length, input_size, hidden_size)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let linear = Linear::new(
+            device,
+            vocab_size,
+            hidden_size,
+            WeightsInitialization::Kaiming,
+            context_length,
+        )?;
+        let softmax = Softmax::new_with_next_is_cross_entropy_loss(device);
+
+        let model = Self {
+            input_shape: vec![context_length, vocab_size],
+            output_shape: vec![context_length, vocab_size],
+            embedding,
+            layers,
+            linear,
+            softmax,
+        };
+        Ok(model)
+    }
+}
+
+impl UnaryOperator for RecurrentModel {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let mut output = self.embedding.forward(input)?;
+        for layer in self.layers.iter() {
+            output = layer.forward(&output)?;
+        }
+        let linear = self.linear.forward(&output)?;
+        let softmax = self.softmax.forward(&linear)?;
+        Ok(softmax)
+    }
+}
+
+impl Model for RecurrentModel {
+    fn input_size(&self) -> Vec<usize> {
+        self.input_shape.clone()
+    }
+
+    fn output_size(&self) -> Vec<usize> {
+        self.output_shape.clone()
+    }
+}
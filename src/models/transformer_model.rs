@@ -1,8 +1,8 @@
-use crate::statistics::layer_norm::LayerNormalization;
-use crate::tensor::Error;
-use crate::transformer::Transformer;
-use crate::{Device, Dropout, UnaryModel, UnaryOperator, WeightsInitialization};
-use crate::{Embedding, Linear, Model, Softmax, TensorWithGrad};
+use crate::statistics::normalization::NormalizationOperator;
+use crate::tensor::{Error, ErrorEnum};
+use crate::transformer::{Transformer, TransformerConfig};
+use crate::{error, Device, Dropout, UnaryModel, UnaryOperator, WeightsInitialization};
+use crate::{Linear, Model, Softmax, SparseEmbedding, TensorWithGrad};
 
 /// See
 /// Full GPT Architecture
@@ -15,13 +15,16 @@ use crate::{Embedding, Linear, Model, Softmax, TensorWithGrad};
 /// See
 /// OpenAI GPT 1
 /// https://huggingface.co/openai-community/openai-gpt
+///
+/// The input has one token id per row, see into_token_id_rows.
+/// With tied_embedding, the output Linear uses the table of the SparseEmbedding as its weights, like GPT-2.
 pub struct TransformerModel {
     input_shape: Vec<usize>,
     output_shape: Vec<usize>,
-    embedding: Embedding,
+    embedding: SparseEmbedding,
     dropout: Dropout,
     transformers: Vec<Transformer>,
-    layer_norm: LayerNormalization,
+    layer_norm: NormalizationOperator,
     linear: Linear,
     softmax: Softmax,
 }
@@ -32,40 +35,36 @@ impl TransformerModel {
     pub fn new(
         device: &Device,
         layers: usize,
-        num_heads: usize,
-        dropout_probability: f32,
         n_embd: usize,
         context_length: usize,
         vocab_size: usize,
-        causal_mask: bool,
+        config: &TransformerConfig,
     ) -> Result<Self, Error> {
-        let embedding = Embedding::new(device, vocab_size, n_embd)?;
-        let dropout = Dropout::try_new(device, context_length, n_embd, dropout_probability)?;
+        let normalization = &config.normalization;
+        if !normalization.is_row_wise() {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        let embedding = SparseEmbedding::new(device, vocab_size, n_embd)?;
+        let dropout = Dropout::try_new(device, context_length, n_embd, config.dropout_probability)?;
         let transformers = (0..layers)
-            .map(|_| {
-                Transformer::try_new(
-                    device,
-                    context_length,
-                    n_embd,
-                    causal_mask,
-                    num_heads,
-                    dropout_probability,
-                )
-            })
+            .map(|_| Transformer::try_new(device, context_length, n_embd, config))
             .collect::<Result<Vec<_>, _>>()?;
 
-        let layer_norm = LayerNormalization::try_new(device, context_length, n_embd)?;
-        let linear = Linear::new(
-            device,
-            vocab_size,
-            n_embd,
-            WeightsInitialization::Kaiming,
-            context_length,
-        )?;
+        let layer_norm = normalization.try_new_operator(device, context_length, n_embd)?;
+        let linear = match config.tied_embedding {
+            true => Linear::try_new_tied(device, &embedding, context_length)?,
+            false => Linear::new(
+                device,
+                vocab_size,
+                n_embd,
+                WeightsInitialization::Kaiming,
+                context_length,
+            )?,
+        };
         let softmax = Softmax::new_with_next_is_cross_entropy_loss(device);
 
         let model = Self {
-            input_shape: vec![context_length, vocab_size],
+            input_shape: vec![context_length, 1],
             output_shape: vec![context_length, vocab_size],
             embedding,
             dropout,
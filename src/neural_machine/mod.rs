@@ -2,6 +2,8 @@ mod instruction;
 pub use instruction::*;
 mod neural_machine;
 pub use neural_machine::*;
+mod online_learning;
+pub use online_learning::*;
 pub mod neural_program;
 pub mod schedulers;
 pub mod streams;
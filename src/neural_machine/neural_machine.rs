@@ -1,11 +1,12 @@
-use std::{marker::PhantomData, ops::Deref, sync::Arc};
+use std::{collections::HashSet, marker::PhantomData, ops::Deref, sync::Arc, time::Instant};
 
 use crate::schedulers::SchedulerTrait;
 use crate::stream::StreamTrait;
 use crate::{
-    neural_machine::streams::stream::print_streams, neural_program::NeuralProgram,
-    schedulers::StreamExecutor, stream::DeviceStream, tensor::Error, Category, Device, Instruction,
-    TensorWithGrad,
+    error, get_row_tokens, neural_machine::streams::stream::print_streams,
+    neural_program::NeuralProgram, new_tensor, opcode::OpCode, schedulers::StreamExecutor,
+    stream::DeviceStream, tensor::Error, tensor::ErrorEnum, tensor::Tensor, Category, Device,
+    DeviceTrait, Instruction, OnlineLearning, TensorWithGrad,
 };
 
 use super::streams::{
@@ -24,6 +25,10 @@ where
     example_output: TensorWithGrad,
     machine_output: TensorWithGrad,
     loss: TensorWithGrad,
+    /// Sum of the losses accumulated on the device, see accumulate_loss.
+    total_loss: Tensor,
+    gradient_norm: Option<Tensor>,
+    auxiliary_losses: Vec<Tensor>,
     inference_instructions: Arc<Vec<Instruction>>,
     inference_streams: Arc<Vec<Stream>>,
     inference_scheduler: Scheduler,
@@ -36,6 +41,16 @@ where
     optimization_instructions: Arc<Vec<Instruction>>,
     optimization_streams: Arc<Vec<Stream>>,
     optimization_scheduler: Scheduler,
+    online_learning: Option<OnlineLearning>,
+    online_examples: usize,
+    online_updates: usize,
+    last_online_update: Option<Instant>,
+    training: bool,
+    /// Tensors of the inference that behave differently in evaluation mode,
+    /// with their values in evaluation mode.
+    evaluation_values: Vec<(Tensor, Vec<f32>)>,
+    /// Values of these tensors in training mode, saved when switching to evaluation mode.
+    training_values: Vec<Vec<f32>>,
     phantom_data: PhantomData<T>,
 }
 
@@ -49,7 +64,7 @@ where
         maximum_device_streams: usize,
     ) -> Result<Self, Error> {
         let all_instructions = program.instructions;
-        let inference_instructions = all_instructions
+        let inference_instructions: Vec<_> = all_instructions
             .clone()
             .into_iter()
             .filter(|i| i.category() == Category::Inference)
@@ -77,10 +92,14 @@ where
             .collect();
         let optimization_instructions = Arc::new(optimization_instructions);
 
+        let evaluation_values = Self::evaluation_values(&inference_instructions);
+
         let example_input = program.example_input;
         let example_output = program.example_output;
         let machine_output = program.machine_output;
         let loss = program.loss;
+        let gradient_norm = program.gradient_norm;
+        let auxiliary_losses = program.auxiliary_losses;
 
         let inference_streams = Self::assign_streams(&example_input, &inference_instructions);
         let inference_streams = Arc::new(inference_streams);
@@ -133,6 +152,9 @@ where
             example_output,
             machine_output,
             loss,
+            total_loss: new_tensor!(device, 1, 1, vec![0.0])?,
+            gradient_norm,
+            auxiliary_losses,
             inference_instructions,
             inference_streams,
             inference_scheduler,
@@ -145,6 +167,13 @@ where
             optimization_instructions,
             optimization_streams,
             optimization_scheduler,
+            online_learning: None,
+            online_examples: 0,
+            online_updates: 0,
+            last_online_update: None,
+            training: true,
+            evaluation_values,
+            training_values: vec![],
             phantom_data: Default::default(),
         };
 
@@ -177,6 +206,27 @@ where
         Ok(self.loss.clone())
     }
 
+    /// Add the loss of the last example to the total loss, on the device.
+    /// The total loss restarts from the loss of the last example if restart is true.
+    pub fn accumulate_loss(&mut self, restart: bool) -> Result<(), Error> {
+        let loss = &self.loss.tensor();
+        if restart {
+            self.device
+                .copy_to(loss, &self.total_loss, &self.io_stream)?;
+        } else {
+            let n = loss.len() as i32;
+            self.device
+                .axpy(n, 1.0, loss, 1, &self.total_loss, 1, &self.io_stream)?;
+        }
+        Ok(())
+    }
+
+    /// Total loss accumulated by accumulate_loss.
+    pub fn total_loss(&self) -> Result<f32, Error> {
+        self.io_stream.wait_for()?;
+        (&self.total_loss).try_into()
+    }
+
     pub fn compute_gradient(&mut self) -> Result<(), Error> {
         self.forward(&Category::Gradient)?;
         Ok(())
@@ -187,6 +237,147 @@ where
         Ok(())
     }
 
+    /// Global norm of the parameter gradients measured by the last optimization,
+    /// before clipping. None if global gradient norm clipping is disabled.
+    pub fn gradient_norm(&self) -> Result<Option<f32>, Error> {
+        match &self.gradient_norm {
+            Some(gradient_norm) => Ok(Some(gradient_norm.try_into()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Sum of the auxiliary losses of the last inference, like the load-balancing loss
+    /// of a MixtureOfExperts. None if the model has no auxiliary loss.
+    pub fn auxiliary_loss(&self) -> Result<Option<f32>, Error> {
+        if self.auxiliary_losses.is_empty() {
+            return Ok(None);
+        }
+        let mut sum = 0.0;
+        for auxiliary_loss in self.auxiliary_losses.iter() {
+            let value: f32 = auxiliary_loss.try_into()?;
+            sum += value;
+        }
+        Ok(Some(sum))
+    }
+
+    /// Enable or disable the online learning mode, see OnlineLearning.
+    pub fn set_online_learning(&mut self, online_learning: Option<OnlineLearning>) {
+        self.online_learning = online_learning;
+        self.online_examples = 0;
+    }
+
+    /// In online learning mode and in training mode, learn to predict the next tokens of
+    /// the input of the last inference.
+    /// The loss and the gradient are computed, and the parameters are optimized according
+    /// to the update frequency and the rate limits.
+    /// While the minimum update interval blocks the optimization, the gradient of
+    /// update_frequency examples is kept and the next examples are not learned.
+    fn learn_online(&mut self, input: &TensorWithGrad) -> Result<(), Error> {
+        let online_learning = match &self.online_learning {
+            Some(online_learning) if self.training => online_learning.clone(),
+            _ => return Ok(()),
+        };
+        if let Some(maximum_updates) = online_learning.maximum_updates {
+            if self.online_updates >= maximum_updates {
+                return Ok(());
+            }
+        }
+
+        if self.online_examples < online_learning.update_frequency {
+            let expected_output =
+                next_token_targets(&input.tensor(), online_learning.ignore_index)?;
+            {
+                let example_output = &self.example_output.tensor();
+                if example_output.cols() != 1 || example_output.rows() != expected_output.len() {
+                    return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+                }
+                example_output.set_values(expected_output)?;
+            }
+            self.forward(&Category::Loss)?;
+            self.compute_gradient()?;
+            self.online_examples += 1;
+        }
+
+        let interval_elapsed = match self.last_online_update {
+            Some(last_update) => last_update.elapsed() >= online_learning.minimum_update_interval,
+            None => true,
+        };
+        if self.online_examples >= online_learning.update_frequency && interval_elapsed {
+            self.optimize()?;
+            self.online_examples = 0;
+            self.online_updates += 1;
+            self.last_online_update = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    /// Number of optimizations done in online learning mode.
+    pub fn online_updates(&self) -> usize {
+        self.online_updates
+    }
+
+    /// Switch between the training mode and the evaluation mode.
+    /// In evaluation mode, the masks of the dropout layers are filled with ones and are not scaled,
+    /// and the batch standardizations use their running statistics.
+    /// The instructions are not compiled again.
+    pub fn set_training(&mut self, training: bool) -> Result<(), Error> {
+        if training == self.training {
+            return Ok(());
+        }
+        if training {
+            for ((tensor, _), values) in self
+                .evaluation_values
+                .iter()
+                .zip(self.training_values.drain(..))
+            {
+                tensor.set_values(values)?;
+            }
+        } else {
+            for (tensor, values) in self.evaluation_values.iter() {
+                self.training_values.push(tensor.get_values()?);
+                tensor.set_values(values.clone())?;
+            }
+        }
+        self.training = training;
+        Ok(())
+    }
+
+    pub fn is_training(&self) -> bool {
+        self.training
+    }
+
+    /// Find the tensors that configure the training behaviour of the inference instructions:
+    /// - the probabilities of Bernoulli(probabilities) -> mask of a dropout,
+    /// - the alpha of ScalarMul(alpha, Mul(x, mask)) that scales the output of a dropout,
+    /// - the training flag of a BatchStandardization.
+    fn evaluation_values(instructions: &[Instruction]) -> Vec<(Tensor, Vec<f32>)> {
+        let mut evaluation_values = vec![];
+        let mut masks = HashSet::new();
+        let mut masked_outputs = HashSet::new();
+        for instruction in instructions.iter() {
+            let inputs = instruction.inputs();
+            let outputs = instruction.outputs();
+            match instruction.opcode() {
+                OpCode::Bernoulli => {
+                    let probabilities = &inputs[0];
+                    evaluation_values.push((probabilities.clone(), vec![1.0; probabilities.len()]));
+                    masks.insert(outputs[0].name());
+                }
+                OpCode::Mul if inputs.iter().any(|input| masks.contains(&input.name())) => {
+                    masked_outputs.insert(outputs[0].name());
+                }
+                OpCode::ScalarMul if masked_outputs.contains(&inputs[1].name()) => {
+                    evaluation_values.push((inputs[0].clone(), vec![1.0]));
+                }
+                OpCode::BatchStandardization => {
+                    evaluation_values.push((inputs[0].clone(), vec![0.0]));
+                }
+                _ => {}
+            }
+        }
+        evaluation_values
+    }
+
     fn forward_with_streams(&mut self, category: &Category) -> Result<(), Error> {
         let scheduler = match category {
             Category::Inference => &mut self.inference_scheduler,
@@ -214,6 +405,7 @@ where
         }
 
         self.forward(&Category::Inference)?;
+        self.learn_online(input)?;
 
         Ok(self.machine_output.clone())
     }
@@ -369,3 +561,14 @@ where
         self.optimization_scheduler.stop();
     }
 }
+
+/// The expected output of each row is the token of the next row, see get_row_tokens.
+fn next_token_targets(input: &Tensor, ignore_index: usize) -> Result<Vec<f32>, Error> {
+    let mut targets = get_row_tokens(input)?
+        .into_iter()
+        .skip(1)
+        .map(|token| token as f32)
+        .collect::<Vec<_>>();
+    targets.push(ignore_index as f32);
+    Ok(targets)
+}
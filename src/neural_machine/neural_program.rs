@@ -1,11 +1,10 @@
-use std::{collections::HashSet, ops::Deref};
-
 use crate::{
-    gradient_instruction, instruction, new_tensor, new_tensor_with_grad,
+    error, instruction, new_tensor, new_tensor_with_grad,
     opcode::OpCode,
-    tensor::{Error, Tensor},
+    split_parameters,
+    tensor::{Error, ErrorEnum, Tensor},
     BinaryOperator, Category, Device, Instruction, OperatorAttributes, OptimizerTrait,
-    TensorWithGrad, UnaryModel,
+    ParameterGroup, TensorWithGrad, UnaryModel,
 };
 
 pub struct NeuralProgram {
@@ -13,6 +12,10 @@ pub struct NeuralProgram {
     pub example_output: TensorWithGrad,
     pub machine_output: TensorWithGrad,
     pub loss: TensorWithGrad,
+    /// Global norm of the parameter gradients, before clipping.
+    pub gradient_norm: Option<Tensor>,
+    /// Auxiliary losses computed by the inference, see Device::auxiliary_losses.
+    pub auxiliary_losses: Vec<Tensor>,
     pub instructions: Vec<Instruction>,
 }
 
@@ -22,7 +25,8 @@ impl NeuralProgram {
         model: &impl UnaryModel,
         loss_operator: &impl BinaryOperator,
         optimizer: &impl OptimizerTrait,
-        clipped_gradient_norm: bool,
+        max_gradient_norm: Option<f32>,
+        parameter_groups: &[ParameterGroup],
     ) -> Result<NeuralProgram, Error> {
         let zero = new_tensor!(device, 1, 1, vec![0.0])?;
         // input
@@ -38,7 +42,7 @@ impl NeuralProgram {
             false,
         )?;
         // output
-        let output_shape = model.output_size();
+        let output_shape = loss_operator.expected_output_size(&model.output_size());
         let output_len = output_shape[0] * output_shape[1];
         let example_output = new_tensor_with_grad!(
             device,
@@ -52,21 +56,28 @@ impl NeuralProgram {
 
         let machine_output = model.forward(&example_input)?;
         let loss = BinaryOperator::forward(loss_operator, &example_output, &machine_output)?;
-        let tape = loss.get_tape();
         let mut instructions = vec![];
 
-        let mut processed_forward_tensors = HashSet::<usize>::new();
-        for tensor in tape.iter() {
-            let tensor_name = tensor.tensor().name();
-            if processed_forward_tensors.contains(&tensor_name) {
-                continue;
-            }
+        for tensor in loss.get_tape().iter() {
             for instruction in tensor.forward_instructions().into_iter() {
                 instructions.push(instruction);
             }
-            processed_forward_tensors.insert(tensor_name);
         }
 
+        let auxiliary_losses: Vec<Tensor> = device
+            .auxiliary_losses()
+            .iter()
+            .filter(|auxiliary_loss| {
+                instructions.iter().any(|instruction| {
+                    instruction
+                        .outputs()
+                        .iter()
+                        .any(|output| output.name() == auxiliary_loss.name())
+                })
+            })
+            .cloned()
+            .collect();
+
         // Gradient instructions
         let internal_tensors = device.internal_tensors();
         for tensor in internal_tensors.iter() {
@@ -80,37 +91,63 @@ impl NeuralProgram {
             instructions.push(inst);
         }
 
-        let mut processed_backward_tensors = HashSet::<usize>::new();
-        for tensor in tape.iter().rev() {
-            let tensor_name = tensor.tensor().name();
-            if processed_backward_tensors.contains(&tensor_name) {
-                continue;
-            }
+        for tensor in loss.get_backward_tape().iter() {
             for instruction in tensor.gradient_instructions().into_iter() {
-                let outputs: Vec<Tensor> =
-                    instruction.outputs().deref().clone().into_iter().collect();
-                let outputs: Vec<&Tensor> = outputs.iter().collect();
-
                 instructions.push(instruction);
-
-                if clipped_gradient_norm {
-                    for output in outputs {
-                        instructions.push(gradient_instruction!(
-                            OpCode::ClipNorm,
-                            OperatorAttributes::None,
-                            &[output],
-                            &[output],
-                        ));
-                    }
-                }
             }
-            processed_backward_tensors.insert(tensor_name);
         }
 
         // Optimization instructions
         let parameter_tensors = device.parameter_tensors();
-        let mut optimizer_instructions = optimizer.optimize(device, &parameter_tensors)?;
-        instructions.append(&mut optimizer_instructions);
+
+        // Global gradient norm clipping
+        let gradient_norm = match max_gradient_norm {
+            Some(max_gradient_norm) => {
+                let max_gradient_norm = new_tensor!(device, 1, 1, vec![max_gradient_norm])?;
+                let gradient_norm = new_tensor!(device, 1, 1, vec![0.0])?;
+                let gradients: Vec<Tensor> = parameter_tensors
+                    .iter()
+                    .map(|tensor| tensor.gradient().clone())
+                    .collect();
+                let inputs: Vec<&Tensor> = [&max_gradient_norm]
+                    .into_iter()
+                    .chain(gradients.iter())
+                    .collect();
+                let outputs: Vec<&Tensor> = [&gradient_norm]
+                    .into_iter()
+                    .chain(gradients.iter())
+                    .collect();
+                instructions.push(instruction!(
+                    OpCode::ClipGlobalNorm,
+                    OperatorAttributes::None,
+                    &inputs,
+                    &outputs,
+                    Category::Optimization,
+                ));
+                Some(gradient_norm)
+            }
+            None => None,
+        };
+
+        let grouped_parameter_tensors = split_parameters(parameter_groups, &parameter_tensors);
+        for (index, tensors) in grouped_parameter_tensors.iter().enumerate() {
+            if tensors.is_empty() {
+                continue;
+            }
+            let mut optimizer_instructions = match parameter_groups.get(index) {
+                Some(group) => match optimizer.for_parameter_group(group) {
+                    Some(group_optimizer) => group_optimizer.optimize(device, tensors)?,
+                    None => {
+                        if group.learning_rate.is_some() || group.weight_decay.is_some() {
+                            return Err(error!(ErrorEnum::UnsupportedOperation));
+                        }
+                        optimizer.optimize(device, tensors)?
+                    }
+                },
+                None => optimizer.optimize(device, tensors)?,
+            };
+            instructions.append(&mut optimizer_instructions);
+        }
 
         for tensor in parameter_tensors.iter() {
             let inst = instruction!(
@@ -128,6 +165,8 @@ impl NeuralProgram {
             example_output,
             machine_output,
             loss,
+            gradient_norm,
+            auxiliary_losses,
             instructions,
         };
         Ok(program)
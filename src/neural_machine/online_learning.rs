@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+/// Configuration of the online learning mode of a NeuralMachine.
+/// In online learning mode, every inference in training mode is followed by
+/// the loss, the gradient and, eventually, the optimization.
+/// The expected output of a row of the input is the token of the next row,
+/// i.e. the next observed token. The loss is a SparseSoftmaxCrossEntropyLoss.
+#[derive(Clone, Debug)]
+pub struct OnlineLearning {
+    /// Optimize once every update_frequency observed examples.
+    /// Gradients are accumulated in between.
+    pub update_frequency: usize,
+    /// Minimum duration between two optimizations.
+    /// Once update_frequency examples are accumulated, the next examples are not
+    /// learned until the duration has elapsed.
+    pub minimum_update_interval: Duration,
+    /// Maximum number of optimizations. None means no limit.
+    pub maximum_updates: Option<usize>,
+    /// Expected output of the last row, whose next token is not observed yet.
+    /// The loss must ignore it, see SparseCrossEntropyAttributes::ignore_index.
+    pub ignore_index: usize,
+}
+
+impl OnlineLearning {
+    pub fn new(update_frequency: usize, ignore_index: usize) -> Self {
+        Self {
+            update_frequency,
+            minimum_update_interval: Duration::ZERO,
+            maximum_updates: None,
+            ignore_index,
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use std::f32::consts::PI;
+
+use crate::devices::Device;
+use crate::opcode::OpCode;
+use crate::stream::DeviceStream;
+use crate::{
+    error, gradient_instruction, inference_instruction, new_tensor, new_tensor_with_grad,
+    tensor::{Error, ErrorEnum, Tensor},
+    DeviceTrait, ExecutableOperator, OperatorAttributes, TensorWithGrad, UnaryOperator,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Activation functions that are applied to each element.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ActivationFunction {
+    /// https://onnx.ai/onnx/operators/onnx__Relu.html
+    Relu,
+    /// https://onnx.ai/onnx/operators/onnx__LeakyRelu.html
+    LeakyRelu { alpha: f32 },
+    /// https://onnx.ai/onnx/operators/onnx__Tanh.html
+    Tanh,
+    /// Also known as Swish.
+    /// See:
+    /// Sigmoid-Weighted Linear Units for Neural Network Function Approximation in Reinforcement Learning
+    /// https://arxiv.org/abs/1702.03118
+    Silu,
+    /// https://onnx.ai/onnx/operators/onnx__Elu.html
+    Elu { alpha: f32 },
+    /// https://onnx.ai/onnx/operators/onnx__Softplus.html
+    Softplus,
+    /// https://onnx.ai/onnx/operators/onnx__Gelu.html with approximate = "tanh"
+    GeluTanh,
+}
+
+impl ActivationFunction {
+    pub fn from_attributes(attributes: &OperatorAttributes) -> Result<&Self, Error> {
+        match attributes {
+            OperatorAttributes::Activation(function) => Ok(function),
+            _ => Err(error!(ErrorEnum::UnsupportedOperation)),
+        }
+    }
+
+    /// Identifier of the function in the CUDA kernels.
+    pub fn code(&self) -> i32 {
+        match self {
+            ActivationFunction::Relu => 0,
+            ActivationFunction::LeakyRelu { .. } => 1,
+            ActivationFunction::Tanh => 2,
+            ActivationFunction::Silu => 3,
+            ActivationFunction::Elu { .. } => 4,
+            ActivationFunction::Softplus => 5,
+            ActivationFunction::GeluTanh => 6,
+        }
+    }
+
+    /// Parameter of the function in the CUDA kernels.
+    pub fn alpha(&self) -> f32 {
+        match *self {
+            ActivationFunction::LeakyRelu { alpha } | ActivationFunction::Elu { alpha } => alpha,
+            _ => 0.0,
+        }
+    }
+
+    pub fn apply(&self, x: f32) -> f32 {
+        match *self {
+            ActivationFunction::Relu => x.max(0.0),
+            ActivationFunction::LeakyRelu { alpha } => {
+                if x >= 0.0 {
+                    x
+                } else {
+                    alpha * x
+                }
+            }
+            ActivationFunction::Tanh => x.tanh(),
+            ActivationFunction::Silu => x * sigmoid(x),
+            ActivationFunction::Elu { alpha } => {
+                if x > 0.0 {
+                    x
+                } else {
+                    alpha * (x.exp() - 1.0)
+                }
+            }
+            // ln(1 + exp(x)) without overflow.
+            ActivationFunction::Softplus => x.max(0.0) + (-x.abs()).exp().ln_1p(),
+            ActivationFunction::GeluTanh => 0.5 * x * (1.0 + gelu_tanh_inner(x).tanh()),
+        }
+    }
+
+    /// Derivative with respect to the input.
+    pub fn derivative(&self, x: f32) -> f32 {
+        match *self {
+            ActivationFunction::Relu => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            ActivationFunction::LeakyRelu { alpha } => {
+                if x >= 0.0 {
+                    1.0
+                } else {
+                    alpha
+                }
+            }
+            ActivationFunction::Tanh => 1.0 - x.tanh().powi(2),
+            ActivationFunction::Silu => {
+                let s = sigmoid(x);
+                s * (1.0 + x * (1.0 - s))
+            }
+            ActivationFunction::Elu { alpha } => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    alpha * x.exp()
+                }
+            }
+            ActivationFunction::Softplus => sigmoid(x),
+            ActivationFunction::GeluTanh => {
+                let t = gelu_tanh_inner(x).tanh();
+                let inner_derivative = (2.0 / PI).sqrt() * (1.0 + 3.0 * 0.044715 * x * x);
+                0.5 * (1.0 + t) + 0.5 * x * (1.0 - t * t) * inner_derivative
+            }
+        }
+    }
+
+    pub fn activation(&self, input: &[f32], output: &mut [f32]) {
+        for (x, y) in input.iter().zip(output.iter_mut()) {
+            *y = self.apply(*x);
+        }
+    }
+
+    pub fn activation_derivative(&self, input: &[f32], output: &mut [f32]) {
+        for (x, y) in input.iter().zip(output.iter_mut()) {
+            *y = self.derivative(*x);
+        }
+    }
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// sqrt(2 / π) * (x + 0.044715 * x^3)
+fn gelu_tanh_inner(x: f32) -> f32 {
+    (2.0 / PI).sqrt() * (x + 0.044715 * x * x * x)
+}
+
+pub struct Activation {
+    device: Device,
+    function: ActivationFunction,
+}
+
+impl Activation {
+    pub fn new(device: &Device, function: ActivationFunction) -> Self {
+        Self {
+            device: device.clone(),
+            function,
+        }
+    }
+}
+
+impl ExecutableOperator for Activation {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let function = ActivationFunction::from_attributes(attributes)?;
+        let input = inputs[0];
+        let output = outputs[0];
+        device.activation(function, input, output, device_stream)
+    }
+}
+
+impl UnaryOperator for Activation {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let input_t: &Tensor = &input.tensor();
+        let rows = input_t.rows();
+        let cols = input_t.cols();
+        let len = rows * cols;
+        let output = new_tensor_with_grad!(
+            self.device,
+            rows,
+            cols,
+            vec![0.0; len],
+            &[input],
+            true,
+            false
+        )?;
+        let attributes = OperatorAttributes::Activation(self.function);
+
+        output.push_instruction(inference_instruction!(
+            OpCode::Activation,
+            attributes.clone(),
+            &[input_t],
+            &[&output.tensor()],
+        ));
+
+        if input.gradient().requires_grad() {
+            let device = &self.device;
+            let layer_f_derivative = new_tensor!(device, rows, cols, vec![0.0; len])?;
+            output.push_instruction(gradient_instruction!(
+                OpCode::ActivationDerivative,
+                attributes,
+                &[input_t],
+                &[&layer_f_derivative],
+            ));
+            let tmp = new_tensor!(device, rows, cols, vec![0.0; len])?;
+            output.push_instruction(gradient_instruction!(
+                OpCode::Mul,
+                OperatorAttributes::None,
+                &[&output.gradient(), &layer_f_derivative],
+                &[&tmp],
+            ));
+            output.push_instruction(gradient_instruction!(
+                OpCode::Add,
+                OperatorAttributes::None,
+                &[&tmp, &input.gradient()],
+                &[&input.gradient()],
+            ));
+        }
+
+        Ok(output)
+    }
+}
+
+pub struct ActivationDerivative {}
+
+impl ExecutableOperator for ActivationDerivative {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let function = ActivationFunction::from_attributes(attributes)?;
+        let input = inputs[0];
+        let output = outputs[0];
+        device.activation_derivative(function, input, output, device_stream)
+    }
+}
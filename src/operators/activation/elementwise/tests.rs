@@ -0,0 +1,72 @@
+use more_asserts::assert_le;
+
+use crate::{
+    elementwise::{Activation, ActivationDerivative, ActivationFunction},
+    new_tensor,
+    stream::StreamTrait,
+    Device, ExecutableOperator, OperatorAttributes,
+};
+
+fn functions() -> Vec<ActivationFunction> {
+    vec![
+        ActivationFunction::Relu,
+        ActivationFunction::LeakyRelu { alpha: 0.01 },
+        ActivationFunction::Tanh,
+        ActivationFunction::Silu,
+        ActivationFunction::Elu { alpha: 1.0 },
+        ActivationFunction::Softplus,
+        ActivationFunction::GeluTanh,
+    ]
+}
+
+#[test]
+fn derivatives_match_central_differences() {
+    let h = 1e-3;
+    for function in functions() {
+        for x in [-3.0, -1.5, -0.2, 0.3, 1.0, 2.5] {
+            let expected = (function.apply(x + h) - function.apply(x - h)) / (2.0 * h);
+            let actual = function.derivative(x);
+            assert_le!((expected - actual).abs(), 1e-2, "{:?} at {}", function, x);
+        }
+    }
+}
+
+#[test]
+fn activation_values() {
+    let relu = ActivationFunction::Relu;
+    assert_eq!(relu.apply(-2.0), 0.0);
+    assert_eq!(relu.apply(3.0), 3.0);
+    let leaky_relu = ActivationFunction::LeakyRelu { alpha: 0.1 };
+    assert_eq!(leaky_relu.apply(-2.0), -0.2);
+    let softplus = ActivationFunction::Softplus;
+    assert_le!((softplus.apply(0.0) - 2.0_f32.ln()).abs(), 1e-6);
+    assert_le!((softplus.apply(100.0) - 100.0).abs(), 1e-6);
+    let gelu = ActivationFunction::GeluTanh;
+    assert_le!((gelu.apply(1.0) - 0.841192).abs(), 1e-5);
+    let silu = ActivationFunction::Silu;
+    assert_le!((silu.apply(1.0) - 0.731059).abs(), 1e-5);
+}
+
+#[test]
+fn activation_operator() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let attributes = OperatorAttributes::Activation(ActivationFunction::Relu);
+    let input = new_tensor!(device, 1, 4, vec![-1.0, 2.0, -3.0, 4.0]).unwrap();
+    let output = new_tensor!(device, 1, 4, vec![0.0; 4]).unwrap();
+    let derivative = new_tensor!(device, 1, 4, vec![0.0; 4]).unwrap();
+
+    Activation::execute(&attributes, &[&input], &[&output], &device, &device_stream).unwrap();
+    ActivationDerivative::execute(
+        &attributes,
+        &[&input],
+        &[&derivative],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(output.get_values().unwrap(), vec![0.0, 2.0, 0.0, 4.0]);
+    assert_eq!(derivative.get_values().unwrap(), vec![0.0, 1.0, 0.0, 1.0]);
+}
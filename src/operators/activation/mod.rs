@@ -2,4 +2,5 @@ mod sigmoid;
 pub use sigmoid::*;
 mod softmax;
 pub use softmax::*;
+pub mod elementwise;
 pub mod gelu;
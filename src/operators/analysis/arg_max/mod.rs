@@ -0,0 +1,191 @@
+use crate::{
+    error, inference_instruction, new_tensor_with_grad,
+    opcode::OpCode,
+    stream::DeviceStream,
+    tensor::{Error, ErrorEnum, Tensor},
+    Device, DeviceTrait, ExecutableOperator, OperatorAttributes, TensorWithGrad, UnaryOperator,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Attributes of ArgMax and ArgMin.
+/// Indices are stored as f32.
+/// Without keepdims, the output is a row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArgReduce {
+    pub axis: usize,
+    pub keepdims: bool,
+    pub select_last_index: bool,
+}
+
+impl ArgReduce {
+    /// keepdims and select_last_index have their ONNX default values.
+    pub fn new(axis: usize) -> Self {
+        Self {
+            axis,
+            keepdims: true,
+            select_last_index: false,
+        }
+    }
+
+    pub fn from_attributes(attributes: &OperatorAttributes) -> Result<&ArgReduce, Error> {
+        match attributes {
+            OperatorAttributes::ArgReduce(attributes) => Ok(attributes),
+            _ => Err(error!(ErrorEnum::UnsupportedOperation)),
+        }
+    }
+
+    pub fn output_size(&self, rows: usize, cols: usize) -> Result<[usize; 2], Error> {
+        match (self.axis, self.keepdims) {
+            (0, _) => Ok([1, cols]),
+            (1, true) => Ok([rows, 1]),
+            (1, false) => Ok([1, rows]),
+            _ => Err(error!(ErrorEnum::IncorrectOperatorConfiguration)),
+        }
+    }
+
+    fn arg_reduce(
+        &self,
+        rows: usize,
+        cols: usize,
+        input: &[f32],
+        output: &mut [f32],
+        is_better: impl Fn(f32, f32) -> bool,
+    ) {
+        let (lines, len, stride) = match self.axis {
+            0 => (cols, rows, cols),
+            _ => (rows, cols, 1),
+        };
+        for (line, output) in output.iter_mut().enumerate().take(lines) {
+            let start = if self.axis == 0 { line } else { line * cols };
+            let mut best = 0;
+            let mut best_value = input[start];
+            for index in 1..len {
+                let value = input[start + index * stride];
+                if is_better(value, best_value) || (self.select_last_index && value == best_value) {
+                    best = index;
+                    best_value = value;
+                }
+            }
+            *output = best as f32;
+        }
+    }
+
+    pub fn arg_max(&self, rows: usize, cols: usize, input: &[f32], output: &mut [f32]) {
+        self.arg_reduce(rows, cols, input, output, |value, best| value > best)
+    }
+
+    pub fn arg_min(&self, rows: usize, cols: usize, input: &[f32], output: &mut [f32]) {
+        self.arg_reduce(rows, cols, input, output, |value, best| value < best)
+    }
+}
+
+fn check_sizes(attributes: &ArgReduce, input: &Tensor, output: &Tensor) -> Result<(), Error> {
+    let [rows, cols] = attributes.output_size(input.rows(), input.cols())?;
+    if output.rows() != rows || output.cols() != cols {
+        return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+    }
+    Ok(())
+}
+
+/// The output has no gradient.
+fn forward(
+    device: &Device,
+    attributes: &ArgReduce,
+    opcode: OpCode,
+    input: &TensorWithGrad,
+) -> Result<TensorWithGrad, Error> {
+    let input_t: &Tensor = &input.tensor();
+    let [rows, cols] = attributes.output_size(input_t.rows(), input_t.cols())?;
+    let output = new_tensor_with_grad!(
+        device,
+        rows,
+        cols,
+        vec![0.0; rows * cols],
+        &[input],
+        false,
+        false,
+    )?;
+    output.push_instruction(inference_instruction!(
+        opcode,
+        OperatorAttributes::ArgReduce(attributes.clone()),
+        &[input_t],
+        &[&output.tensor()],
+    ));
+    Ok(output)
+}
+
+/// https://onnx.ai/onnx/operators/onnx__ArgMax.html
+pub struct ArgMax {
+    device: Device,
+    attributes: ArgReduce,
+}
+
+impl ArgMax {
+    pub fn new(device: &Device, attributes: ArgReduce) -> Self {
+        Self {
+            device: device.clone(),
+            attributes,
+        }
+    }
+}
+
+impl ExecutableOperator for ArgMax {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = ArgReduce::from_attributes(attributes)?;
+        let input = inputs[0];
+        let output = outputs[0];
+        check_sizes(attributes, input, output)?;
+        device.arg_max(attributes, input, output, device_stream)
+    }
+}
+
+impl UnaryOperator for ArgMax {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        forward(&self.device, &self.attributes, OpCode::ArgMax, input)
+    }
+}
+
+/// https://onnx.ai/onnx/operators/onnx__ArgMin.html
+pub struct ArgMin {
+    device: Device,
+    attributes: ArgReduce,
+}
+
+impl ArgMin {
+    pub fn new(device: &Device, attributes: ArgReduce) -> Self {
+        Self {
+            device: device.clone(),
+            attributes,
+        }
+    }
+}
+
+impl ExecutableOperator for ArgMin {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = ArgReduce::from_attributes(attributes)?;
+        let input = inputs[0];
+        let output = outputs[0];
+        check_sizes(attributes, input, output)?;
+        device.arg_min(attributes, input, output, device_stream)
+    }
+}
+
+impl UnaryOperator for ArgMin {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        forward(&self.device, &self.attributes, OpCode::ArgMin, input)
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    analysis::arg_max::{ArgMax, ArgMin, ArgReduce},
+    new_tensor,
+    stream::StreamTrait,
+    Device, ExecutableOperator, OperatorAttributes,
+};
+
+fn input(device: &Device) -> crate::tensor::Tensor {
+    new_tensor!(
+        device,
+        2,
+        4,
+        vec![
+            //
+            1.0, 4.0, 4.0, 0.0, //
+            3.0, 2.0, 5.0, -1.0, //
+        ],
+    )
+    .unwrap()
+}
+
+#[test]
+fn arg_max() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let input = input(&device);
+    let output = new_tensor!(device, 2, 1, vec![0.0; 2]).unwrap();
+
+    ArgMax::execute(
+        &OperatorAttributes::ArgReduce(ArgReduce::new(1)),
+        &[&input],
+        &[&output],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(output.get_values().unwrap(), vec![1.0, 2.0]);
+}
+
+#[test]
+fn arg_max_select_last_index() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let input = input(&device);
+    let output = new_tensor!(device, 1, 2, vec![0.0; 2]).unwrap();
+    let attributes = ArgReduce {
+        axis: 1,
+        keepdims: false,
+        select_last_index: true,
+    };
+
+    ArgMax::execute(
+        &OperatorAttributes::ArgReduce(attributes),
+        &[&input],
+        &[&output],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(output.get_values().unwrap(), vec![2.0, 2.0]);
+}
+
+#[test]
+fn arg_min_axis_0() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let input = input(&device);
+    let output = new_tensor!(device, 1, 4, vec![0.0; 4]).unwrap();
+
+    ArgMin::execute(
+        &OperatorAttributes::ArgReduce(ArgReduce::new(0)),
+        &[&input],
+        &[&output],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(output.get_values().unwrap(), vec![0.0, 1.0, 0.0, 1.0]);
+}
+
+#[test]
+fn incompatible_output() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let input = input(&device);
+    let output = new_tensor!(device, 1, 2, vec![0.0; 2]).unwrap();
+
+    let result = ArgMax::execute(
+        &OperatorAttributes::ArgReduce(ArgReduce::new(1)),
+        &[&input],
+        &[&output],
+        &device,
+        &device_stream,
+    );
+    assert!(result.is_err());
+}
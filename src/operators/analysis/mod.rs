@@ -1 +1,3 @@
+pub mod arg_max;
 pub mod min;
+pub mod top_k;
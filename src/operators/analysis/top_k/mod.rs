@@ -0,0 +1,151 @@
+use crate::{
+    error, inference_instruction, new_tensor_with_grad,
+    opcode::OpCode,
+    stream::DeviceStream,
+    tensor::{Error, ErrorEnum, Tensor},
+    Device, DeviceTrait, ExecutableOperator, OperatorAttributes, TensorWithGrad,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Attributes of TopK.
+/// k is an attribute instead of an input.
+/// The k values are always sorted.
+/// Ties are broken by the lowest index.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopKAttributes {
+    pub axis: usize,
+    pub k: usize,
+    pub largest: bool,
+}
+
+impl TopKAttributes {
+    pub fn from_attributes(attributes: &OperatorAttributes) -> Result<&TopKAttributes, Error> {
+        match attributes {
+            OperatorAttributes::TopK(attributes) => Ok(attributes),
+            _ => Err(error!(ErrorEnum::UnsupportedOperation)),
+        }
+    }
+
+    pub fn output_size(&self, rows: usize, cols: usize) -> Result<[usize; 2], Error> {
+        match self.axis {
+            0 if self.k <= rows => Ok([self.k, cols]),
+            1 if self.k <= cols => Ok([rows, self.k]),
+            _ => Err(error!(ErrorEnum::IncorrectOperatorConfiguration)),
+        }
+    }
+
+    pub fn top_k(
+        &self,
+        rows: usize,
+        cols: usize,
+        input: &[f32],
+        values: &mut [f32],
+        indices: &mut [f32],
+    ) {
+        let (lines, len, stride) = match self.axis {
+            0 => (cols, rows, cols),
+            _ => (rows, cols, 1),
+        };
+        let (output_line_stride, output_stride) = match self.axis {
+            0 => (1, cols),
+            _ => (self.k, 1),
+        };
+        for line in 0..lines {
+            let start = if self.axis == 0 { line } else { line * cols };
+            let mut line_indices: Vec<usize> = (0..len).collect();
+            line_indices.sort_by(|a, b| {
+                let a = input[start + a * stride];
+                let b = input[start + b * stride];
+                match self.largest {
+                    true => b.total_cmp(&a),
+                    false => a.total_cmp(&b),
+                }
+            });
+            for (rank, index) in line_indices.into_iter().take(self.k).enumerate() {
+                let output_index = line * output_line_stride + rank * output_stride;
+                values[output_index] = input[start + index * stride];
+                indices[output_index] = index as f32;
+            }
+        }
+    }
+}
+
+/// https://onnx.ai/onnx/operators/onnx__TopK.html
+pub struct TopK {
+    device: Device,
+    attributes: TopKAttributes,
+}
+
+impl TopK {
+    pub fn new(device: &Device, attributes: TopKAttributes) -> Self {
+        Self {
+            device: device.clone(),
+            attributes,
+        }
+    }
+
+    /// Returns the values and their indices.
+    /// The outputs have no gradient.
+    pub fn forward(
+        &self,
+        input: &TensorWithGrad,
+    ) -> Result<(TensorWithGrad, TensorWithGrad), Error> {
+        let input_t: &Tensor = &input.tensor();
+        let [rows, cols] = self
+            .attributes
+            .output_size(input_t.rows(), input_t.cols())?;
+        let len = rows * cols;
+        let values = new_tensor_with_grad!(
+            self.device,
+            rows,
+            cols,
+            vec![0.0; len],
+            &[input],
+            false,
+            false,
+        )?;
+        let indices = new_tensor_with_grad!(
+            self.device,
+            rows,
+            cols,
+            vec![0.0; len],
+            &[input],
+            false,
+            false,
+        )?;
+        // Both outputs have the instruction because a tape may contain only one of them.
+        let instruction = inference_instruction!(
+            OpCode::TopK,
+            OperatorAttributes::TopK(self.attributes.clone()),
+            &[input_t],
+            &[&values.tensor(), &indices.tensor()],
+        );
+        values.push_instruction(instruction.clone());
+        indices.push_instruction(instruction);
+        Ok((values, indices))
+    }
+}
+
+impl ExecutableOperator for TopK {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = TopKAttributes::from_attributes(attributes)?;
+        let input = inputs[0];
+        let values = outputs[0];
+        let indices = outputs[1];
+        let [rows, cols] = attributes.output_size(input.rows(), input.cols())?;
+        for output in [values, indices] {
+            if output.rows() != rows || output.cols() != cols {
+                return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+            }
+        }
+        device.top_k(attributes, input, values, indices, device_stream)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{
+    analysis::top_k::{TopK, TopKAttributes},
+    new_tensor, new_tensor_with_grad,
+    stream::StreamTrait,
+    Device, ExecutableOperator, OperatorAttributes,
+};
+
+#[test]
+fn top_k() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let input = new_tensor!(
+        device,
+        2,
+        4,
+        vec![
+            //
+            1.0, 4.0, 4.0, 0.0, //
+            3.0, 2.0, 5.0, -1.0, //
+        ],
+    )
+    .unwrap();
+    let values = new_tensor!(device, 2, 2, vec![0.0; 4]).unwrap();
+    let indices = new_tensor!(device, 2, 2, vec![0.0; 4]).unwrap();
+    let attributes = TopKAttributes {
+        axis: 1,
+        k: 2,
+        largest: true,
+    };
+
+    TopK::execute(
+        &OperatorAttributes::TopK(attributes),
+        &[&input],
+        &[&values, &indices],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(values.get_values().unwrap(), vec![4.0, 4.0, 5.0, 3.0]);
+    assert_eq!(indices.get_values().unwrap(), vec![1.0, 2.0, 2.0, 0.0]);
+}
+
+#[test]
+fn top_k_smallest_axis_0() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let input = new_tensor_with_grad!(
+        device,
+        3,
+        2,
+        vec![
+            //
+            1.0, 6.0, //
+            3.0, 5.0, //
+            2.0, 4.0, //
+        ],
+        &[],
+        false,
+        false,
+    )
+    .unwrap();
+    let attributes = TopKAttributes {
+        axis: 0,
+        k: 2,
+        largest: false,
+    };
+    let top_k = TopK::new(&device, attributes);
+    let (values, indices) = top_k.forward(&input).unwrap();
+    for tensor in indices.get_tape().iter() {
+        for instruction in tensor.forward_instructions().iter() {
+            instruction.execute(&device, &device_stream).unwrap();
+        }
+    }
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(
+        values.tensor().get_values().unwrap(),
+        vec![1.0, 4.0, 2.0, 5.0]
+    );
+    assert_eq!(
+        indices.tensor().get_values().unwrap(),
+        vec![0.0, 2.0, 2.0, 1.0]
+    );
+}
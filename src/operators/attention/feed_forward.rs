@@ -0,0 +1,128 @@
+use crate::{
+    elementwise::{Activation, ActivationFunction},
+    gelu::Gelu,
+    tensor::Error,
+    BinaryOperator, Device, Linear, MixtureOfExperts, Mul, TensorWithGrad, UnaryOperator,
+    WeightsInitialization,
+};
+
+/// Position-wise feed-forward network of a Transformer.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FeedForward {
+    /// Linear, Gelu, Linear
+    #[default]
+    Gelu,
+    /// Linear(Silu(Linear(x)) * Linear(x))
+    /// See:
+    /// GLU Variants Improve Transformer
+    /// https://arxiv.org/abs/2002.05202
+    SwiGlu,
+    /// Linear(Gelu(Linear(x)) * Linear(x))
+    /// See:
+    /// GLU Variants Improve Transformer
+    /// https://arxiv.org/abs/2002.05202
+    GeGlu,
+    /// Gelu experts selected by a learned router.
+    /// See MixtureOfExperts.
+    MixtureOfExperts {
+        num_experts: usize,
+        top_k: usize,
+        load_balancing_coefficient: f32,
+    },
+}
+
+impl FeedForward {
+    pub fn try_new_operator(
+        &self,
+        device: &Device,
+        rows: usize,
+        cols: usize,
+    ) -> Result<Box<dyn UnaryOperator>, Error> {
+        let operator: Box<dyn UnaryOperator> = match self {
+            FeedForward::Gelu => Box::new(FeedForwardOperator::try_new(
+                device,
+                rows,
+                cols,
+                Box::new(Gelu::new(device)),
+                false,
+            )?),
+            FeedForward::SwiGlu => Box::new(FeedForwardOperator::try_new(
+                device,
+                rows,
+                cols,
+                Box::new(Activation::new(device, ActivationFunction::Silu)),
+                true,
+            )?),
+            FeedForward::GeGlu => Box::new(FeedForwardOperator::try_new(
+                device,
+                rows,
+                cols,
+                Box::new(Activation::new(device, ActivationFunction::GeluTanh)),
+                true,
+            )?),
+            FeedForward::MixtureOfExperts {
+                num_experts,
+                top_k,
+                load_balancing_coefficient,
+            } => Box::new(MixtureOfExperts::try_new(
+                device,
+                rows,
+                cols,
+                *num_experts,
+                *top_k,
+                *load_balancing_coefficient,
+            )?),
+        };
+        Ok(operator)
+    }
+}
+
+/// Linear(activation(Linear(x))), or Linear(activation(Linear(x)) * Linear(x)) when gated.
+pub struct FeedForwardOperator {
+    linear_1: Linear,
+    activation: Box<dyn UnaryOperator>,
+    gated_value: Option<(Linear, Mul)>,
+    linear_2: Linear,
+}
+
+impl FeedForwardOperator {
+    fn try_new(
+        device: &Device,
+        rows: usize,
+        cols: usize,
+        activation: Box<dyn UnaryOperator>,
+        gated: bool,
+    ) -> Result<Self, Error> {
+        let linear_1 = Linear::new(device, cols, cols, WeightsInitialization::Kaiming, rows)?;
+        let gated_value = match gated {
+            true => Some((
+                Linear::new(device, cols, cols, WeightsInitialization::Kaiming, rows)?,
+                Mul::new(device),
+            )),
+            false => None,
+        };
+        let linear_2 = Linear::new(device, cols, cols, WeightsInitialization::Kaiming, rows)?;
+        let op = Self {
+            linear_1,
+            activation,
+            gated_value,
+            linear_2,
+        };
+        Ok(op)
+    }
+}
+
+impl UnaryOperator for FeedForwardOperator {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let lin_1 = self.linear_1.forward(input)?;
+        let activated = self.activation.forward(&lin_1)?;
+        let hidden = match &self.gated_value {
+            Some((linear, mul)) => {
+                let value = linear.forward(input)?;
+                mul.forward(&activated, &value)?
+            }
+            None => activated,
+        };
+        self.linear_2.forward(&hidden)
+    }
+}
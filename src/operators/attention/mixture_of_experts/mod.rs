@@ -0,0 +1,383 @@
+use crate::{
+    error, gradient_instruction, inference_instruction, new_tensor, new_tensor_with_grad,
+    opcode::OpCode,
+    stream::DeviceStream,
+    tensor::{Error, ErrorEnum, Tensor},
+    Add, BinaryOperator, Device, DeviceTrait, ExecutableOperator, FeedForward, Linear, MatMul, Mul,
+    OperatorAttributes, TensorWithGrad, UnaryOperator, WeightsInitialization,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Attributes of TopKGating.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GatingAttributes {
+    pub top_k: usize,
+    /// Coefficient of the load-balancing auxiliary loss.
+    pub load_balancing_coefficient: f32,
+}
+
+impl GatingAttributes {
+    pub fn from_attributes(attributes: &OperatorAttributes) -> Result<&Self, Error> {
+        match attributes {
+            OperatorAttributes::Gating(attributes) => Ok(attributes),
+            _ => Err(error!(ErrorEnum::UnsupportedOperation)),
+        }
+    }
+
+    /// Indices of the top_k largest logits of a row.
+    /// Ties are broken by the lowest index.
+    fn selected(&self, row: &[f32]) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..row.len()).collect();
+        indices.sort_by(|a, b| row[*b].total_cmp(&row[*a]).then(a.cmp(b)));
+        indices.truncate(self.top_k);
+        indices
+    }
+
+    fn softmax(row: &[f32], output: &mut [f32]) {
+        let max = row.iter().fold(f32::NEG_INFINITY, |max, x| max.max(*x));
+        for (output, x) in output.iter_mut().zip(row.iter()) {
+            *output = f32::exp(x - max);
+        }
+        let sum: f32 = output.iter().sum();
+        for output in output.iter_mut() {
+            *output /= sum;
+        }
+    }
+
+    /// Router probabilities, selected experts of each row,
+    /// and fraction of the selections that go to each expert.
+    fn route(&self, experts: usize, logits: &[f32]) -> (Vec<f32>, Vec<Vec<usize>>, Vec<f32>) {
+        let rows = logits.len() / experts;
+        let mut probabilities = vec![0.0; logits.len()];
+        let mut selections = vec![];
+        let mut fractions = vec![0.0; experts];
+        for (row, probabilities) in logits
+            .chunks(experts)
+            .zip(probabilities.chunks_mut(experts))
+        {
+            Self::softmax(row, probabilities);
+            let selected = self.selected(row);
+            for expert in selected.iter() {
+                fractions[*expert] += 1.0 / (rows * self.top_k) as f32;
+            }
+            selections.push(selected);
+        }
+        (probabilities, selections, fractions)
+    }
+
+    /// The gates of a row are the softmax of its top_k logits, the other gates are 0.
+    /// aux_loss = coefficient * experts * Σ_e f_e * P_e
+    /// where f_e is the fraction of the selections that go to expert e
+    /// and P_e is the mean router probability of expert e.
+    pub fn gating(
+        &self,
+        experts: usize,
+        logits: &[f32],
+        gates: &mut [f32],
+        aux_loss: &mut [f32],
+    ) -> Result<(), Error> {
+        if self.top_k == 0 || self.top_k > experts {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        let rows = logits.len() / experts;
+        let (probabilities, selections, fractions) = self.route(experts, logits);
+        gates.fill(0.0);
+        for ((row, gates), selected) in logits
+            .chunks(experts)
+            .zip(gates.chunks_mut(experts))
+            .zip(selections.iter())
+        {
+            let selected_logits: Vec<f32> = selected.iter().map(|expert| row[*expert]).collect();
+            let mut selected_gates = vec![0.0; selected.len()];
+            Self::softmax(&selected_logits, &mut selected_gates);
+            for (expert, gate) in selected.iter().zip(selected_gates.iter()) {
+                gates[*expert] = *gate;
+            }
+        }
+        let mut balance = 0.0;
+        for (expert, fraction) in fractions.iter().enumerate() {
+            let mean_probability: f32 = probabilities
+                .iter()
+                .skip(expert)
+                .step_by(experts)
+                .sum::<f32>()
+                / rows as f32;
+            balance += fraction * mean_probability;
+        }
+        aux_loss[0] = self.load_balancing_coefficient * experts as f32 * balance;
+        Ok(())
+    }
+
+    /// Gradient of the logits, added to logits_gradient.
+    /// Only the selected logits receive the gradient of the gates.
+    /// All the logits receive the gradient of the auxiliary loss,
+    /// for which the fractions f_e are constants.
+    pub fn gating_gradient(
+        &self,
+        experts: usize,
+        logits: &[f32],
+        gates_gradient: &[f32],
+        logits_gradient: &mut [f32],
+    ) -> Result<(), Error> {
+        if self.top_k == 0 || self.top_k > experts {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        let rows = logits.len() / experts;
+        let (probabilities, selections, fractions) = self.route(experts, logits);
+        let aux_scale = self.load_balancing_coefficient * experts as f32 / rows as f32;
+        for (((row, probabilities), (gates_gradient, logits_gradient)), selected) in logits
+            .chunks(experts)
+            .zip(probabilities.chunks(experts))
+            .zip(
+                gates_gradient
+                    .chunks(experts)
+                    .zip(logits_gradient.chunks_mut(experts)),
+            )
+            .zip(selections.iter())
+        {
+            // d aux / d z_j = aux_scale * p_j * (f_j - Σ_e f_e * p_e)
+            let balance: f32 = fractions
+                .iter()
+                .zip(probabilities.iter())
+                .map(|(f, p)| f * p)
+                .sum();
+            for ((gradient, p), f) in logits_gradient
+                .iter_mut()
+                .zip(probabilities.iter())
+                .zip(fractions.iter())
+            {
+                *gradient += aux_scale * p * (f - balance);
+            }
+            // d gates / d z_i = g_i * (u_i - Σ_j g_j * u_j) for the selected experts
+            let selected_logits: Vec<f32> = selected.iter().map(|expert| row[*expert]).collect();
+            let mut selected_gates = vec![0.0; selected.len()];
+            Self::softmax(&selected_logits, &mut selected_gates);
+            let weighted: f32 = selected
+                .iter()
+                .zip(selected_gates.iter())
+                .map(|(expert, gate)| gate * gates_gradient[*expert])
+                .sum();
+            for (expert, gate) in selected.iter().zip(selected_gates.iter()) {
+                logits_gradient[*expert] += gate * (gates_gradient[*expert] - weighted);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Not ONNX-compliant
+/// Top-k softmax gating of the router of a mixture of experts.
+/// The outputs are the gates and the load-balancing auxiliary loss.
+/// See:
+/// Outrageously Large Neural Networks: The Sparsely-Gated Mixture-of-Experts Layer
+/// https://arxiv.org/abs/1701.06538
+pub struct TopKGating {
+    device: Device,
+    attributes: GatingAttributes,
+}
+
+impl TopKGating {
+    pub fn try_new(
+        device: &Device,
+        experts: usize,
+        attributes: GatingAttributes,
+    ) -> Result<Self, Error> {
+        if attributes.top_k == 0 || attributes.top_k > experts {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        Ok(Self {
+            device: device.clone(),
+            attributes,
+        })
+    }
+
+    /// Returns the gates and the auxiliary loss.
+    /// The gradient of the auxiliary loss is added to the gradient of the logits,
+    /// so the auxiliary loss does not need to be part of the loss of the model.
+    /// The auxiliary loss is registered in Device::auxiliary_losses, so that it can be logged.
+    pub fn forward(&self, logits: &TensorWithGrad) -> Result<(TensorWithGrad, Tensor), Error> {
+        let logits_t: &Tensor = &logits.tensor();
+        let rows = logits_t.rows();
+        let cols = logits_t.cols();
+        let gates = new_tensor_with_grad!(
+            self.device,
+            rows,
+            cols,
+            vec![0.0; rows * cols],
+            &[logits],
+            true,
+            false,
+        )?;
+        let aux_loss = new_tensor!(self.device, 1, 1, vec![0.0])?;
+        self.device.add_auxiliary_loss(&aux_loss);
+        let attributes = OperatorAttributes::Gating(self.attributes.clone());
+
+        gates.push_instruction(inference_instruction!(
+            OpCode::TopKGating,
+            attributes.clone(),
+            &[logits_t],
+            &[&gates.tensor(), &aux_loss],
+        ));
+
+        let logits_gradient: &Tensor = &logits.gradient();
+        if logits_gradient.requires_grad() {
+            gates.push_instruction(gradient_instruction!(
+                OpCode::TopKGatingGradient,
+                attributes,
+                &[logits_t, &gates.gradient()],
+                &[logits_gradient],
+            ));
+        }
+
+        Ok((gates, aux_loss))
+    }
+}
+
+fn check_shapes(logits: &Tensor, gates: &Tensor) -> Result<(), Error> {
+    if *logits.size() != *gates.size() {
+        return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+    }
+    Ok(())
+}
+
+impl ExecutableOperator for TopKGating {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = GatingAttributes::from_attributes(attributes)?;
+        let logits = inputs[0];
+        let gates = outputs[0];
+        let aux_loss = outputs[1];
+        check_shapes(logits, gates)?;
+        device.top_k_gating(attributes, logits, gates, aux_loss, device_stream)
+    }
+}
+
+pub struct TopKGatingGradient {}
+
+impl ExecutableOperator for TopKGatingGradient {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = GatingAttributes::from_attributes(attributes)?;
+        let logits = inputs[0];
+        let gates_gradient = inputs[1];
+        let logits_gradient = outputs[0];
+        check_shapes(logits, gates_gradient)?;
+        check_shapes(logits, logits_gradient)?;
+        device.top_k_gating_gradient(
+            attributes,
+            logits,
+            gates_gradient,
+            logits_gradient,
+            device_stream,
+        )
+    }
+}
+
+/// Mixture of experts that replaces the feed-forward network of a Transformer.
+/// A learned router selects top_k experts for each row.
+///
+/// The instruction streams are static, so every expert computes every row and
+/// the output of an expert is multiplied by its gate, which is 0 for the rows
+/// that did not select it. The gradient therefore only flows through the selected experts.
+///
+/// See:
+/// Switch Transformers: Scaling to Trillion Parameter Models with Simple and Efficient Sparsity
+/// https://arxiv.org/abs/2101.03961
+pub struct MixtureOfExperts {
+    router: Linear,
+    gating: TopKGating,
+    experts: Vec<Box<dyn UnaryOperator>>,
+    /// Constant matrices that broadcast the gate of an expert to the columns of its output.
+    broadcasts: Vec<TensorWithGrad>,
+    matmul: MatMul,
+    mul: Mul,
+    add: Add,
+}
+
+impl MixtureOfExperts {
+    pub fn try_new(
+        device: &Device,
+        rows: usize,
+        cols: usize,
+        num_experts: usize,
+        top_k: usize,
+        load_balancing_coefficient: f32,
+    ) -> Result<Self, Error> {
+        let router = Linear::new(
+            device,
+            num_experts,
+            cols,
+            WeightsInitialization::Kaiming,
+            rows,
+        )?;
+        let attributes = GatingAttributes {
+            top_k,
+            load_balancing_coefficient,
+        };
+        let gating = TopKGating::try_new(device, num_experts, attributes)?;
+        let experts = (0..num_experts)
+            .map(|_| FeedForward::Gelu.try_new_operator(device, rows, cols))
+            .collect::<Result<Vec<_>, _>>()?;
+        let broadcasts = (0..num_experts)
+            .map(|expert| {
+                let mut values = vec![0.0; cols * num_experts];
+                for row in 0..cols {
+                    values[row * num_experts + expert] = 1.0;
+                }
+                new_tensor_with_grad!(device, cols, num_experts, values, &[], false, false)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let transb = true;
+        Ok(Self {
+            router,
+            gating,
+            experts,
+            broadcasts,
+            matmul: MatMul::new(device, transb),
+            mul: Mul::new(device),
+            add: Add::new(device),
+        })
+    }
+
+    /// Returns the output and the load-balancing auxiliary loss.
+    pub fn forward_with_aux_loss(
+        &self,
+        input: &TensorWithGrad,
+    ) -> Result<(TensorWithGrad, Tensor), Error> {
+        let logits = self.router.forward(input)?;
+        let (gates, aux_loss) = self.gating.forward(&logits)?;
+        let mut output: Option<TensorWithGrad> = None;
+        for (expert, broadcast) in self.experts.iter().zip(self.broadcasts.iter()) {
+            let gate = self.matmul.forward(&gates, broadcast)?;
+            let expert_output = expert.forward(input)?;
+            let weighted = self.mul.forward(&gate, &expert_output)?;
+            output = match output {
+                Some(sum) => Some(self.add.forward(&sum, &weighted)?),
+                None => Some(weighted),
+            };
+        }
+        match output {
+            Some(output) => Ok((output, aux_loss)),
+            None => Err(error!(ErrorEnum::IncorrectOperatorConfiguration)),
+        }
+    }
+}
+
+impl UnaryOperator for MixtureOfExperts {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let (output, _) = self.forward_with_aux_loss(input)?;
+        Ok(output)
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{
+    new_tensor_with_grad, stream::StreamTrait, tensor::Tensor, Device, GatingAttributes,
+    MixtureOfExperts,
+};
+
+fn assert_close(actual: &[f32], expected: &[f32], tolerance: f32) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected.iter()) {
+        assert!(
+            (actual - expected).abs() < tolerance,
+            "actual {}, expected {}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn gates_of_top_k_experts() {
+    let attributes = GatingAttributes {
+        top_k: 2,
+        load_balancing_coefficient: 0.5,
+    };
+    let logits = [2.0, 1.0, 0.0, -1.0, 0.0, 0.0, 0.0, 0.0];
+    let mut gates = [0.0; 8];
+    let mut aux_loss = [0.0];
+    attributes
+        .gating(4, &logits, &mut gates, &mut aux_loss)
+        .unwrap();
+    let e = f32::exp(1.0);
+    assert_close(
+        &gates,
+        &[e / (e + 1.0), 1.0 / (e + 1.0), 0.0, 0.0, 0.5, 0.5, 0.0, 0.0],
+        1e-6,
+    );
+
+    // A uniform router gives an auxiliary loss equal to its coefficient.
+    let uniform = [0.0; 8];
+    attributes
+        .gating(4, &uniform, &mut gates, &mut aux_loss)
+        .unwrap();
+    assert_close(&aux_loss, &[0.5], 1e-6);
+
+    let too_many = GatingAttributes {
+        top_k: 5,
+        load_balancing_coefficient: 0.0,
+    };
+    assert!(too_many
+        .gating(4, &logits, &mut gates, &mut aux_loss)
+        .is_err());
+}
+
+#[test]
+fn gating_gradient_matches_finite_differences() {
+    let attributes = GatingAttributes {
+        top_k: 2,
+        load_balancing_coefficient: 0.1,
+    };
+    let experts = 4;
+    let logits = vec![0.3, -0.2, 1.1, 0.5, -0.7, 0.9, 0.1, 0.4];
+    let weights = [0.5, -1.0, 2.0, 0.25, 1.5, -0.5, 0.75, -2.0];
+    // objective = Σ weights * gates + aux_loss
+    let objective = |logits: &[f32]| {
+        let mut gates = vec![0.0; logits.len()];
+        let mut aux_loss = [0.0];
+        attributes
+            .gating(experts, logits, &mut gates, &mut aux_loss)
+            .unwrap();
+        let weighted: f32 = gates.iter().zip(weights.iter()).map(|(g, w)| g * w).sum();
+        weighted + aux_loss[0]
+    };
+    // The gradient is accumulated.
+    let mut gradient = vec![1.0; logits.len()];
+    attributes
+        .gating_gradient(experts, &logits, &weights, &mut gradient)
+        .unwrap();
+
+    let epsilon = 1e-3;
+    let expected: Vec<f32> = (0..logits.len())
+        .map(|i| {
+            let mut plus = logits.clone();
+            plus[i] += epsilon;
+            let mut minus = logits.clone();
+            minus[i] -= epsilon;
+            1.0 + (objective(&plus) - objective(&minus)) / (2.0 * epsilon)
+        })
+        .collect();
+    assert_close(&gradient, &expected, 1e-3);
+}
+
+#[test]
+fn only_selected_experts_receive_gradient() {
+    let device = Device::default();
+    let num_experts = 3;
+    let mixture_of_experts =
+        MixtureOfExperts::try_new(&device, 1, 4, num_experts, 1, 0.01).unwrap();
+    let input = new_tensor_with_grad!(device, 1, 4, vec![0.5, -0.25, 1.0, 0.75], &[], false, false)
+        .unwrap();
+    let (output, aux_loss) = mixture_of_experts.forward_with_aux_loss(&input).unwrap();
+    assert_eq!(*output.tensor().size(), [1, 4]);
+
+    let device_stream = device.new_stream().unwrap();
+    for tensor in output.get_tape().iter() {
+        tensor.forward(&device, &device_stream).unwrap();
+    }
+    output.gradient().set_values(vec![1.0; 4]).unwrap();
+    for tensor in output.get_backward_tape().iter() {
+        tensor.compute_gradient(&device, &device_stream).unwrap();
+    }
+    device_stream.wait_for().unwrap();
+    assert!(aux_loss.get_values().unwrap()[0] > 0.0);
+
+    // The parameters are the router weights and biases,
+    // followed by the weights and biases of the 2 Linear of each expert.
+    let parameters = device.parameter_tensors();
+    assert_eq!(parameters.len(), 2 + num_experts * 4);
+    let experts_with_gradient = parameters[2..]
+        .chunks(4)
+        .filter(|expert| {
+            expert.iter().any(|parameter| {
+                let gradient: &Tensor = &parameter.gradient();
+                gradient.get_values().unwrap().iter().any(|x| *x != 0.0)
+            })
+        })
+        .count();
+    assert_eq!(experts_with_gradient, 1);
+}
@@ -4,4 +4,8 @@ mod attention_head;
 pub use attention_head::*;
 mod multi_head_attention;
 pub use multi_head_attention::*;
+mod feed_forward;
+pub use feed_forward::*;
+mod mixture_of_experts;
+pub use mixture_of_experts::*;
 pub mod transformer;
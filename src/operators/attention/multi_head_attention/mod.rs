@@ -0,0 +1,156 @@
+use crate::{
+    error,
+    tensor::{Error, ErrorEnum},
+    Concat, Device, Linear, NaryOperator, ScaledDotProductAttention, TensorWithGrad,
+    TernaryOperator, UnaryOperator, WeightsInitialization,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Query heads are split in num_kv_heads groups.
+/// The query heads of a group share the same key and value projections.
+/// With num_kv_heads = num_heads, this is multi-head attention (MHA).
+/// With 1 < num_kv_heads < num_heads, this is grouped-query attention (GQA).
+/// With num_kv_heads = 1, this is multi-query attention (MQA).
+///
+/// See:
+/// Attention Is All You Need
+/// https://arxiv.org/abs/1706.03762
+///
+/// See:
+/// GQA: Training Generalized Multi-Query Transformer Models from Multi-Head Checkpoints
+/// https://arxiv.org/abs/2305.13245
+pub struct MultiHeadAttention {
+    queries: Vec<Linear>,
+    keys: Vec<Linear>,
+    values: Vec<Linear>,
+    attentions: Vec<ScaledDotProductAttention>,
+    concat: Concat,
+    linear: Linear,
+}
+
+impl MultiHeadAttention {
+    pub fn try_new(
+        device: &Device,
+        rows: usize,
+        cols: usize,
+        causal_mask: bool,
+        num_heads: usize,
+        dropout_probability: f32,
+    ) -> Result<Self, Error> {
+        Self::try_new_with_kv_heads(
+            device,
+            rows,
+            cols,
+            causal_mask,
+            num_heads,
+            num_heads,
+            dropout_probability,
+        )
+    }
+
+    pub fn try_new_with_kv_heads(
+        device: &Device,
+        rows: usize,
+        cols: usize,
+        causal_mask: bool,
+        num_heads: usize,
+        num_kv_heads: usize,
+        dropout_probability: f32,
+    ) -> Result<Self, Error> {
+        if num_heads == 0
+            || num_kv_heads == 0
+            || !cols.is_multiple_of(num_heads)
+            || !num_heads.is_multiple_of(num_kv_heads)
+        {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        let head_cols = cols / num_heads;
+        let projection = || {
+            Linear::new(
+                device,
+                head_cols,
+                cols,
+                WeightsInitialization::Kaiming,
+                rows,
+            )
+        };
+        let queries = (0..num_heads)
+            .map(|_| projection())
+            .collect::<Result<Vec<_>, _>>()?;
+        let keys = (0..num_kv_heads)
+            .map(|_| projection())
+            .collect::<Result<Vec<_>, _>>()?;
+        let values = (0..num_kv_heads)
+            .map(|_| projection())
+            .collect::<Result<Vec<_>, _>>()?;
+        let attentions = (0..num_heads)
+            .map(|_| {
+                ScaledDotProductAttention::try_new(
+                    device,
+                    rows,
+                    cols,
+                    causal_mask,
+                    dropout_probability,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let concat = Concat::new(device);
+        let linear = Linear::new(device, cols, cols, WeightsInitialization::Kaiming, rows)?;
+        let multi_head_attention = Self {
+            queries,
+            keys,
+            values,
+            attentions,
+            concat,
+            linear,
+        };
+        Ok(multi_head_attention)
+    }
+
+    pub fn num_heads(&self) -> usize {
+        self.queries.len()
+    }
+
+    pub fn num_kv_heads(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+impl TernaryOperator for MultiHeadAttention {
+    fn forward(
+        &self,
+        q: &TensorWithGrad,
+        k: &TensorWithGrad,
+        v: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        let keys = self
+            .keys
+            .iter()
+            .map(|key| key.forward(k))
+            .collect::<Result<Vec<_>, _>>()?;
+        let values = self
+            .values
+            .iter()
+            .map(|value| value.forward(v))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let group_size = self.num_heads() / self.num_kv_heads();
+        let mut attention_head_attentions = vec![];
+        for (head, (query, attention)) in
+            self.queries.iter().zip(self.attentions.iter()).enumerate()
+        {
+            let group = head / group_size;
+            let query = query.forward(q)?;
+            let attentions = attention.forward(&query, &keys[group], &values[group])?;
+            attention_head_attentions.push(attentions);
+        }
+
+        let attention_head_attentions: Vec<_> = attention_head_attentions.iter().collect();
+        let concat = self.concat.forward(&attention_head_attentions)?;
+        let linear = self.linear.forward(&concat)?;
+        Ok(linear)
+    }
+}
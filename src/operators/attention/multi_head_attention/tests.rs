@@ -0,0 +1,64 @@
+use crate::{
+    new_tensor_with_grad, stream::StreamTrait, Device, MultiHeadAttention, TernaryOperator,
+};
+
+fn parameter_count(num_kv_heads: usize) -> usize {
+    let device = Device::default();
+    MultiHeadAttention::try_new_with_kv_heads(&device, 4, 16, true, 4, num_kv_heads, 0.0).unwrap();
+    let count = device
+        .parameter_tensors()
+        .iter()
+        .map(|tensor| tensor.tensor().len())
+        .sum();
+    count
+}
+
+#[test]
+fn key_value_heads_are_shared_across_query_groups() {
+    let rows = 4;
+    let cols = 16;
+    let head_cols = 4;
+    // Weights and biases of a projection.
+    let projection = head_cols * cols + rows * head_cols;
+    let output_linear = cols * cols + rows * cols;
+    // Multi-head attention.
+    assert_eq!(parameter_count(4), 12 * projection + output_linear);
+    // Grouped-query attention.
+    assert_eq!(parameter_count(2), 8 * projection + output_linear);
+    // Multi-query attention.
+    assert_eq!(parameter_count(1), 6 * projection + output_linear);
+}
+
+#[test]
+fn num_kv_heads_must_divide_num_heads() {
+    let device = Device::default();
+    assert!(MultiHeadAttention::try_new_with_kv_heads(&device, 4, 16, true, 4, 3, 0.0).is_err());
+    assert!(MultiHeadAttention::try_new_with_kv_heads(&device, 4, 16, true, 4, 0, 0.0).is_err());
+}
+
+#[test]
+fn grouped_query_attention_forward() {
+    let device = Device::default();
+    let rows = 4;
+    let cols = 16;
+    let attention =
+        MultiHeadAttention::try_new_with_kv_heads(&device, rows, cols, true, 4, 2, 0.0).unwrap();
+    let values = (0..rows * cols).map(|x| (x as f32 * 0.1).sin()).collect();
+    let input = new_tensor_with_grad!(device, rows, cols, values, &[], true, false).unwrap();
+    let output = attention.forward(&input, &input, &input).unwrap();
+
+    let device_stream = device.new_stream().unwrap();
+    for tensor in output.get_tape().iter() {
+        for instruction in tensor.forward_instructions().iter() {
+            instruction.execute(&device, &device_stream).unwrap();
+        }
+    }
+    device_stream.wait_for().unwrap();
+
+    let output = output.tensor();
+    assert_eq!(output.rows(), rows);
+    assert_eq!(output.cols(), cols);
+    for value in output.get_values().unwrap() {
+        assert!(value.is_finite());
+    }
+}
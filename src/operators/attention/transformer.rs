@@ -1,7 +1,9 @@
 use crate::{
-    gelu::Gelu, statistics::layer_norm::LayerNormalization, tensor::Error, Add, BinaryOperator,
-    Device, Dropout, Linear, MultiHeadAttention, TensorWithGrad, TernaryOperator, UnaryOperator,
-    WeightsInitialization,
+    error,
+    statistics::normalization::{Normalization, NormalizationOperator},
+    tensor::{Error, ErrorEnum},
+    Add, BinaryOperator, Device, Dropout, FeedForward, MultiHeadAttention, TensorWithGrad,
+    TernaryOperator, UnaryOperator,
 };
 
 /// See:
@@ -11,43 +13,75 @@ use crate::{
 /// See:
 /// Full GPT Architecture
 /// https://en.wikipedia.org/wiki/GPT-1#/media/File:Full_GPT_architecture.svg
+///
+/// The normalization must be row-wise, see Normalization::is_row_wise.
 pub struct Transformer {
-    layer_norm_1: LayerNormalization,
+    layer_norm_1: NormalizationOperator,
     multi_head_attention: MultiHeadAttention,
     dropout_1: Dropout,
-    layer_norm_2: LayerNormalization,
+    layer_norm_2: NormalizationOperator,
     add: Add,
-    linear_1: Linear,
-    gelu: Gelu,
-    linear_2: Linear,
+    feed_forward: Box<dyn UnaryOperator>,
     dropout_2: Dropout,
 }
 
+/// Hyperparameters of the Transformer blocks of a TransformerModel.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransformerConfig {
+    pub num_heads: usize,
+    /// Heads of the keys and values, see MultiHeadAttention::try_new_with_kv_heads.
+    pub num_kv_heads: usize,
+    pub causal_mask: bool,
+    pub dropout_probability: f32,
+    pub normalization: Normalization,
+    pub feed_forward: FeedForward,
+    /// Only used by TransformerModel, see TransformerModel.
+    pub tied_embedding: bool,
+}
+
+impl TransformerConfig {
+    /// A causal configuration with one key and value head per head, no dropout,
+    /// the default normalization and feed-forward and no tied embedding.
+    pub fn new(num_heads: usize) -> Self {
+        Self {
+            num_heads,
+            num_kv_heads: num_heads,
+            causal_mask: true,
+            dropout_probability: 0.0,
+            normalization: Normalization::default(),
+            feed_forward: FeedForward::default(),
+            tied_embedding: false,
+        }
+    }
+}
+
 impl Transformer {
     pub fn try_new(
         device: &Device,
         rows: usize,
         cols: usize,
-        causal_mask: bool,
-        num_heads: usize,
-        dropout_probability: f32,
+        config: &TransformerConfig,
     ) -> Result<Self, Error> {
-        let layer_norm_1 = LayerNormalization::try_new(device, rows, cols)?;
-        let multi_head_attention = MultiHeadAttention::try_new(
+        let normalization = &config.normalization;
+        if !normalization.is_row_wise() {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        let dropout_probability = config.dropout_probability;
+        let layer_norm_1 = normalization.try_new_operator(device, rows, cols)?;
+        let multi_head_attention = MultiHeadAttention::try_new_with_kv_heads(
             device,
             rows,
             cols,
-            causal_mask,
-            num_heads,
+            config.causal_mask,
+            config.num_heads,
+            config.num_kv_heads,
             dropout_probability,
         )?;
         let dropout_1 = Dropout::try_new(device, rows, cols, dropout_probability)?;
         let add = Add::new(device);
-        let layer_norm_2 = LayerNormalization::try_new(device, rows, cols)?;
+        let layer_norm_2 = normalization.try_new_operator(device, rows, cols)?;
 
-        let linear_1 = Linear::new(device, cols, cols, WeightsInitialization::Kaiming, rows)?;
-        let gelu = Gelu::new(device);
-        let linear_2 = Linear::new(device, cols, cols, WeightsInitialization::Kaiming, rows)?;
+        let feed_forward = config.feed_forward.try_new_operator(device, rows, cols)?;
         let dropout_2 = Dropout::try_new(device, rows, cols, dropout_probability)?;
 
         let transformer = Self {
@@ -56,9 +90,7 @@ impl Transformer {
             dropout_1,
             layer_norm_2,
             add,
-            linear_1,
-            gelu,
-            linear_2,
+            feed_forward,
             dropout_2,
         };
         Ok(transformer)
@@ -76,10 +108,8 @@ impl UnaryOperator for Transformer {
         let with_dropout_1 = self.dropout_1.forward(&attended)?;
         let residual_1 = self.add.forward(&with_dropout_1, &input)?;
         let normalized_output = self.layer_norm_2.forward(&residual_1)?;
-        let lin_1 = self.linear_1.forward(&normalized_output)?;
-        let activated = self.gelu.forward(&lin_1)?;
-        let lin_2 = self.linear_2.forward(&activated)?;
-        let with_dropout_2 = self.dropout_2.forward(&lin_2)?;
+        let fed_forward = self.feed_forward.forward(&normalized_output)?;
+        let with_dropout_2 = self.dropout_2.forward(&fed_forward)?;
         let residual_2 = self.add.forward(&with_dropout_2, &normalized_output)?;
         Ok(residual_2)
     }
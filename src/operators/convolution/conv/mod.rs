@@ -0,0 +1,214 @@
+use crate::{
+    error,
+    gather::Gather,
+    new_tensor_with_grad,
+    tensor::{Error, ErrorEnum},
+    Add, BinaryOperator, Concat, Device, FanMode, Im2Col, MatMul, NaryOperator, Reshape,
+    SlidingWindow, TensorWithGrad, UnaryOperator, WeightsInitialization,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// https://onnx.ai/onnx/operators/onnx__Conv.html
+/// The convolution is a Gemm of the weights and of the columns of Im2Col.
+/// Each group has its own weights and only multiplies the rows of the columns of its input channels.
+/// Output channels are rows.
+pub struct Conv {
+    /// The weights of each group.
+    weights: Vec<TensorWithGrad>,
+    biases: TensorWithGrad,
+    ones: TensorWithGrad,
+    groups: Option<Groups>,
+    im2col: Im2Col,
+    matmul: MatMul,
+    add: Add,
+}
+
+/// Operators and indices that split the columns by group and
+/// put the output channels of the groups back in order.
+struct Groups {
+    /// For each group, the rows of the columns of its input channels.
+    column_rows: Vec<TensorWithGrad>,
+    /// Row of each output channel once the group outputs are concatenated and reshaped.
+    output_rows: TensorWithGrad,
+    gather: Gather,
+    concat: Concat,
+    reshape: Reshape,
+}
+
+impl Groups {
+    fn try_new(
+        device: &Device,
+        groups: usize,
+        group_cols: usize,
+        group_output_channels: usize,
+        positions: usize,
+    ) -> Result<Self, Error> {
+        let column_rows = (0..groups)
+            .map(|group| {
+                let rows = (group * group_cols..(group + 1) * group_cols)
+                    .map(|row| row as f32)
+                    .collect::<Vec<_>>();
+                new_tensor_with_grad!(device, group_cols, 1, rows, &[], false, false)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Once concatenated and reshaped, the row r of the group g is at r * groups + g.
+        let output_channels = groups * group_output_channels;
+        let output_rows = (0..output_channels)
+            .map(|channel| {
+                let (group, row) = (
+                    channel / group_output_channels,
+                    channel % group_output_channels,
+                );
+                (row * groups + group) as f32
+            })
+            .collect::<Vec<_>>();
+        let output_rows =
+            new_tensor_with_grad!(device, output_channels, 1, output_rows, &[], false, false)?;
+
+        Ok(Self {
+            column_rows,
+            output_rows,
+            gather: Gather::new(device),
+            concat: Concat::new(device),
+            reshape: Reshape::new(
+                device,
+                vec![group_output_channels, groups * positions],
+                vec![output_channels, positions],
+            ),
+        })
+    }
+}
+
+impl Conv {
+    pub fn try_new(
+        device: &Device,
+        window: &SlidingWindow,
+        output_channels: usize,
+        groups: usize,
+        weights_initialization: WeightsInitialization,
+    ) -> Result<Self, Error> {
+        let im2col = Im2Col::try_new(device, window)?;
+        if groups == 0
+            || output_channels == 0
+            || !window.channels.is_multiple_of(groups)
+            || !output_channels.is_multiple_of(groups)
+        {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+
+        let kernel_len = window.kernel_shape[0] * window.kernel_shape[1];
+        let group_input_channels = window.channels / groups;
+        let group_output_channels = output_channels / groups;
+        let group_cols = group_input_channels * kernel_len;
+
+        // Kaiming uses the fan-in for Conv.
+        let weights_initialization = match weights_initialization {
+            WeightsInitialization::Kaiming => WeightsInitialization::KaimingNormal(FanMode::FanIn),
+            _ => weights_initialization,
+        };
+        let fan_in = group_cols;
+        let fan_out = group_output_channels * kernel_len;
+        // Frozen when the device has a LoraConfig.
+        let trainable = device.lora_config().is_none();
+        let weights = (0..groups)
+            .map(|_| {
+                let values = weights_initialization.initialize(
+                    &mut *device.rng(),
+                    group_output_channels,
+                    group_cols,
+                    fan_in,
+                    fan_out,
+                )?;
+                let weights = new_tensor_with_grad!(
+                    device,
+                    group_output_channels,
+                    group_cols,
+                    values,
+                    &[],
+                    trainable,
+                    trainable,
+                )?;
+                weights.add_tag("Conv");
+                weights.add_tag("weights");
+                Ok(weights)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let biases = new_tensor_with_grad!(
+            device,
+            output_channels,
+            1,
+            vec![0.0; output_channels],
+            &[],
+            trainable,
+            trainable,
+        )?;
+        biases.add_tag("Conv");
+        biases.add_tag("biases");
+
+        // The biases are broadcast to every output position with a Gemm.
+        let positions = window.output_height() * window.output_width();
+        let ones = new_tensor_with_grad!(
+            device,
+            1,
+            positions,
+            vec![1.0; positions],
+            &[],
+            false,
+            false,
+        )?;
+
+        let groups = match groups {
+            1 => None,
+            _ => Some(Groups::try_new(
+                device,
+                groups,
+                group_cols,
+                group_output_channels,
+                positions,
+            )?),
+        };
+
+        let transb = false;
+        let op = Self {
+            weights,
+            biases,
+            ones,
+            groups,
+            im2col,
+            matmul: MatMul::new(device, transb),
+            add: Add::new(device),
+        };
+        Ok(op)
+    }
+}
+
+impl UnaryOperator for Conv {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let columns = self.im2col.forward(input)?;
+        let product = match &self.groups {
+            Some(groups) => {
+                let products = self
+                    .weights
+                    .iter()
+                    .zip(groups.column_rows.iter())
+                    .map(|(weights, column_rows)| {
+                        let group_columns = groups.gather.forward(&columns, column_rows)?;
+                        self.matmul.forward(weights, &group_columns)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let products = products.iter().collect::<Vec<_>>();
+                let concatenated = groups.concat.forward(&products)?;
+                let reshaped = groups.reshape.forward(&concatenated)?;
+                groups.gather.forward(&reshaped, &groups.output_rows)?
+            }
+            None => self.matmul.forward(&self.weights[0], &columns)?,
+        };
+        let biases = self.matmul.forward(&self.biases, &self.ones)?;
+        let sum = self.add.forward(&product, &biases)?;
+        Ok(sum)
+    }
+}
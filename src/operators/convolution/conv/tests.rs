@@ -0,0 +1,165 @@
+use crate::{
+    new_tensor_with_grad, stream::StreamTrait, Conv, Device, SlidingWindow, TensorWithGrad,
+    UnaryOperator, WeightsInitialization,
+};
+
+fn run_forward(device: &Device, output: &TensorWithGrad) {
+    let device_stream = device.new_stream().unwrap();
+    for tensor in output.get_tape().iter() {
+        for instruction in tensor.forward_instructions().iter() {
+            instruction.execute(device, &device_stream).unwrap();
+        }
+    }
+    device_stream.wait_for().unwrap();
+}
+
+fn run_backward(device: &Device, output: &TensorWithGrad) {
+    let device_stream = device.new_stream().unwrap();
+    for tensor in output.get_backward_tape().iter() {
+        tensor.compute_gradient(device, &device_stream).unwrap();
+    }
+    device_stream.wait_for().unwrap();
+}
+
+#[test]
+fn conv() {
+    let device = Device::default();
+    let window = SlidingWindow::new(1, 3, 3, [2, 2]);
+    let conv = Conv::try_new(&device, &window, 2, 1, WeightsInitialization::None).unwrap();
+    conv.weights[0]
+        .tensor()
+        .set_values(vec![
+            //
+            1.0, 0.0, 0.0, 1.0, //
+            0.0, 1.0, 1.0, 0.0, //
+        ])
+        .unwrap();
+    conv.biases.tensor().set_values(vec![1.0, -1.0]).unwrap();
+    let input = new_tensor_with_grad!(
+        device,
+        1,
+        9,
+        vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0],
+        &[],
+        false,
+        false,
+    )
+    .unwrap();
+
+    let output = conv.forward(&input).unwrap();
+    run_forward(&device, &output);
+
+    assert_eq!(
+        output.tensor().get_values().unwrap(),
+        vec![
+            //
+            7.0, 9.0, 13.0, 15.0, //
+            5.0, 7.0, 11.0, 13.0, //
+        ]
+    );
+}
+
+#[test]
+fn grouped_conv() {
+    let device = Device::default();
+    let window = SlidingWindow::new(2, 3, 3, [2, 2]);
+    let conv = Conv::try_new(&device, &window, 4, 2, WeightsInitialization::None).unwrap();
+    // Each group has 2 output channels and the 4 kernel elements of 1 input channel.
+    assert_eq!(conv.weights.len(), 2);
+    conv.weights[0]
+        .tensor()
+        .set_values(vec![
+            //
+            1.0, 1.0, 1.0, 1.0, //
+            -1.0, -1.0, -1.0, -1.0, //
+        ])
+        .unwrap();
+    conv.weights[1]
+        .tensor()
+        .set_values(vec![
+            //
+            1.0, 1.0, 1.0, 1.0, //
+            2.0, 2.0, 2.0, 2.0, //
+        ])
+        .unwrap();
+    let input = new_tensor_with_grad!(
+        device,
+        2,
+        9,
+        vec![
+            //
+            1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, //
+            10.0, 20.0, 30.0, 40.0, 50.0, 60.0, 70.0, 80.0, 90.0, //
+        ],
+        &[],
+        false,
+        false,
+    )
+    .unwrap();
+
+    let output = conv.forward(&input).unwrap();
+    run_forward(&device, &output);
+
+    assert_eq!(
+        output.tensor().get_values().unwrap(),
+        vec![
+            //
+            12.0, 16.0, 24.0, 28.0, //
+            -12.0, -16.0, -24.0, -28.0, //
+            120.0, 160.0, 240.0, 280.0, //
+            240.0, 320.0, 480.0, 560.0, //
+        ]
+    );
+
+    // Each group only receives the gradient of its input channel.
+    let positions = 4;
+    output
+        .gradient()
+        .set_values(vec![1.0; 4 * positions])
+        .unwrap();
+    run_backward(&device, &output);
+    assert_eq!(
+        conv.weights[0].gradient().get_values().unwrap(),
+        [[12.0, 16.0, 24.0, 28.0]; 2].concat()
+    );
+    assert_eq!(
+        conv.weights[1].gradient().get_values().unwrap(),
+        [[120.0, 160.0, 240.0, 280.0]; 2].concat()
+    );
+    assert_eq!(
+        conv.biases.gradient().get_values().unwrap(),
+        vec![positions as f32; 4]
+    );
+}
+
+#[test]
+fn incompatible_groups() {
+    let device = Device::default();
+    let window = SlidingWindow::new(3, 3, 3, [2, 2]);
+    assert!(Conv::try_new(&device, &window, 2, 2, WeightsInitialization::None).is_err());
+}
+
+#[test]
+fn dilated_conv_with_pads_not_smaller_than_the_kernel() {
+    let device = Device::default();
+    // A 3x3 kernel with a dilation of 4 needs pads of 4 to keep the size of the image.
+    let window = SlidingWindow {
+        pads: [4, 4, 4, 4],
+        dilations: [4, 4],
+        ..SlidingWindow::new(1, 5, 5, [3, 3])
+    };
+    let conv = Conv::try_new(&device, &window, 1, 1, WeightsInitialization::None).unwrap();
+    conv.weights[0].tensor().set_values(vec![1.0; 9]).unwrap();
+    let input = new_tensor_with_grad!(device, 1, 25, vec![1.0; 25], &[], false, false).unwrap();
+
+    let output = conv.forward(&input).unwrap();
+    run_forward(&device, &output);
+
+    // The output counts the kernel elements that are in the image.
+    let counts = [2.0, 1.0, 1.0, 1.0, 2.0];
+    let expected: Vec<f32> = counts
+        .iter()
+        .flat_map(|y| counts.iter().map(move |x| y * x))
+        .collect();
+    assert_eq!(output.tensor().get_values().unwrap(), expected);
+}
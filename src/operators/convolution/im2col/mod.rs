@@ -0,0 +1,117 @@
+use crate::{
+    gradient_instruction, inference_instruction, new_tensor, new_tensor_with_grad,
+    opcode::OpCode,
+    stream::DeviceStream,
+    tensor::{Error, Tensor},
+    Device, DeviceTrait, ExecutableOperator, OperatorAttributes, SlidingWindow, TensorWithGrad,
+    UnaryOperator,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Copy every window of an image into a column, so that a convolution is a Gemm.
+/// See:
+/// High Performance Convolutional Neural Networks for Document Processing
+/// https://inria.hal.science/inria-00112631/document
+pub struct Im2Col {
+    device: Device,
+    window: SlidingWindow,
+}
+
+impl Im2Col {
+    pub fn try_new(device: &Device, window: &SlidingWindow) -> Result<Self, Error> {
+        window.validate()?;
+        let op = Self {
+            device: device.clone(),
+            window: window.clone(),
+        };
+        Ok(op)
+    }
+}
+
+impl ExecutableOperator for Im2Col {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let window = SlidingWindow::from_attributes(attributes)?;
+        let input = inputs[0];
+        let output = outputs[0];
+        SlidingWindow::check_size(input, window.image_size())?;
+        SlidingWindow::check_size(output, window.columns_size())?;
+        device.im2col(window, input, output, device_stream)
+    }
+}
+
+impl UnaryOperator for Im2Col {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let input_t: &Tensor = &input.tensor();
+        SlidingWindow::check_size(input_t, self.window.image_size())?;
+        let [rows, cols] = self.window.columns_size();
+        let output = new_tensor_with_grad!(
+            self.device,
+            rows,
+            cols,
+            vec![0.0; rows * cols],
+            &[input],
+            true,
+            false,
+        )?;
+        let attributes = OperatorAttributes::SlidingWindow(self.window.clone());
+
+        output.push_instruction(inference_instruction!(
+            OpCode::Im2Col,
+            attributes.clone(),
+            &[input_t],
+            &[&output.tensor()],
+        ));
+
+        if input.gradient().requires_grad() {
+            let [image_rows, image_cols] = self.window.image_size();
+            let image_gradient = new_tensor!(
+                self.device,
+                image_rows,
+                image_cols,
+                vec![0.0; image_rows * image_cols],
+            )?;
+            output.push_instruction(gradient_instruction!(
+                OpCode::Col2Im,
+                attributes,
+                &[&output.gradient()],
+                &[&image_gradient],
+            ));
+            output.push_instruction(gradient_instruction!(
+                OpCode::Add,
+                OperatorAttributes::None,
+                &[&image_gradient, &input.gradient()],
+                &[&input.gradient()],
+            ));
+        }
+
+        Ok(output)
+    }
+}
+
+/// https://onnx.ai/onnx/operators/onnx__Col2Im.html
+pub struct Col2Im {}
+
+impl ExecutableOperator for Col2Im {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let window = SlidingWindow::from_attributes(attributes)?;
+        let input = inputs[0];
+        let output = outputs[0];
+        SlidingWindow::check_size(input, window.columns_size())?;
+        SlidingWindow::check_size(output, window.image_size())?;
+        device.col2im(window, input, output, device_stream)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    new_tensor, stream::StreamTrait, Col2Im, Device, ExecutableOperator, Im2Col,
+    OperatorAttributes, SlidingWindow,
+};
+
+#[test]
+fn im2col() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let window = SlidingWindow::new(1, 3, 3, [2, 2]);
+    let image = new_tensor!(
+        device,
+        1,
+        9,
+        vec![
+            //
+            1.0, 2.0, 3.0, //
+            4.0, 5.0, 6.0, //
+            7.0, 8.0, 9.0, //
+        ],
+    )
+    .unwrap();
+    let columns = new_tensor!(device, 4, 4, vec![0.0; 16]).unwrap();
+
+    Im2Col::execute(
+        &OperatorAttributes::SlidingWindow(window),
+        &[&image],
+        &[&columns],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(
+        columns.get_values().unwrap(),
+        vec![
+            //
+            1.0, 2.0, 4.0, 5.0, //
+            2.0, 3.0, 5.0, 6.0, //
+            4.0, 5.0, 7.0, 8.0, //
+            5.0, 6.0, 8.0, 9.0, //
+        ]
+    );
+}
+
+#[test]
+fn im2col_with_strides_and_pads() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let window = SlidingWindow {
+        strides: [2, 2],
+        pads: [1, 1, 1, 1],
+        ..SlidingWindow::new(1, 3, 3, [2, 2])
+    };
+    let image = new_tensor!(
+        device,
+        1,
+        9,
+        vec![
+            //
+            1.0, 2.0, 3.0, //
+            4.0, 5.0, 6.0, //
+            7.0, 8.0, 9.0, //
+        ],
+    )
+    .unwrap();
+    let columns = new_tensor!(device, 4, 4, vec![0.0; 16]).unwrap();
+
+    Im2Col::execute(
+        &OperatorAttributes::SlidingWindow(window),
+        &[&image],
+        &[&columns],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(
+        columns.get_values().unwrap(),
+        vec![
+            //
+            0.0, 0.0, 0.0, 5.0, //
+            0.0, 0.0, 4.0, 6.0, //
+            0.0, 2.0, 0.0, 8.0, //
+            1.0, 3.0, 7.0, 9.0, //
+        ]
+    );
+}
+
+#[test]
+fn col2im() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let window = SlidingWindow::new(1, 3, 3, [2, 2]);
+    let columns = new_tensor!(device, 4, 4, vec![1.0; 16]).unwrap();
+    let image = new_tensor!(device, 1, 9, vec![0.0; 9]).unwrap();
+
+    Col2Im::execute(
+        &OperatorAttributes::SlidingWindow(window),
+        &[&columns],
+        &[&image],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(
+        image.get_values().unwrap(),
+        vec![
+            //
+            1.0, 2.0, 1.0, //
+            2.0, 4.0, 2.0, //
+            1.0, 2.0, 1.0, //
+        ]
+    );
+}
@@ -0,0 +1,8 @@
+mod sliding_window;
+pub use sliding_window::*;
+mod im2col;
+pub use im2col::*;
+mod conv;
+pub use conv::*;
+mod pool;
+pub use pool::*;
@@ -0,0 +1,204 @@
+use crate::{
+    gradient_instruction, inference_instruction, new_tensor, new_tensor_with_grad,
+    opcode::OpCode,
+    stream::DeviceStream,
+    tensor::{Error, Tensor},
+    Device, DeviceTrait, ExecutableOperator, OperatorAttributes, SlidingWindow, TensorWithGrad,
+    UnaryOperator,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Push the instructions of a pooling.
+/// The gradient operator computes the gradient of the image from the gradient of the output.
+fn pool(
+    device: &Device,
+    window: &SlidingWindow,
+    opcode: OpCode,
+    gradient_opcode: OpCode,
+    input: &TensorWithGrad,
+) -> Result<TensorWithGrad, Error> {
+    let input_t: &Tensor = &input.tensor();
+    SlidingWindow::check_size(input_t, window.image_size())?;
+    let [rows, cols] = window.pooled_size();
+    let output = new_tensor_with_grad!(
+        device,
+        rows,
+        cols,
+        vec![0.0; rows * cols],
+        &[input],
+        true,
+        false,
+    )?;
+    let attributes = OperatorAttributes::SlidingWindow(window.clone());
+
+    output.push_instruction(inference_instruction!(
+        opcode,
+        attributes.clone(),
+        &[input_t],
+        &[&output.tensor()],
+    ));
+
+    if input.gradient().requires_grad() {
+        let [image_rows, image_cols] = window.image_size();
+        let image_gradient = new_tensor!(
+            device,
+            image_rows,
+            image_cols,
+            vec![0.0; image_rows * image_cols],
+        )?;
+        output.push_instruction(gradient_instruction!(
+            gradient_opcode,
+            attributes,
+            &[input_t, &output.gradient()],
+            &[&image_gradient],
+        ));
+        output.push_instruction(gradient_instruction!(
+            OpCode::Add,
+            OperatorAttributes::None,
+            &[&image_gradient, &input.gradient()],
+            &[&input.gradient()],
+        ));
+    }
+
+    Ok(output)
+}
+
+/// https://onnx.ai/onnx/operators/onnx__MaxPool.html
+pub struct MaxPool {
+    device: Device,
+    window: SlidingWindow,
+}
+
+impl MaxPool {
+    pub fn try_new(device: &Device, window: &SlidingWindow) -> Result<Self, Error> {
+        window.validate_pooling()?;
+        let op = Self {
+            device: device.clone(),
+            window: window.clone(),
+        };
+        Ok(op)
+    }
+}
+
+impl ExecutableOperator for MaxPool {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let window = SlidingWindow::pooling_from_attributes(attributes)?;
+        let input = inputs[0];
+        let output = outputs[0];
+        SlidingWindow::check_size(input, window.image_size())?;
+        SlidingWindow::check_size(output, window.pooled_size())?;
+        device.max_pool(window, input, output, device_stream)
+    }
+}
+
+impl UnaryOperator for MaxPool {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        pool(
+            &self.device,
+            &self.window,
+            OpCode::MaxPool,
+            OpCode::MaxPoolGradient,
+            input,
+        )
+    }
+}
+
+pub struct MaxPoolGradient {}
+
+impl ExecutableOperator for MaxPoolGradient {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let window = SlidingWindow::pooling_from_attributes(attributes)?;
+        let input = inputs[0];
+        let output_gradient = inputs[1];
+        let input_gradient = outputs[0];
+        SlidingWindow::check_size(input, window.image_size())?;
+        SlidingWindow::check_size(output_gradient, window.pooled_size())?;
+        SlidingWindow::check_size(input_gradient, window.image_size())?;
+        device.max_pool_gradient(
+            window,
+            input,
+            output_gradient,
+            input_gradient,
+            device_stream,
+        )
+    }
+}
+
+/// https://onnx.ai/onnx/operators/onnx__AveragePool.html
+pub struct AveragePool {
+    device: Device,
+    window: SlidingWindow,
+}
+
+impl AveragePool {
+    pub fn try_new(device: &Device, window: &SlidingWindow) -> Result<Self, Error> {
+        window.validate_pooling()?;
+        let op = Self {
+            device: device.clone(),
+            window: window.clone(),
+        };
+        Ok(op)
+    }
+}
+
+impl ExecutableOperator for AveragePool {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let window = SlidingWindow::pooling_from_attributes(attributes)?;
+        let input = inputs[0];
+        let output = outputs[0];
+        SlidingWindow::check_size(input, window.image_size())?;
+        SlidingWindow::check_size(output, window.pooled_size())?;
+        device.average_pool(window, input, output, device_stream)
+    }
+}
+
+impl UnaryOperator for AveragePool {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        pool(
+            &self.device,
+            &self.window,
+            OpCode::AveragePool,
+            OpCode::AveragePoolGradient,
+            input,
+        )
+    }
+}
+
+pub struct AveragePoolGradient {}
+
+impl ExecutableOperator for AveragePoolGradient {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let window = SlidingWindow::pooling_from_attributes(attributes)?;
+        let output_gradient = inputs[1];
+        let input_gradient = outputs[0];
+        SlidingWindow::check_size(output_gradient, window.pooled_size())?;
+        SlidingWindow::check_size(input_gradient, window.image_size())?;
+        device.average_pool_gradient(window, output_gradient, input_gradient, device_stream)
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{
+    new_tensor, stream::StreamTrait, AveragePool, AveragePoolGradient, Device, ExecutableOperator,
+    MaxPool, MaxPoolGradient, OperatorAttributes, SlidingWindow,
+};
+
+fn window() -> SlidingWindow {
+    SlidingWindow {
+        strides: [2, 2],
+        ..SlidingWindow::new(1, 4, 4, [2, 2])
+    }
+}
+
+fn image_values() -> Vec<f32> {
+    vec![
+        //
+        1.0, 2.0, 3.0, 4.0, //
+        5.0, 6.0, 7.0, 8.0, //
+        9.0, 10.0, 11.0, 12.0, //
+        13.0, 14.0, 15.0, 16.0, //
+    ]
+}
+
+#[test]
+fn max_pool() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let attributes = OperatorAttributes::SlidingWindow(window());
+    let image = new_tensor!(device, 1, 16, image_values()).unwrap();
+    let output = new_tensor!(device, 1, 4, vec![0.0; 4]).unwrap();
+    let output_gradient = new_tensor!(device, 1, 4, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    let image_gradient = new_tensor!(device, 1, 16, vec![0.0; 16]).unwrap();
+
+    MaxPool::execute(&attributes, &[&image], &[&output], &device, &device_stream).unwrap();
+    MaxPoolGradient::execute(
+        &attributes,
+        &[&image, &output_gradient],
+        &[&image_gradient],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(output.get_values().unwrap(), vec![6.0, 8.0, 14.0, 16.0]);
+    assert_eq!(
+        image_gradient.get_values().unwrap(),
+        vec![
+            //
+            0.0, 0.0, 0.0, 0.0, //
+            0.0, 1.0, 0.0, 2.0, //
+            0.0, 0.0, 0.0, 0.0, //
+            0.0, 3.0, 0.0, 4.0, //
+        ]
+    );
+}
+
+#[test]
+fn average_pool() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let attributes = OperatorAttributes::SlidingWindow(window());
+    let image = new_tensor!(device, 1, 16, image_values()).unwrap();
+    let output = new_tensor!(device, 1, 4, vec![0.0; 4]).unwrap();
+    let output_gradient = new_tensor!(device, 1, 4, vec![4.0, 8.0, 12.0, 16.0]).unwrap();
+    let image_gradient = new_tensor!(device, 1, 16, vec![0.0; 16]).unwrap();
+
+    AveragePool::execute(&attributes, &[&image], &[&output], &device, &device_stream).unwrap();
+    AveragePoolGradient::execute(
+        &attributes,
+        &[&image, &output_gradient],
+        &[&image_gradient],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(output.get_values().unwrap(), vec![3.5, 5.5, 11.5, 13.5]);
+    assert_eq!(
+        image_gradient.get_values().unwrap(),
+        vec![
+            //
+            1.0, 1.0, 2.0, 2.0, //
+            1.0, 1.0, 2.0, 2.0, //
+            3.0, 3.0, 4.0, 4.0, //
+            3.0, 3.0, 4.0, 4.0, //
+        ]
+    );
+}
+
+#[test]
+fn average_pool_count_include_pad() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let image = new_tensor!(device, 1, 4, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    let output = new_tensor!(device, 1, 1, vec![0.0]).unwrap();
+
+    for (count_include_pad, expected) in [(false, 1.0), (true, 0.25)] {
+        let window = SlidingWindow {
+            strides: [2, 2],
+            pads: [1, 1, 0, 0],
+            count_include_pad,
+            ..SlidingWindow::new(1, 2, 2, [2, 2])
+        };
+        AveragePool::execute(
+            &OperatorAttributes::SlidingWindow(window),
+            &[&image],
+            &[&output],
+            &device,
+            &device_stream,
+        )
+        .unwrap();
+        device_stream.wait_for().unwrap();
+        assert_eq!(output.get_values().unwrap(), vec![expected]);
+    }
+}
+
+#[test]
+fn pads_must_be_smaller_than_the_kernel() {
+    let device = Device::default();
+    for pads in [[2, 0, 0, 0], [0, 2, 0, 0], [0, 0, 2, 0], [0, 0, 0, 2]] {
+        let window = SlidingWindow {
+            pads,
+            ..SlidingWindow::new(1, 2, 2, [2, 2])
+        };
+        assert!(window.validate_pooling().is_err());
+        assert!(MaxPool::try_new(&device, &window).is_err());
+        assert!(AveragePool::try_new(&device, &window).is_err());
+        let attributes = OperatorAttributes::SlidingWindow(window);
+        assert!(SlidingWindow::pooling_from_attributes(&attributes).is_err());
+    }
+    let window = SlidingWindow {
+        pads: [1, 1, 1, 1],
+        ..SlidingWindow::new(1, 2, 2, [2, 2])
+    };
+    assert!(window.validate_pooling().is_ok());
+}
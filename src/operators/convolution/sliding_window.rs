@@ -0,0 +1,292 @@
+use crate::{
+    error,
+    tensor::{Error, ErrorEnum, Tensor},
+    OperatorAttributes,
+};
+
+/// Spatial attributes of Conv, MaxPool and AveragePool.
+/// Batches are not supported.
+/// An image with C channels of H x W pixels is a tensor with C rows and H * W columns.
+/// See https://onnx.ai/onnx/operators/onnx__Conv.html for the meaning of the attributes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlidingWindow {
+    pub channels: usize,
+    pub height: usize,
+    pub width: usize,
+    pub kernel_shape: [usize; 2],
+    pub strides: [usize; 2],
+    /// [top, left, bottom, right]
+    pub pads: [usize; 4],
+    pub dilations: [usize; 2],
+    /// Only used by AveragePool.
+    pub count_include_pad: bool,
+}
+
+impl SlidingWindow {
+    /// A window with strides of 1, no padding and no dilation.
+    pub fn new(channels: usize, height: usize, width: usize, kernel_shape: [usize; 2]) -> Self {
+        Self {
+            channels,
+            height,
+            width,
+            kernel_shape,
+            strides: [1, 1],
+            pads: [0, 0, 0, 0],
+            dilations: [1, 1],
+            count_include_pad: false,
+        }
+    }
+
+    /// The window of the attributes of an instruction, validated.
+    pub fn from_attributes(attributes: &OperatorAttributes) -> Result<&SlidingWindow, Error> {
+        match attributes {
+            OperatorAttributes::SlidingWindow(window) => {
+                window.validate()?;
+                Ok(window)
+            }
+            _ => Err(error!(ErrorEnum::UnsupportedOperation)),
+        }
+    }
+
+    /// The window of the attributes of a pooling instruction, validated.
+    pub fn pooling_from_attributes(
+        attributes: &OperatorAttributes,
+    ) -> Result<&SlidingWindow, Error> {
+        let window = Self::from_attributes(attributes)?;
+        window.validate_pooling()?;
+        Ok(window)
+    }
+
+    /// The padded image must be at least as large as the dilated kernel.
+    /// Like in ONNX, Conv accepts any pads.
+    pub fn validate(&self) -> Result<(), Error> {
+        let [kernel_height, kernel_width] = self.kernel_shape;
+        let [stride_height, stride_width] = self.strides;
+        let [dilation_height, dilation_width] = self.dilations;
+        let [top, left, bottom, right] = self.pads;
+        let valid = self.channels > 0
+            && kernel_height > 0
+            && kernel_width > 0
+            && stride_height > 0
+            && stride_width > 0
+            && dilation_height > 0
+            && dilation_width > 0
+            && self.height + top + bottom > dilation_height * (kernel_height - 1)
+            && self.width + left + right > dilation_width * (kernel_width - 1);
+        match valid {
+            true => Ok(()),
+            false => Err(error!(ErrorEnum::IncorrectOperatorConfiguration)),
+        }
+    }
+
+    /// Pads of a pooling must also be smaller than the kernel so that every window
+    /// covers at least one pixel, otherwise AveragePool would divide by a count of zero.
+    pub fn validate_pooling(&self) -> Result<(), Error> {
+        self.validate()?;
+        let [kernel_height, kernel_width] = self.kernel_shape;
+        let [top, left, bottom, right] = self.pads;
+        let valid = top < kernel_height
+            && bottom < kernel_height
+            && left < kernel_width
+            && right < kernel_width;
+        match valid {
+            true => Ok(()),
+            false => Err(error!(ErrorEnum::IncorrectOperatorConfiguration)),
+        }
+    }
+
+    pub fn output_height(&self) -> usize {
+        let [top, _, bottom, _] = self.pads;
+        let extent = self.dilations[0] * (self.kernel_shape[0] - 1) + 1;
+        (self.height + top + bottom - extent) / self.strides[0] + 1
+    }
+
+    pub fn output_width(&self) -> usize {
+        let [_, left, _, right] = self.pads;
+        let extent = self.dilations[1] * (self.kernel_shape[1] - 1) + 1;
+        (self.width + left + right - extent) / self.strides[1] + 1
+    }
+
+    /// Rows and columns of an image.
+    pub fn image_size(&self) -> [usize; 2] {
+        [self.channels, self.height * self.width]
+    }
+
+    /// Rows and columns of the output of Im2Col.
+    pub fn columns_size(&self) -> [usize; 2] {
+        [
+            self.channels * self.kernel_shape[0] * self.kernel_shape[1],
+            self.output_height() * self.output_width(),
+        ]
+    }
+
+    /// Rows and columns of the output of a pooling.
+    pub fn pooled_size(&self) -> [usize; 2] {
+        [self.channels, self.output_height() * self.output_width()]
+    }
+
+    pub fn check_size(tensor: &Tensor, size: [usize; 2]) -> Result<(), Error> {
+        if tensor.rows() != size[0] || tensor.cols() != size[1] {
+            return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+        }
+        Ok(())
+    }
+
+    /// Index of the pixel of an image channel covered by the
+    /// kernel element (kernel_y, kernel_x) at output position (output_y, output_x).
+    /// Returns None if the pixel is in the padding.
+    fn pixel(
+        &self,
+        output_y: usize,
+        output_x: usize,
+        kernel_y: usize,
+        kernel_x: usize,
+    ) -> Option<usize> {
+        let y = output_y * self.strides[0] + kernel_y * self.dilations[0];
+        let x = output_x * self.strides[1] + kernel_x * self.dilations[1];
+        let [top, left, _, _] = self.pads;
+        if y < top || x < left {
+            return None;
+        }
+        let (y, x) = (y - top, x - left);
+        if y >= self.height || x >= self.width {
+            return None;
+        }
+        Some(y * self.width + x)
+    }
+
+    /// Visit every (channel, output position, kernel element) of the window.
+    /// The visitor receives the channel, the output position, the kernel element
+    /// and the pixel, if it is not in the padding.
+    fn visit(&self, mut visitor: impl FnMut(usize, usize, usize, Option<usize>)) {
+        let [kernel_height, kernel_width] = self.kernel_shape;
+        let output_height = self.output_height();
+        let output_width = self.output_width();
+        for channel in 0..self.channels {
+            for output_y in 0..output_height {
+                for output_x in 0..output_width {
+                    let position = output_y * output_width + output_x;
+                    for kernel_y in 0..kernel_height {
+                        for kernel_x in 0..kernel_width {
+                            let element = kernel_y * kernel_width + kernel_x;
+                            let pixel = self.pixel(output_y, output_x, kernel_y, kernel_x);
+                            visitor(channel, position, element, pixel);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Copy every window of the image into a column.
+    /// Padding is filled with zeros.
+    pub fn im2col(&self, image: &[f32], columns: &mut [f32]) {
+        let image_len = self.height * self.width;
+        let kernel_len = self.kernel_shape[0] * self.kernel_shape[1];
+        let positions = self.output_height() * self.output_width();
+        self.visit(|channel, position, element, pixel| {
+            let row = channel * kernel_len + element;
+            columns[row * positions + position] = match pixel {
+                Some(pixel) => image[channel * image_len + pixel],
+                None => 0.0,
+            };
+        });
+    }
+
+    /// Sum every column back into its window of the image.
+    /// This is the adjoint of im2col.
+    pub fn col2im(&self, columns: &[f32], image: &mut [f32]) {
+        let image_len = self.height * self.width;
+        let kernel_len = self.kernel_shape[0] * self.kernel_shape[1];
+        let positions = self.output_height() * self.output_width();
+        image.fill(0.0);
+        self.visit(|channel, position, element, pixel| {
+            if let Some(pixel) = pixel {
+                let row = channel * kernel_len + element;
+                image[channel * image_len + pixel] += columns[row * positions + position];
+            }
+        });
+    }
+
+    /// Padding is ignored.
+    pub fn max_pool(&self, image: &[f32], output: &mut [f32]) {
+        let image_len = self.height * self.width;
+        let positions = self.output_height() * self.output_width();
+        output.fill(f32::NEG_INFINITY);
+        self.visit(|channel, position, _element, pixel| {
+            if let Some(pixel) = pixel {
+                let value = image[channel * image_len + pixel];
+                let output = &mut output[channel * positions + position];
+                if value > *output {
+                    *output = value;
+                }
+            }
+        });
+    }
+
+    /// The gradient of an output goes to the first maximum of its window.
+    pub fn max_pool_gradient(
+        &self,
+        image: &[f32],
+        output_gradient: &[f32],
+        image_gradient: &mut [f32],
+    ) {
+        let image_len = self.height * self.width;
+        let positions = self.output_height() * self.output_width();
+        let mut argmaxes = vec![None; self.channels * positions];
+        self.visit(|channel, position, _element, pixel| {
+            if let Some(pixel) = pixel {
+                let index = channel * image_len + pixel;
+                let argmax = &mut argmaxes[channel * positions + position];
+                match argmax {
+                    Some(argmax_index) if image[*argmax_index] >= image[index] => {}
+                    _ => *argmax = Some(index),
+                }
+            }
+        });
+        image_gradient.fill(0.0);
+        for (output_index, argmax) in argmaxes.into_iter().enumerate() {
+            if let Some(index) = argmax {
+                image_gradient[index] += output_gradient[output_index];
+            }
+        }
+    }
+
+    /// Number of values averaged for each output.
+    fn average_pool_counts(&self) -> Vec<f32> {
+        let positions = self.output_height() * self.output_width();
+        let mut counts = vec![0.0; positions];
+        self.visit(|channel, position, _element, pixel| {
+            if channel == 0 && (pixel.is_some() || self.count_include_pad) {
+                counts[position] += 1.0;
+            }
+        });
+        counts
+    }
+
+    pub fn average_pool(&self, image: &[f32], output: &mut [f32]) {
+        let image_len = self.height * self.width;
+        let positions = self.output_height() * self.output_width();
+        let counts = self.average_pool_counts();
+        output.fill(0.0);
+        self.visit(|channel, position, _element, pixel| {
+            if let Some(pixel) = pixel {
+                output[channel * positions + position] +=
+                    image[channel * image_len + pixel] / counts[position];
+            }
+        });
+    }
+
+    pub fn average_pool_gradient(&self, output_gradient: &[f32], image_gradient: &mut [f32]) {
+        let image_len = self.height * self.width;
+        let positions = self.output_height() * self.output_width();
+        let counts = self.average_pool_counts();
+        image_gradient.fill(0.0);
+        self.visit(|channel, position, _element, pixel| {
+            if let Some(pixel) = pixel {
+                image_gradient[channel * image_len + pixel] +=
+                    output_gradient[channel * positions + position] / counts[position];
+            }
+        });
+    }
+}
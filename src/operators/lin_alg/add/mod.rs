@@ -44,14 +44,22 @@ impl ExecutableOperator for Add {
             println!("y {}", output);
             return Err(error!(ErrorEnum::IncompatibleTensorShapes));
         }
-        device.copy_to(input_0, output, device_stream)?;
+        // When the output is the second input, like when a gradient is accumulated,
+        // the first input is added in place.
+        let addend = match output.name() == input_1.name() {
+            true => input_0,
+            false => {
+                device.copy_to(input_0, output, device_stream)?;
+                input_1
+            }
+        };
 
         let alpha = 1.0;
 
-        let n = input_1.len() as i32;
+        let n = addend.len() as i32;
         let incx = 1;
         let incy = 1;
-        device.axpy(n, alpha, input_1, incx, output, incy, device_stream)
+        device.axpy(n, alpha, addend, incx, output, incy, device_stream)
     }
 }
 
@@ -1,6 +1,6 @@
 use rand::Rng;
 
-use crate::{new_tensor, Add, Device, ExecutableOperator};
+use crate::{new_tensor, stream::StreamTrait, Add, Device, ExecutableOperator};
 
 #[test]
 fn matrix_addition_result() {
@@ -86,3 +86,23 @@ fn big_matrix_addition() {
     )
     .unwrap();
 }
+
+#[test]
+fn addition_accumulates_in_place() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let update = new_tensor!(device, 1, 3, vec![1.0, 2.0, 3.0]).unwrap();
+    let accumulator = new_tensor!(device, 1, 3, vec![10.0, 20.0, 30.0]).unwrap();
+    for _ in 0..2 {
+        Add::execute(
+            &Default::default(),
+            &[&update, &accumulator],
+            &[&accumulator],
+            &device,
+            &device_stream,
+        )
+        .unwrap();
+    }
+    device_stream.wait_for().unwrap();
+    assert_eq!(accumulator.get_values().unwrap(), vec![12.0, 24.0, 36.0]);
+}
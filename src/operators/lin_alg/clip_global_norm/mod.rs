@@ -0,0 +1,51 @@
+use crate::{
+    error,
+    stream::DeviceStream,
+    tensor::{Error, ErrorEnum, Tensor},
+    Device, DeviceTrait, ExecutableOperator, OperatorAttributes,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// See
+/// On the difficulty of training Recurrent Neural Networks
+/// https://arxiv.org/abs/1211.5063
+pub struct ClipGlobalNorm {}
+
+impl ExecutableOperator for ClipGlobalNorm {
+    fn execute(
+        _attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        if inputs.len() != outputs.len() {
+            return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+        }
+        let max_norm = inputs[0];
+        let norm = outputs[0];
+        let zero = &device_stream.zero;
+        device.scalar_mul(zero, norm, device_stream)?;
+
+        let squared_norm = &device_stream.l2_norm;
+        for (input, output) in inputs[1..].iter().zip(outputs[1..].iter()) {
+            if input.name() != output.name() {
+                device.copy_to(input, output, device_stream)?;
+            }
+            device.dot(output, output, squared_norm, device_stream)?;
+            device.axpy(1, 1.0, squared_norm, 1, norm, 1, device_stream)?;
+        }
+        device.sqrt(norm, norm, device_stream)?;
+
+        let max_alpha = &device_stream.max_alpha;
+        let alpha = &device_stream.alpha;
+        device.div(max_norm, norm, alpha, device_stream)?;
+        device.min(max_alpha, alpha, alpha, device_stream)?;
+        for output in outputs[1..].iter() {
+            device.scalar_mul(alpha, output, device_stream)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{
+    new_tensor, stream::StreamTrait, ClipGlobalNorm, Device, ExecutableOperator, OperatorAttributes,
+};
+
+#[test]
+fn clip_global_norm() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let tensor1 = new_tensor!(device, 1, 2, vec![6.0, 0.0]).unwrap();
+    let tensor2 = new_tensor!(device, 1, 2, vec![0.0, 8.0]).unwrap();
+    let max_norm = new_tensor!(device, 1, 1, vec![5.0]).unwrap();
+    let norm = new_tensor!(device, 1, 1, vec![0.0]).unwrap();
+
+    ClipGlobalNorm::execute(
+        &OperatorAttributes::None,
+        &[&max_norm, &tensor1, &tensor2],
+        &[&norm, &tensor1, &tensor2],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(norm.get_values().unwrap()[0], 10.0);
+    assert_eq!(tensor1.get_values().unwrap(), vec![3.0, 0.0]);
+    assert_eq!(tensor2.get_values().unwrap(), vec![0.0, 4.0]);
+}
+
+#[test]
+fn does_not_scale_up_small_gradients() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let tensor = new_tensor!(device, 1, 2, vec![0.3, 0.4]).unwrap();
+    let max_norm = new_tensor!(device, 1, 1, vec![1.0]).unwrap();
+    let norm = new_tensor!(device, 1, 1, vec![0.0]).unwrap();
+
+    ClipGlobalNorm::execute(
+        &OperatorAttributes::None,
+        &[&max_norm, &tensor],
+        &[&norm, &tensor],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+
+    assert_eq!(norm.get_values().unwrap()[0], 0.5);
+    assert_eq!(tensor.get_values().unwrap(), vec![0.3, 0.4]);
+}
@@ -1,12 +1,13 @@
 use crate::{
     devices::Device,
+    gather::Gather,
     new_tensor, new_tensor_with_grad,
     stream::StreamTrait,
     tensor::{Error, Tensor},
     transpose::Transpose,
     BinaryOperator, ExecutableOperator, MatMul, TensorWithGrad, UnaryOperator,
 };
-use rand::{distributions::Uniform, thread_rng, Rng};
+use rand::{distributions::Uniform, Rng};
 
 pub struct Embedding {
     embedding_table: TensorWithGrad,
@@ -14,6 +15,7 @@ pub struct Embedding {
 }
 
 impl Embedding {
+    /// When the device has a LoraConfig, the table is frozen.
     pub fn new(
         device: &Device,
         num_embeddings: usize,
@@ -31,15 +33,18 @@ impl Embedding {
             &device_stream,
         )?;
         device_stream.wait_for()?;
+        let trainable = device.lora_config().is_none();
         let embedding_table = new_tensor_with_grad!(
             device,
             transposed.rows(),
             transposed.cols(),
             transposed.get_values().unwrap(),
             &[],
-            true,
-            true,
+            trainable,
+            trainable,
         )?;
+        embedding_table.add_tag("Embedding");
+        embedding_table.add_tag("embedding_table");
 
         let transb = true;
         let matmul = MatMul::new(device, transb);
@@ -54,6 +59,11 @@ impl Embedding {
         };
         Ok(op)
     }
+
+    /// Table with shape [embedding_dim, num_embeddings].
+    pub fn embedding_table(&self) -> &TensorWithGrad {
+        &self.embedding_table
+    }
 }
 
 impl UnaryOperator for Embedding {
@@ -65,12 +75,60 @@ impl UnaryOperator for Embedding {
     }
 }
 
+/// Embedding of token ids.
+/// The input has one row per token and one column, the token id.
+/// Unlike Embedding, the input is not one-hot encoded.
+/// Only the rows of the table that are looked up receive a gradient.
+pub struct SparseEmbedding {
+    embedding_table: TensorWithGrad,
+    gather: Gather,
+}
+
+impl SparseEmbedding {
+    /// When the device has a LoraConfig, the table is frozen.
+    pub fn new(
+        device: &Device,
+        num_embeddings: usize,
+        embedding_dim: usize,
+    ) -> Result<Self, Error> {
+        let embedding_table = get_embedding_table(device, num_embeddings, embedding_dim)?;
+        let trainable = device.lora_config().is_none();
+        let embedding_table = new_tensor_with_grad!(
+            device,
+            num_embeddings,
+            embedding_dim,
+            embedding_table.get_values()?,
+            &[],
+            trainable,
+            trainable,
+        )?;
+        embedding_table.add_tag("SparseEmbedding");
+        embedding_table.add_tag("embedding_table");
+        let op = Self {
+            embedding_table,
+            gather: Gather::new(device),
+        };
+        Ok(op)
+    }
+
+    /// Table with shape [num_embeddings, embedding_dim].
+    pub fn embedding_table(&self) -> &TensorWithGrad {
+        &self.embedding_table
+    }
+}
+
+impl UnaryOperator for SparseEmbedding {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        self.gather.forward(&self.embedding_table, input)
+    }
+}
+
 fn get_embedding_table(
     device: &Device,
     num_embeddings: usize,
     embedding_dim: usize,
 ) -> Result<Tensor, Error> {
-    let mut rng = thread_rng();
+    let mut rng = device.rng();
     let mut embeddings_table: Vec<f32> = Vec::new();
     let left = 0.0;
     let right = 1.0;
@@ -86,5 +144,6 @@ fn get_embedding_table(
         embeddings_table.append(&mut token_embeddings);
         token += 1;
     }
+    drop(rng);
     new_tensor!(device, num_embeddings, embedding_dim, embeddings_table)
 }
@@ -0,0 +1,161 @@
+use crate::{
+    error, gradient_instruction, inference_instruction, new_tensor_with_grad,
+    opcode::OpCode,
+    stream::DeviceStream,
+    tensor::{Error, ErrorEnum, Tensor},
+    BinaryOperator, Device, DeviceTrait, ExecutableOperator, OperatorAttributes, TensorWithGrad,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Row indices are stored as f32 in a tensor with one column.
+fn row_index(index: f32, rows: usize) -> Result<usize, Error> {
+    let row = index as usize;
+    if index < 0.0 || index.fract() != 0.0 || row >= rows {
+        return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+    }
+    Ok(row)
+}
+
+/// Every index must be a row of a table with the given rows.
+/// Devices that do not report errors from their kernels check the indices before the launch.
+pub fn check_indices(indices: &[f32], rows: usize) -> Result<(), Error> {
+    for index in indices.iter() {
+        row_index(*index, rows)?;
+    }
+    Ok(())
+}
+
+/// output[i, :] = data[indices[i], :]
+pub fn gather(cols: usize, data: &[f32], indices: &[f32], output: &mut [f32]) -> Result<(), Error> {
+    let rows = data.len() / cols;
+    for (index, output_row) in indices.iter().zip(output.chunks_mut(cols)) {
+        let row = row_index(*index, rows)?;
+        output_row.copy_from_slice(&data[row * cols..(row + 1) * cols]);
+    }
+    Ok(())
+}
+
+/// output[indices[i], :] += updates[i, :]
+/// Rows of output that are not indexed are not touched.
+pub fn scatter_add(
+    cols: usize,
+    indices: &[f32],
+    updates: &[f32],
+    output: &mut [f32],
+) -> Result<(), Error> {
+    let rows = output.len() / cols;
+    for (index, update_row) in indices.iter().zip(updates.chunks(cols)) {
+        let row = row_index(*index, rows)?;
+        for (output, update) in output[row * cols..(row + 1) * cols]
+            .iter_mut()
+            .zip(update_row.iter())
+        {
+            *output += update;
+        }
+    }
+    Ok(())
+}
+
+fn check_shapes(data: &Tensor, indices: &Tensor, output: &Tensor) -> Result<(), Error> {
+    if indices.cols() != 1 || output.rows() != indices.rows() || output.cols() != data.cols() {
+        return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+    }
+    Ok(())
+}
+
+/// Gather with axis = 0.
+/// The gradient of data is accumulated with ScatterAdd in the gathered rows only.
+/// See https://onnx.ai/onnx/operators/onnx__Gather.html
+pub struct Gather {
+    device: Device,
+}
+
+impl Gather {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            device: device.clone(),
+        }
+    }
+}
+
+impl BinaryOperator for Gather {
+    fn forward(
+        &self,
+        data: &TensorWithGrad,
+        indices: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        let data_t: &Tensor = &data.tensor();
+        let indices_t: &Tensor = &indices.tensor();
+        let rows = indices_t.rows();
+        let cols = data_t.cols();
+        if indices_t.cols() != 1 {
+            return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+        }
+        let output = new_tensor_with_grad!(
+            self.device,
+            rows,
+            cols,
+            vec![0.0; rows * cols],
+            &[data, indices],
+            true,
+            false,
+        )?;
+
+        output.push_instruction(inference_instruction!(
+            OpCode::Gather,
+            OperatorAttributes::None,
+            &[data_t, indices_t],
+            &[&output.tensor()],
+        ));
+
+        if data.gradient().requires_grad() {
+            output.push_instruction(gradient_instruction!(
+                OpCode::ScatterAdd,
+                OperatorAttributes::None,
+                &[indices_t, &output.gradient()],
+                &[&data.gradient()],
+            ));
+        }
+
+        Ok(output)
+    }
+}
+
+impl ExecutableOperator for Gather {
+    fn execute(
+        _attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let data = inputs[0];
+        let indices = inputs[1];
+        let output = outputs[0];
+        check_shapes(data, indices, output)?;
+        device.gather(data, indices, output, device_stream)
+    }
+}
+
+/// Not ONNX-compliant
+/// Like ScatterND with reduction = "add", but the output is updated in place.
+/// See https://onnx.ai/onnx/operators/onnx__ScatterND.html
+pub struct ScatterAdd {}
+
+impl ExecutableOperator for ScatterAdd {
+    fn execute(
+        _attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let indices = inputs[0];
+        let updates = inputs[1];
+        let output = outputs[0];
+        check_shapes(output, indices, updates)?;
+        device.scatter_add(indices, updates, output, device_stream)
+    }
+}
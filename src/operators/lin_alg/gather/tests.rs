@@ -0,0 +1,101 @@
+use crate::{
+    datasets::into_token_id_rows,
+    gather::{Gather, ScatterAdd},
+    new_tensor,
+    stream::StreamTrait,
+    Device, ExecutableOperator, OperatorAttributes, SparseEmbedding, UnaryOperator,
+};
+
+#[test]
+fn gather() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let data = new_tensor!(device, 3, 2, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+    let indices = new_tensor!(device, 4, 1, vec![2.0, 0.0, 2.0, 1.0]).unwrap();
+    let output = new_tensor!(device, 4, 2, vec![0.0; 8]).unwrap();
+    Gather::execute(
+        &OperatorAttributes::None,
+        &[&data, &indices],
+        &[&output],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+    assert_eq!(
+        output.get_values().unwrap(),
+        vec![5.0, 6.0, 1.0, 2.0, 5.0, 6.0, 3.0, 4.0]
+    );
+}
+
+#[test]
+fn scatter_add_accumulates_in_indexed_rows_only() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let indices = new_tensor!(device, 3, 1, vec![2.0, 0.0, 2.0]).unwrap();
+    let updates = new_tensor!(device, 3, 2, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+    let output = new_tensor!(device, 4, 2, vec![1.0; 8]).unwrap();
+    ScatterAdd::execute(
+        &OperatorAttributes::None,
+        &[&indices, &updates],
+        &[&output],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+    assert_eq!(
+        output.get_values().unwrap(),
+        vec![4.0, 5.0, 1.0, 1.0, 7.0, 9.0, 1.0, 1.0]
+    );
+}
+
+#[test]
+fn gather_rejects_out_of_range_indices() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let data = new_tensor!(device, 2, 2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+    let indices = new_tensor!(device, 1, 1, vec![2.0]).unwrap();
+    let output = new_tensor!(device, 1, 2, vec![0.0; 2]).unwrap();
+    let result = Gather::execute(
+        &OperatorAttributes::None,
+        &[&data, &indices],
+        &[&output],
+        &device,
+        &device_stream,
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn sparse_embedding_gradient_touches_looked_up_rows() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let embedding = SparseEmbedding::new(&device, 5, 3).unwrap();
+    let input = into_token_id_rows(&device, &[1, 3, 1]).unwrap();
+    let output = embedding.forward(&input).unwrap();
+    assert_eq!(output.tensor().rows(), 3);
+    assert_eq!(output.tensor().cols(), 3);
+
+    output.gradient().set_values(vec![1.0; 9]).unwrap();
+    output.forward(&device, &device_stream).unwrap();
+    output.compute_gradient(&device, &device_stream).unwrap();
+    device_stream.wait_for().unwrap();
+
+    let table = device.parameter_tensors()[0].clone();
+    let table_values = table.tensor().get_values().unwrap();
+    let output_values = output.tensor().get_values().unwrap();
+    assert_eq!(output_values[0..3], table_values[3..6]);
+    assert_eq!(output_values[3..6], table_values[9..12]);
+    assert_eq!(
+        table.gradient().get_values().unwrap(),
+        vec![
+            //
+            0.0, 0.0, 0.0, //
+            2.0, 2.0, 2.0, //
+            0.0, 0.0, 0.0, //
+            1.0, 1.0, 1.0, //
+            0.0, 0.0, 0.0, //
+        ]
+    );
+}
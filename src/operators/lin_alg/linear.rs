@@ -1,43 +1,19 @@
 use crate::{
-    error, new_tensor_with_grad,
-    tensor::{Error, ErrorEnum},
-    Add, BinaryOperator, Device, MatMul, TensorWithGrad, UnaryOperator,
+    new_tensor_with_grad, tensor::Error, Add, BinaryOperator, Device, LoraAdapter, MatMul,
+    ScalarMul, SparseEmbedding, TensorWithGrad, UnaryOperator, WeightsInitialization,
 };
-use rand::{thread_rng, Rng};
-use rand_distr::Normal;
-
-pub enum WeightsInitialization {
-    None,
-    Kaiming,
-    Xavier,
-}
 
 pub struct Linear {
     weights: TensorWithGrad,
     biases: TensorWithGrad,
+    adapter: Option<(LoraAdapter, ScalarMul)>,
     matmul: MatMul,
     add: Add,
 }
 
-fn kaiming_initialization(
-    weights_rows: usize,
-    _weights_cols: usize,
-    weights: &mut Vec<f32>,
-) -> Result<(), Error> {
-    let mut rng = thread_rng();
-    let mean = 0.0;
-    let fan_in = weights_rows as f32;
-    let stddev = (2.0 / fan_in).sqrt();
-    let distribution =
-        Normal::new(mean, stddev).map_err(|_| error!(ErrorEnum::UnsupportedOperation))?;
-
-    for index in 0..weights.len() {
-        weights[index] = rng.sample(distribution);
-    }
-    Ok(())
-}
-
 impl Linear {
+    /// When the device has a LoraConfig, the weights and biases are frozen
+    /// and a LoraAdapter is the only trainable part.
     pub fn new(
         device: &Device,
         weights_rows: usize,
@@ -45,18 +21,26 @@ impl Linear {
         weights_initialization: WeightsInitialization,
         bias_rows: usize,
     ) -> Result<Self, Error> {
-        let mut weights = Vec::new();
-        weights.resize(weights_rows * weights_cols, 0.0);
-        match weights_initialization {
-            WeightsInitialization::None => {}
-            WeightsInitialization::Kaiming => {
-                kaiming_initialization(weights_rows, weights_cols, &mut weights)?;
-            }
-            WeightsInitialization::Xavier => todo!(),
-        }
+        let (fan_in, fan_out) = (weights_cols, weights_rows);
+        let weights = weights_initialization.initialize(
+            &mut *device.rng(),
+            weights_rows,
+            weights_cols,
+            fan_in,
+            fan_out,
+        )?;
 
-        let weights =
-            new_tensor_with_grad!(device, weights_rows, weights_cols, weights, &[], true, true)?;
+        let lora_config = device.lora_config();
+        let trainable = lora_config.is_none();
+        let weights = new_tensor_with_grad!(
+            device,
+            weights_rows,
+            weights_cols,
+            weights,
+            &[],
+            trainable,
+            trainable,
+        )?;
 
         let biases_len = bias_rows * weights_rows;
         let biases = new_tensor_with_grad!(
@@ -65,14 +49,80 @@ impl Linear {
             weights_rows,
             vec![0.0; biases_len],
             &[],
-            true,
-            true,
+            trainable,
+            trainable,
         )?;
+        weights.add_tag("Linear");
+        weights.add_tag("weights");
+        biases.add_tag("Linear");
+        biases.add_tag("biases");
+
+        let adapter = match lora_config {
+            Some(config) => {
+                let adapter = LoraAdapter::try_new(device, config, &weights)?;
+                device.add_lora_adapter(&adapter);
+                Some((adapter, ScalarMul::new(device, config.scaling())))
+            }
+            None => None,
+        };
 
         let transb = true;
         let op = Self {
             weights,
             biases,
+            adapter,
+            matmul: MatMul::new(device, transb),
+            add: Add::new(device),
+        };
+        Ok(op)
+    }
+
+    /// Output projection that uses the table of a SparseEmbedding as its weights.
+    /// The gradients of both uses are accumulated in the same parameter.
+    /// Only the biases are new parameters.
+    /// When the device has a LoraConfig, the biases are frozen like the table
+    /// and a LoraAdapter of the table is the only trainable part.
+    ///
+    /// See:
+    /// Using the Output Embedding to Improve Language Models
+    /// https://arxiv.org/abs/1608.05859
+    pub fn try_new_tied(
+        device: &Device,
+        embedding: &SparseEmbedding,
+        bias_rows: usize,
+    ) -> Result<Self, Error> {
+        // The table of SparseEmbedding is stored as [num_embeddings, embedding_dim],
+        // like the weights of a Linear.
+        let weights = embedding.embedding_table().clone();
+        let num_embeddings = weights.tensor().rows();
+        let lora_config = device.lora_config();
+        let trainable = lora_config.is_none();
+        let biases = new_tensor_with_grad!(
+            device,
+            bias_rows,
+            num_embeddings,
+            vec![0.0; bias_rows * num_embeddings],
+            &[],
+            trainable,
+            trainable,
+        )?;
+        biases.add_tag("Linear");
+        biases.add_tag("biases");
+
+        let adapter = match lora_config {
+            Some(config) => {
+                let adapter = LoraAdapter::try_new(device, config, &weights)?;
+                device.add_lora_adapter(&adapter);
+                Some((adapter, ScalarMul::new(device, config.scaling())))
+            }
+            None => None,
+        };
+
+        let transb = true;
+        let op = Self {
+            weights,
+            biases,
+            adapter,
             matmul: MatMul::new(device, transb),
             add: Add::new(device),
         };
@@ -83,6 +133,13 @@ impl Linear {
 impl UnaryOperator for Linear {
     fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
         let product = self.matmul.forward(input, &self.weights)?;
+        let product = match &self.adapter {
+            Some((adapter, scalar_mul)) => {
+                let delta = adapter.forward(&self.matmul, scalar_mul, input)?;
+                self.add.forward(&product, &delta)?
+            }
+            None => product,
+        };
         let sum = self.add.forward(&product, &self.biases)?;
         Ok(sum)
     }
@@ -0,0 +1,206 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error, new_tensor_with_grad,
+    tensor::{Error, ErrorEnum, Tensor},
+    BinaryOperator, Device, FanMode, MatMul, ScalarMul, TensorWithGrad, UnaryOperator,
+    WeightsInitialization,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Settings of the low-rank adapters.
+/// The Linear layers created while the settings are set on the Device with
+/// Device::set_lora_config get an adapter and frozen weights and biases.
+/// The other parameters created meanwhile, like embedding tables and
+/// normalization gains and biases, are frozen too.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoraConfig {
+    pub rank: usize,
+    pub alpha: f32,
+}
+
+impl LoraConfig {
+    /// The product of the adapter is multiplied by alpha / rank.
+    pub fn scaling(&self) -> f32 {
+        self.alpha / self.rank as f32
+    }
+}
+
+/// Low-rank adapter of a Linear with weights W.
+/// The weights of the Linear become W + (alpha / rank) * B A
+/// where A has shape [rank, W.cols] and B has shape [W.rows, rank].
+/// B is initialized with zeros so that the adapter starts as the identity.
+///
+/// See:
+/// LoRA: Low-Rank Adaptation of Large Language Models
+/// https://arxiv.org/abs/2106.09685
+#[derive(Clone)]
+pub struct LoraAdapter {
+    config: LoraConfig,
+    weights: TensorWithGrad,
+    a: TensorWithGrad,
+    b: TensorWithGrad,
+}
+
+impl LoraAdapter {
+    pub(crate) fn try_new(
+        device: &Device,
+        config: LoraConfig,
+        weights: &TensorWithGrad,
+    ) -> Result<Self, Error> {
+        if config.rank == 0 {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        let (rows, cols) = {
+            let weights: &Tensor = &weights.tensor();
+            (weights.rows(), weights.cols())
+        };
+        let (fan_in, fan_out) = (cols, config.rank);
+        let a = WeightsInitialization::KaimingNormal(FanMode::FanIn).initialize(
+            &mut *device.rng(),
+            config.rank,
+            cols,
+            fan_in,
+            fan_out,
+        )?;
+        let a = new_tensor_with_grad!(device, config.rank, cols, a, &[], true, true)?;
+        let b = new_tensor_with_grad!(
+            device,
+            rows,
+            config.rank,
+            vec![0.0; rows * config.rank],
+            &[],
+            true,
+            true,
+        )?;
+        for tensor in [&a, &b] {
+            tensor.add_tag("Linear");
+            tensor.add_tag("lora");
+        }
+        Ok(Self {
+            config,
+            weights: weights.clone(),
+            a,
+            b,
+        })
+    }
+
+    pub fn config(&self) -> &LoraConfig {
+        &self.config
+    }
+
+    /// Frozen weights of the adapted Linear.
+    pub fn weights(&self) -> &TensorWithGrad {
+        &self.weights
+    }
+
+    pub fn a(&self) -> &TensorWithGrad {
+        &self.a
+    }
+
+    pub fn b(&self) -> &TensorWithGrad {
+        &self.b
+    }
+
+    /// (alpha / rank) * (x A^T) B^T
+    pub(crate) fn forward(
+        &self,
+        matmul: &MatMul,
+        scalar_mul: &ScalarMul,
+        input: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        let low_rank = matmul.forward(input, &self.a)?;
+        let product = matmul.forward(&low_rank, &self.b)?;
+        scalar_mul.forward(&product)
+    }
+
+    /// Adds (alpha / rank) * B A to the weights and resets B to zeros.
+    /// The output of the Linear is unchanged and training can continue with a fresh adapter.
+    /// The weights of a tied Linear are the table of its embedding,
+    /// so the merge also changes the embedded tokens.
+    pub fn merge(&self) -> Result<(), Error> {
+        let weights: &Tensor = &self.weights.tensor();
+        let a: &Tensor = &self.a.tensor();
+        let b: &Tensor = &self.b.tensor();
+        let (rows, cols, rank) = (weights.rows(), weights.cols(), self.config.rank);
+        let a_values = a.get_values()?;
+        let b_values = b.get_values()?;
+        let mut values = weights.get_values()?;
+        let scaling = self.config.scaling();
+        for row in 0..rows {
+            for col in 0..cols {
+                let delta: f32 = (0..rank)
+                    .map(|k| b_values[row * rank + k] * a_values[k * cols + col])
+                    .sum();
+                values[row * cols + col] += scaling * delta;
+            }
+        }
+        weights.set_values(values)?;
+        b.set_values(vec![0.0; b.len()])
+    }
+}
+
+/// Merges all the adapters of the device into their weights.
+pub fn merge_lora_adapters(device: &Device) -> Result<(), Error> {
+    for adapter in device.lora_adapters().iter() {
+        adapter.merge()?;
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedLoraAdapter {
+    rank: usize,
+    alpha: f32,
+    a: Vec<f32>,
+    b: Vec<f32>,
+}
+
+/// Writes the A and B matrices of the adapters of the device, in creation order, to a JSON file.
+/// The frozen weights are not written.
+pub fn save_lora_adapters(device: &Device, path: &str) -> Result<(), Error> {
+    let adapters = device
+        .lora_adapters()
+        .iter()
+        .map(|adapter| {
+            Ok(SavedLoraAdapter {
+                rank: adapter.config.rank,
+                alpha: adapter.config.alpha,
+                a: adapter.a.tensor().get_values()?,
+                b: adapter.b.tensor().get_values()?,
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let json = serde_json::to_string(&adapters).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+    fs::write(path, json).map_err(|_| error!(ErrorEnum::InputOutputError))
+}
+
+/// Reads adapters written by save_lora_adapters into the adapters of the device.
+/// The device must have the same adapters, with the same ranks and shapes.
+pub fn load_lora_adapters(device: &Device, path: &str) -> Result<(), Error> {
+    let json = fs::read_to_string(path).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+    let saved: Vec<SavedLoraAdapter> =
+        serde_json::from_str(&json).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+    let adapters = device.lora_adapters();
+    if saved.len() != adapters.len() {
+        return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+    }
+    for (adapter, saved) in adapters.iter().zip(saved) {
+        let a: &Tensor = &adapter.a.tensor();
+        let b: &Tensor = &adapter.b.tensor();
+        if saved.rank != adapter.config.rank
+            || saved.alpha != adapter.config.alpha
+            || saved.a.len() != a.len()
+            || saved.b.len() != b.len()
+        {
+            return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+        }
+        a.set_values(saved.a)?;
+        b.set_values(saved.b)?;
+    }
+    Ok(())
+}
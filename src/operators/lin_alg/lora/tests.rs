@@ -0,0 +1,167 @@
+use crate::{
+    datasets::into_token_id_rows, load_lora_adapters, merge_lora_adapters, new_tensor_with_grad,
+    save_lora_adapters, stream::StreamTrait, tensor::Tensor, transformer::TransformerConfig,
+    transformer_model::TransformerModel, Device, Linear, LoraConfig, SparseEmbedding,
+    TensorWithGrad, UnaryOperator, WeightsInitialization,
+};
+
+fn run(device: &Device, output: &TensorWithGrad) -> Vec<f32> {
+    let device_stream = device.new_stream().unwrap();
+    for tensor in output.get_tape().iter() {
+        tensor.forward(device, &device_stream).unwrap();
+    }
+    device_stream.wait_for().unwrap();
+    output.tensor().get_values().unwrap()
+}
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected.iter()) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "actual {}, expected {}",
+            actual,
+            expected
+        );
+    }
+}
+
+fn adapted_linear(device: &Device) -> (Linear, TensorWithGrad) {
+    device.set_lora_config(Some(LoraConfig {
+        rank: 2,
+        alpha: 4.0,
+    }));
+    let linear = Linear::new(device, 3, 4, WeightsInitialization::Kaiming, 1).unwrap();
+    device.set_lora_config(None);
+    let input =
+        new_tensor_with_grad!(device, 1, 4, vec![1.0, -0.5, 0.25, 2.0], &[], false, false).unwrap();
+    (linear, input)
+}
+
+#[test]
+fn only_adapters_are_parameters() {
+    let device = Device::default();
+    let (linear, input) = adapted_linear(&device);
+    let parameters = device.parameter_tensors();
+    assert_eq!(parameters.len(), 2);
+    assert!(parameters.iter().all(|parameter| parameter.has_tag("lora")));
+
+    // B is zeros, so the adapted Linear starts with the output of the frozen weights.
+    let adapters = device.lora_adapters();
+    let weights = adapters[0].weights().tensor().get_values().unwrap();
+    let expected: Vec<f32> = weights
+        .chunks(4)
+        .map(|row| {
+            row.iter()
+                .zip([1.0, -0.5, 0.25, 2.0])
+                .map(|(w, x)| w * x)
+                .sum()
+        })
+        .collect();
+    let output = linear.forward(&input).unwrap();
+    assert_close(&run(&device, &output), &expected);
+}
+
+#[test]
+fn only_adapters_of_a_transformer_are_parameters() {
+    let device = Device::default();
+    device.set_lora_config(Some(LoraConfig {
+        rank: 2,
+        alpha: 4.0,
+    }));
+    let config = TransformerConfig {
+        tied_embedding: true,
+        ..TransformerConfig::new(2)
+    };
+    let model = TransformerModel::new(&device, 2, 4, 3, 8, &config).unwrap();
+    device.set_lora_config(None);
+
+    // The embedding table and the normalization gains and biases are frozen.
+    let parameters = device.parameter_tensors().clone();
+    assert_eq!(parameters.len(), 2 * device.lora_adapters().len());
+    assert!(parameters.iter().all(|parameter| parameter.has_tag("lora")));
+
+    let input = into_token_id_rows(&device, &[1, 2, 3]).unwrap();
+    let output = model.forward(&input).unwrap();
+    run(&device, &output);
+    let device_stream = device.new_stream().unwrap();
+    let len = output.tensor().len();
+    output.gradient().set_values(vec![1.0; len]).unwrap();
+    for tensor in output.get_backward_tape().iter() {
+        tensor.compute_gradient(&device, &device_stream).unwrap();
+    }
+    device_stream.wait_for().unwrap();
+    assert!(parameters.iter().any(|parameter| {
+        let gradient: &Tensor = &parameter.gradient();
+        gradient.get_values().unwrap().iter().any(|x| *x != 0.0)
+    }));
+}
+
+#[test]
+fn tied_projection_has_an_adapter() {
+    let device = Device::default();
+    device.set_lora_config(Some(LoraConfig {
+        rank: 2,
+        alpha: 4.0,
+    }));
+    let embedding = SparseEmbedding::new(&device, 5, 3).unwrap();
+    let linear = Linear::try_new_tied(&device, &embedding, 1).unwrap();
+    device.set_lora_config(None);
+
+    // The table and the biases are frozen, the adapter of the table is trainable.
+    let adapters = device.lora_adapters().clone();
+    assert_eq!(adapters.len(), 1);
+    let table_name = embedding.embedding_table().tensor().name();
+    assert_eq!(adapters[0].weights().tensor().name(), table_name);
+    let parameters = device.parameter_tensors().clone();
+    assert_eq!(parameters.len(), 2);
+    assert!(parameters.iter().all(|parameter| parameter.has_tag("lora")));
+
+    let input = into_token_id_rows(&device, &[2]).unwrap();
+    let output = linear.forward(&embedding.forward(&input).unwrap()).unwrap();
+    run(&device, &output);
+    let device_stream = device.new_stream().unwrap();
+    output.gradient().set_values(vec![1.0; 5]).unwrap();
+    for tensor in output.get_backward_tape().iter() {
+        tensor.compute_gradient(&device, &device_stream).unwrap();
+    }
+    device_stream.wait_for().unwrap();
+    let gradient: &Tensor = &adapters[0].b().gradient();
+    assert!(gradient.get_values().unwrap().iter().any(|x| *x != 0.0));
+}
+
+#[test]
+fn merge_keeps_the_output() {
+    let device = Device::default();
+    let (linear, input) = adapted_linear(&device);
+    let output = linear.forward(&input).unwrap();
+    let adapter = device.lora_adapters()[0].clone();
+    let b: &Tensor = &adapter.b().tensor();
+    b.set_values(vec![0.5, -1.0, 0.25, 1.5, -0.75, 1.0])
+        .unwrap();
+    let adapted = run(&device, &output);
+
+    merge_lora_adapters(&device).unwrap();
+    assert_close(&b.get_values().unwrap(), &[0.0; 6]);
+    assert_close(&run(&device, &output), &adapted);
+}
+
+#[test]
+fn save_and_load_adapters() {
+    let device = Device::default();
+    let _ = adapted_linear(&device);
+    let adapter = device.lora_adapters()[0].clone();
+    let b: &Tensor = &adapter.b().tensor();
+    let values = vec![0.5, -1.0, 0.25, 1.5, -0.75, 1.0];
+    b.set_values(values.clone()).unwrap();
+
+    let path = std::env::temp_dir().join("novigrad_lora_adapters.json");
+    let path = path.to_str().unwrap();
+    save_lora_adapters(&device, path).unwrap();
+    b.set_values(vec![0.0; 6]).unwrap();
+    load_lora_adapters(&device, path).unwrap();
+    assert_close(&b.get_values().unwrap(), &values);
+
+    let other_device = Device::default();
+    assert!(load_lora_adapters(&other_device, path).is_err());
+}
@@ -2,6 +2,10 @@ mod gemm;
 pub use gemm::*;
 mod linear;
 pub use linear::*;
+mod lora;
+pub use lora::*;
+mod weights_initialization;
+pub use weights_initialization::*;
 mod embedding;
 pub use embedding::*;
 mod matmul;
@@ -18,12 +22,14 @@ mod sub;
 pub use sub::*;
 mod clip_norm;
 pub use clip_norm::*;
+mod clip_global_norm;
+pub use clip_global_norm::*;
 mod div;
 pub use div::*;
 mod sqrt;
 pub use sqrt::*;
 pub mod clip;
+pub mod gather;
 pub mod identity;
 pub mod pow;
-pub mod row_max;
 pub mod transpose;
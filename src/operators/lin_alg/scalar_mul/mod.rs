@@ -74,10 +74,17 @@ impl UnaryOperator for ScalarMul {
             let input = inputs[0];
             let output_ = outputs[0];
             if output_.requires_grad() {
+                let tmp = new_tensor!(self.device, rows, cols, vec![0.0; len])?;
+                output.push_instruction(gradient_instruction!(
+                    OpCode::ScalarMul,
+                    OperatorAttributes::None,
+                    &[&alpha, input],
+                    &[&tmp],
+                ));
                 output.push_instruction(gradient_instruction!(
                     OpCode::Add,
                     OperatorAttributes::None,
-                    &[input, output_],
+                    &[&tmp, output_],
                     &[output_],
                 ));
             }
@@ -1,4 +1,7 @@
-use crate::{new_tensor, Device, ExecutableOperator, ScalarMul};
+use crate::{
+    new_tensor, new_tensor_with_grad, stream::StreamTrait, Device, ExecutableOperator, ScalarMul,
+    UnaryOperator,
+};
 
 #[test]
 fn scalar_mul() {
@@ -48,3 +51,15 @@ fn scalar_mul() {
     .unwrap();
     assert_eq!(result, expected_result);
 }
+
+#[test]
+fn gradient_is_scaled() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let input = new_tensor_with_grad!(device, 1, 2, vec![1.0, 2.0], &[], true, false).unwrap();
+    let output = ScalarMul::new(&device, -2.0).forward(&input).unwrap();
+    output.gradient().set_values(vec![1.0, 3.0]).unwrap();
+    output.compute_gradient(&device, &device_stream).unwrap();
+    device_stream.wait_for().unwrap();
+    assert_eq!(input.gradient().get_values().unwrap(), vec![-2.0, -6.0]);
+}
@@ -0,0 +1,160 @@
+use crate::{
+    error,
+    tensor::{Error, ErrorEnum},
+};
+use rand::{distributions::Uniform, Rng};
+use rand_distr::Normal;
+
+#[cfg(test)]
+mod tests;
+
+/// Fan used by the Kaiming initializations.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FanMode {
+    #[default]
+    FanIn,
+    FanOut,
+}
+
+/// Initialization of weights with rows x cols values.
+/// For a Linear, fan_in is cols and fan_out is rows.
+/// The values are drawn from the random number generator of the Device,
+/// see Device::set_seed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeightsInitialization {
+    /// Zeros
+    None,
+    /// Kaiming normal with the fan-out of a Linear, i.e. N(0, 2 / rows).
+    Kaiming,
+    /// Xavier uniform, U(-a, a) with a = sqrt(6 / (fan_in + fan_out))
+    /// See:
+    /// Understanding the difficulty of training deep feedforward neural networks
+    /// https://proceedings.mlr.press/v9/glorot10a/glorot10a.pdf
+    Xavier,
+    /// Xavier normal, N(0, 2 / (fan_in + fan_out))
+    XavierNormal,
+    /// U(-a, a) with a = sqrt(6 / fan)
+    /// See:
+    /// Delving Deep into Rectifiers: Surpassing Human-Level Performance on ImageNet Classification
+    /// https://arxiv.org/abs/1502.01852
+    KaimingUniform(FanMode),
+    /// N(0, 2 / fan)
+    KaimingNormal(FanMode),
+    /// Rows (or columns if there are more rows than columns) are orthonormal.
+    /// See:
+    /// Exact solutions to the nonlinear dynamics of learning in deep linear neural networks
+    /// https://arxiv.org/abs/1312.6120
+    Orthogonal,
+    /// N(0, std^2) with the values beyond 2 std drawn again.
+    TruncatedNormal { std: f32 },
+    /// N(0, 0.02^2 / (2 * layers)), for the projections that are added to the residual stream.
+    /// See:
+    /// Language Models are Unsupervised Multitask Learners
+    /// https://cdn.openai.com/better-language-models/language_models_are_unsupervised_multitask_learners.pdf
+    ScaledResidual { layers: usize },
+}
+
+fn sample_normal(rng: &mut impl Rng, std: f32, len: usize) -> Result<Vec<f32>, Error> {
+    let distribution =
+        Normal::new(0.0, std).map_err(|_| error!(ErrorEnum::IncorrectOperatorConfiguration))?;
+    Ok((0..len).map(|_| rng.sample(distribution)).collect())
+}
+
+fn sample_uniform(rng: &mut impl Rng, bound: f32, len: usize) -> Vec<f32> {
+    let distribution = Uniform::new_inclusive(-bound, bound);
+    (0..len).map(|_| rng.sample(distribution)).collect()
+}
+
+fn sample_truncated_normal(rng: &mut impl Rng, std: f32, len: usize) -> Result<Vec<f32>, Error> {
+    let distribution =
+        Normal::new(0.0, std).map_err(|_| error!(ErrorEnum::IncorrectOperatorConfiguration))?;
+    Ok((0..len)
+        .map(|_| loop {
+            let value: f32 = rng.sample(distribution);
+            if value.abs() <= 2.0 * std {
+                break value;
+            }
+        })
+        .collect())
+}
+
+/// Modified Gram-Schmidt on the vectors of a normal matrix.
+fn sample_orthogonal(rng: &mut impl Rng, rows: usize, cols: usize) -> Result<Vec<f32>, Error> {
+    // Orthonormalize the smallest dimension, the vectors have the length of the other one.
+    let (count, len) = if rows <= cols {
+        (rows, cols)
+    } else {
+        (cols, rows)
+    };
+    let mut vectors = sample_normal(rng, 1.0, count * len)?;
+    for i in 0..count {
+        for j in 0..i {
+            let dot: f32 = (0..len)
+                .map(|k| vectors[i * len + k] * vectors[j * len + k])
+                .sum();
+            for k in 0..len {
+                vectors[i * len + k] -= dot * vectors[j * len + k];
+            }
+        }
+        let norm = (0..len)
+            .map(|k| vectors[i * len + k] * vectors[i * len + k])
+            .sum::<f32>()
+            .sqrt();
+        if norm == 0.0 {
+            return Err(error!(ErrorEnum::UnsupportedOperation));
+        }
+        for k in 0..len {
+            vectors[i * len + k] /= norm;
+        }
+    }
+    if rows <= cols {
+        return Ok(vectors);
+    }
+    let mut weights = vec![0.0; rows * cols];
+    for col in 0..cols {
+        for row in 0..rows {
+            weights[row * cols + col] = vectors[col * rows + row];
+        }
+    }
+    Ok(weights)
+}
+
+impl WeightsInitialization {
+    pub fn initialize(
+        &self,
+        rng: &mut impl Rng,
+        rows: usize,
+        cols: usize,
+        fan_in: usize,
+        fan_out: usize,
+    ) -> Result<Vec<f32>, Error> {
+        let len = rows * cols;
+        let fan = |mode: &FanMode| match mode {
+            FanMode::FanIn => fan_in as f32,
+            FanMode::FanOut => fan_out as f32,
+        };
+        let fans = (fan_in + fan_out) as f32;
+        match self {
+            WeightsInitialization::None => Ok(vec![0.0; len]),
+            WeightsInitialization::Kaiming => sample_normal(rng, (2.0 / rows as f32).sqrt(), len),
+            WeightsInitialization::Xavier => Ok(sample_uniform(rng, (6.0 / fans).sqrt(), len)),
+            WeightsInitialization::XavierNormal => sample_normal(rng, (2.0 / fans).sqrt(), len),
+            WeightsInitialization::KaimingUniform(mode) => {
+                Ok(sample_uniform(rng, (6.0 / fan(mode)).sqrt(), len))
+            }
+            WeightsInitialization::KaimingNormal(mode) => {
+                sample_normal(rng, (2.0 / fan(mode)).sqrt(), len)
+            }
+            WeightsInitialization::Orthogonal => sample_orthogonal(rng, rows, cols),
+            WeightsInitialization::TruncatedNormal { std } => {
+                sample_truncated_normal(rng, *std, len)
+            }
+            WeightsInitialization::ScaledResidual { layers } => {
+                if *layers == 0 {
+                    return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+                }
+                sample_normal(rng, 0.02 / (2.0 * *layers as f32).sqrt(), len)
+            }
+        }
+    }
+}
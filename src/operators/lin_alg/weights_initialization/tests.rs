@@ -0,0 +1,101 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{Device, FanMode, Linear, WeightsInitialization};
+
+fn mean_and_std(values: &[f32]) -> (f32, f32) {
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let variance =
+        values.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / values.len() as f32;
+    (mean, variance.sqrt())
+}
+
+#[test]
+fn distributions() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let (rows, cols) = (100, 300);
+    let cases = [
+        (
+            WeightsInitialization::Xavier,
+            (6.0 / 400.0_f32).sqrt() / 3.0_f32.sqrt(),
+        ),
+        (
+            WeightsInitialization::XavierNormal,
+            (2.0 / 400.0_f32).sqrt(),
+        ),
+        (
+            WeightsInitialization::KaimingUniform(FanMode::FanIn),
+            (6.0 / 300.0_f32).sqrt() / 3.0_f32.sqrt(),
+        ),
+        (
+            WeightsInitialization::KaimingNormal(FanMode::FanOut),
+            (2.0 / 100.0_f32).sqrt(),
+        ),
+        (WeightsInitialization::Kaiming, (2.0 / 100.0_f32).sqrt()),
+        (
+            WeightsInitialization::ScaledResidual { layers: 2 },
+            0.02 / 2.0,
+        ),
+    ];
+    for (initialization, expected_std) in cases {
+        let values = initialization
+            .initialize(&mut rng, rows, cols, cols, rows)
+            .unwrap();
+        let (mean, std) = mean_and_std(&values);
+        assert!(
+            mean.abs() < 0.05 * expected_std + 1e-3,
+            "{:?}",
+            initialization
+        );
+        assert!(
+            (std - expected_std).abs() < 0.05 * expected_std,
+            "{:?} std {} expected {}",
+            initialization,
+            std,
+            expected_std
+        );
+    }
+
+    let std = 0.5;
+    let values = WeightsInitialization::TruncatedNormal { std }
+        .initialize(&mut rng, rows, cols, cols, rows)
+        .unwrap();
+    assert!(values.iter().all(|x| x.abs() <= 2.0 * std));
+}
+
+#[test]
+fn orthogonal() {
+    let mut rng = StdRng::seed_from_u64(2);
+    for (rows, cols) in [(3, 5), (5, 3)] {
+        let values = WeightsInitialization::Orthogonal
+            .initialize(&mut rng, rows, cols, cols, rows)
+            .unwrap();
+        // W W^T = I when there are fewer rows, W^T W = I otherwise.
+        let (count, stride, step) = match rows <= cols {
+            true => (rows, cols, 1),
+            false => (cols, 1, cols),
+        };
+        let len = rows.max(cols);
+        for i in 0..count {
+            for j in 0..count {
+                let dot: f32 = (0..len)
+                    .map(|k| values[i * stride + k * step] * values[j * stride + k * step])
+                    .sum();
+                let expected = if i == j { 1.0 } else { 0.0 };
+                assert!((dot - expected).abs() < 1e-5);
+            }
+        }
+    }
+}
+
+#[test]
+fn seeded_construction_is_reproducible() {
+    let weights = |seed| {
+        let device = Device::default();
+        device.set_seed(seed);
+        let _ = Linear::new(&device, 4, 3, WeightsInitialization::Xavier, 1).unwrap();
+        let weights = device.parameter_tensors()[0].tensor().get_values().unwrap();
+        weights
+    };
+    assert_eq!(weights(7), weights(7));
+    assert_ne!(weights(7), weights(8));
+}
@@ -0,0 +1,130 @@
+use crate::{
+    devices::Device,
+    gradient_instruction, loss_instruction, new_tensor, new_tensor_with_grad,
+    opcode::OpCode,
+    tensor::{Error, Tensor},
+    BinaryOperator, OperatorAttributes, TensorWithGrad,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Local loss of a layer trained with the forward-forward algorithm.
+///
+/// goodness = Σ h_i^2
+/// probability = Sigmoid(goodness - threshold)
+/// loss = (label - probability)^2
+///
+/// The label is 1 for positive data and 0 for negative data.
+///
+/// See:
+/// The Forward-Forward Algorithm: Some Preliminary Investigations
+/// https://arxiv.org/abs/2212.13345
+pub struct GoodnessLoss {
+    device: Device,
+    threshold: f32,
+}
+
+impl GoodnessLoss {
+    pub fn new(device: &Device, threshold: f32) -> Self {
+        Self {
+            device: device.clone(),
+            threshold,
+        }
+    }
+}
+
+impl BinaryOperator for GoodnessLoss {
+    fn forward(
+        &self,
+        input_1: &TensorWithGrad,
+        input_2: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        let device = &self.device;
+        let output =
+            new_tensor_with_grad!(device, 1, 1, vec![0.0], &[input_1, input_2], true, false)?;
+        let label: &Tensor = &input_1.tensor();
+        let activations: &Tensor = &input_2.tensor();
+
+        let rows = activations.rows();
+        let cols = activations.cols();
+        let zeros = new_tensor!(device, rows, cols, vec![0.0; rows * cols])?;
+        let minus_threshold = new_tensor!(device, 1, 1, vec![-self.threshold])?;
+        let goodness = new_tensor!(device, 1, 1, vec![0.0])?;
+        let logit = new_tensor!(device, 1, 1, vec![0.0])?;
+        let probability = new_tensor!(device, 1, 1, vec![0.0])?;
+
+        output.push_instruction(loss_instruction!(
+            OpCode::ReduceSumSquare,
+            OperatorAttributes::None,
+            &[&zeros, activations],
+            &[&goodness],
+        ));
+        output.push_instruction(loss_instruction!(
+            OpCode::ScalarAdd,
+            OperatorAttributes::None,
+            &[&minus_threshold, &goodness],
+            &[&logit],
+        ));
+        output.push_instruction(loss_instruction!(
+            OpCode::Sigmoid,
+            OperatorAttributes::None,
+            &[&logit],
+            &[&probability],
+        ));
+        output.push_instruction(loss_instruction!(
+            OpCode::ReduceSumSquare,
+            OperatorAttributes::None,
+            &[label, &probability],
+            &[&output.tensor()],
+        ));
+
+        let activations_gradient: &Tensor = &input_2.gradient();
+        if activations_gradient.requires_grad() {
+            // d loss / d h_i = 2 * (probability - label) * probability * (1 - probability) * 2 * h_i
+            let one = new_tensor!(device, 1, 1, vec![1.0])?;
+            let four = new_tensor!(device, 1, 1, vec![4.0])?;
+            let error = new_tensor!(device, 1, 1, vec![0.0])?;
+            let one_minus_probability = new_tensor!(device, 1, 1, vec![0.0])?;
+            let alpha = new_tensor!(device, 1, 1, vec![0.0])?;
+            output.push_instruction(gradient_instruction!(
+                OpCode::Sub,
+                OperatorAttributes::None,
+                &[&probability, label],
+                &[&error],
+            ));
+            output.push_instruction(gradient_instruction!(
+                OpCode::Sub,
+                OperatorAttributes::None,
+                &[&one, &probability],
+                &[&one_minus_probability],
+            ));
+            output.push_instruction(gradient_instruction!(
+                OpCode::Mul,
+                OperatorAttributes::None,
+                &[&error, &probability],
+                &[&alpha],
+            ));
+            output.push_instruction(gradient_instruction!(
+                OpCode::Mul,
+                OperatorAttributes::None,
+                &[&alpha, &one_minus_probability],
+                &[&alpha],
+            ));
+            output.push_instruction(gradient_instruction!(
+                OpCode::ScalarMul,
+                OperatorAttributes::None,
+                &[&four, &alpha],
+                &[&alpha],
+            ));
+            output.push_instruction(gradient_instruction!(
+                OpCode::ScalarMul,
+                OperatorAttributes::None,
+                &[&alpha, activations],
+                &[activations_gradient],
+            ));
+        }
+
+        Ok(output)
+    }
+}
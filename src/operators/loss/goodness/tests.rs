@@ -0,0 +1,29 @@
+use crate::{
+    new_tensor, new_tensor_with_grad, stream::StreamTrait, tensor::Tensor, BinaryOperator, Device,
+};
+
+use super::GoodnessLoss;
+
+#[test]
+fn derive() {
+    let device = Device::default();
+    let label = new_tensor_with_grad!(device, 1, 1, vec![1.0], &[], false, false).unwrap();
+    let activations =
+        new_tensor_with_grad!(device, 1, 2, vec![1.0, 1.0], &[], true, false).unwrap();
+    let operator = GoodnessLoss::new(&device, 2.0);
+    let loss = operator.forward(&label, &activations).unwrap();
+    let device_stream = device.new_stream().unwrap();
+    loss.forward(&device, &device_stream).unwrap();
+    loss.compute_gradient(&device, &device_stream).unwrap();
+    device_stream.wait_for().unwrap();
+
+    // goodness = 2, probability = Sigmoid(0) = 0.5
+    let expected_loss = new_tensor!(device, 1, 1, vec![0.25]).unwrap();
+    let actual_loss: &Tensor = &loss.tensor();
+    assert_eq!(actual_loss, &expected_loss);
+
+    // 2 * (0.5 - 1) * 0.5 * 0.5 * 2 * 1 = -0.5
+    let expected_derived_loss = new_tensor!(device, 1, 2, vec![-0.5, -0.5]).unwrap();
+    let actual_derived_loss: &Tensor = &activations.gradient();
+    assert_eq!(actual_derived_loss, &expected_derived_loss);
+}
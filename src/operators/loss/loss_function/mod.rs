@@ -0,0 +1,431 @@
+use crate::{
+    devices::Device,
+    error, gradient_instruction, loss_instruction, new_tensor_with_grad,
+    opcode::OpCode,
+    stream::DeviceStream,
+    tensor::{Error, ErrorEnum, Tensor},
+    BinaryOperator, DeviceTrait, ExecutableOperator, OperatorAttributes, TensorWithGrad, EPSILON,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Loss functions that compare the expected output and the actual output
+/// element by element or row by row.
+/// The loss is a 1x1 tensor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LossFunction {
+    /// Mean of (actual - expected)^2
+    MeanSquaredError,
+    /// Mean binary cross-entropy of the sigmoid of the actual logits with the expected
+    /// probabilities. The sigmoid is part of the loss for numerical stability.
+    BinaryCrossEntropyWithLogits,
+    /// Mean Huber loss, quadratic below delta and linear above delta.
+    /// See:
+    /// Robust Estimation of a Location Parameter
+    /// https://projecteuclid.org/journals/annals-of-mathematical-statistics/volume-35/issue-1/Robust-Estimation-of-a-Location-Parameter/10.1214/aoms/1177703732.full
+    Huber { delta: f32 },
+    /// Mean smooth L1 loss, which is the Huber loss with delta = beta divided by beta.
+    /// See:
+    /// Fast R-CNN
+    /// https://arxiv.org/abs/1504.08083
+    SmoothL1 { beta: f32 },
+    /// KL(expected || actual) divided by the number of rows.
+    /// Rows of expected and actual are probability distributions.
+    /// See https://en.wikipedia.org/wiki/Kullback%E2%80%93Leibler_divergence
+    KlDivergence,
+    /// Mean of 1 - cos(expected row, actual row).
+    /// Every pair of rows is a similar pair (y = 1 in PyTorch).
+    CosineEmbedding,
+}
+
+impl LossFunction {
+    pub fn from_attributes(attributes: &OperatorAttributes) -> Result<&Self, Error> {
+        match attributes {
+            OperatorAttributes::Loss(function) => Ok(function),
+            _ => Err(error!(ErrorEnum::UnsupportedOperation)),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        let valid = match *self {
+            LossFunction::Huber { delta } => delta > 0.0,
+            LossFunction::SmoothL1 { beta } => beta > 0.0,
+            _ => true,
+        };
+        match valid {
+            true => Ok(()),
+            false => Err(error!(ErrorEnum::IncorrectOperatorConfiguration)),
+        }
+    }
+
+    /// Number of terms that are averaged.
+    fn count(&self, rows: usize, len: usize) -> f32 {
+        match self {
+            LossFunction::KlDivergence | LossFunction::CosineEmbedding => rows as f32,
+            _ => len as f32,
+        }
+    }
+
+    fn element_loss(&self, expected: f32, actual: f32) -> f32 {
+        let diff = actual - expected;
+        match *self {
+            LossFunction::MeanSquaredError => diff * diff,
+            LossFunction::BinaryCrossEntropyWithLogits => {
+                actual.max(0.0) - actual * expected + f32::ln_1p(f32::exp(-actual.abs()))
+            }
+            LossFunction::Huber { delta } => {
+                if diff.abs() <= delta {
+                    0.5 * diff * diff
+                } else {
+                    delta * (diff.abs() - 0.5 * delta)
+                }
+            }
+            LossFunction::SmoothL1 { beta } => {
+                if diff.abs() < beta {
+                    0.5 * diff * diff / beta
+                } else {
+                    diff.abs() - 0.5 * beta
+                }
+            }
+            LossFunction::KlDivergence => {
+                if expected > 0.0 {
+                    expected * f32::ln((expected + EPSILON) / (actual + EPSILON))
+                } else {
+                    0.0
+                }
+            }
+            LossFunction::CosineEmbedding => 0.0,
+        }
+    }
+
+    fn element_gradient(&self, expected: f32, actual: f32) -> f32 {
+        let diff = actual - expected;
+        match *self {
+            LossFunction::MeanSquaredError => 2.0 * diff,
+            LossFunction::BinaryCrossEntropyWithLogits => {
+                1.0 / (1.0 + f32::exp(-actual)) - expected
+            }
+            LossFunction::Huber { delta } => diff.clamp(-delta, delta),
+            LossFunction::SmoothL1 { beta } => {
+                if diff.abs() < beta {
+                    diff / beta
+                } else {
+                    diff.signum()
+                }
+            }
+            LossFunction::KlDivergence => -expected / (actual + EPSILON),
+            LossFunction::CosineEmbedding => 0.0,
+        }
+    }
+
+    /// Cosine similarity of two rows and the norms of the rows.
+    fn cosine(expected: &[f32], actual: &[f32]) -> (f32, f32, f32) {
+        let dot: f32 = expected.iter().zip(actual.iter()).map(|(e, a)| e * a).sum();
+        let expected_norm = expected.iter().map(|e| e * e).sum::<f32>().sqrt();
+        let actual_norm = actual.iter().map(|a| a * a).sum::<f32>().sqrt();
+        let cosine = dot / (expected_norm * actual_norm + EPSILON);
+        (cosine, expected_norm, actual_norm)
+    }
+
+    pub fn loss(&self, cols: usize, expected: &[f32], actual: &[f32]) -> f32 {
+        let rows = expected.len() / cols;
+        let sum: f32 = match self {
+            LossFunction::CosineEmbedding => expected
+                .chunks(cols)
+                .zip(actual.chunks(cols))
+                .map(|(expected, actual)| 1.0 - Self::cosine(expected, actual).0)
+                .sum(),
+            _ => expected
+                .iter()
+                .zip(actual.iter())
+                .map(|(expected, actual)| self.element_loss(*expected, *actual))
+                .sum(),
+        };
+        sum / self.count(rows, expected.len())
+    }
+
+    /// Derivative of the loss with respect to actual.
+    pub fn loss_gradient(
+        &self,
+        cols: usize,
+        expected: &[f32],
+        actual: &[f32],
+        gradient: &mut [f32],
+    ) {
+        let rows = expected.len() / cols;
+        let count = self.count(rows, expected.len());
+        match self {
+            LossFunction::CosineEmbedding => {
+                // d(1 - cos) / da = cos * a / |a|^2 - e / (|e| |a|)
+                for ((expected, actual), gradient) in expected
+                    .chunks(cols)
+                    .zip(actual.chunks(cols))
+                    .zip(gradient.chunks_mut(cols))
+                {
+                    let (cosine, expected_norm, actual_norm) = Self::cosine(expected, actual);
+                    if expected_norm == 0.0 || actual_norm == 0.0 {
+                        gradient.fill(0.0);
+                        continue;
+                    }
+                    for ((e, a), g) in expected.iter().zip(actual.iter()).zip(gradient.iter_mut()) {
+                        *g = (cosine * a / (actual_norm * actual_norm)
+                            - e / (expected_norm * actual_norm))
+                            / count;
+                    }
+                }
+            }
+            _ => {
+                for ((e, a), g) in expected.iter().zip(actual.iter()).zip(gradient.iter_mut()) {
+                    *g = self.element_gradient(*e, *a) / count;
+                }
+            }
+        }
+    }
+}
+
+/// Forward pass shared by the losses of LossFunction.
+/// Like the other losses, the gradient of actual is overwritten.
+pub struct Loss {
+    device: Device,
+    function: LossFunction,
+}
+
+impl Loss {
+    fn try_new(device: &Device, function: LossFunction) -> Result<Self, Error> {
+        function.validate()?;
+        Ok(Self {
+            device: device.clone(),
+            function,
+        })
+    }
+
+    fn forward(
+        &self,
+        input_1: &TensorWithGrad,
+        input_2: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        let expected: &Tensor = &input_1.tensor();
+        let actual: &Tensor = &input_2.tensor();
+        if *expected.size() != *actual.size() {
+            return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+        }
+        let output = new_tensor_with_grad!(
+            self.device,
+            1,
+            1,
+            vec![0.0],
+            &[input_1, input_2],
+            true,
+            false
+        )?;
+        let attributes = OperatorAttributes::Loss(self.function);
+
+        output.push_instruction(loss_instruction!(
+            OpCode::Loss,
+            attributes.clone(),
+            &[expected, actual],
+            &[&output.tensor()],
+        ));
+
+        let actual_gradient: &Tensor = &input_2.gradient();
+        if actual_gradient.requires_grad() {
+            output.push_instruction(gradient_instruction!(
+                OpCode::LossGradient,
+                attributes,
+                &[expected, actual],
+                &[actual_gradient],
+            ));
+        }
+
+        Ok(output)
+    }
+}
+
+fn check_shapes(expected: &Tensor, actual: &Tensor) -> Result<(), Error> {
+    if *expected.size() != *actual.size() {
+        return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+    }
+    Ok(())
+}
+
+impl ExecutableOperator for Loss {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let function = LossFunction::from_attributes(attributes)?;
+        let expected = inputs[0];
+        let actual = inputs[1];
+        let loss = outputs[0];
+        check_shapes(expected, actual)?;
+        device.loss(function, expected, actual, loss, device_stream)
+    }
+}
+
+pub struct LossGradient {}
+
+impl ExecutableOperator for LossGradient {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let function = LossFunction::from_attributes(attributes)?;
+        let expected = inputs[0];
+        let actual = inputs[1];
+        let actual_gradient = outputs[0];
+        check_shapes(expected, actual)?;
+        check_shapes(actual, actual_gradient)?;
+        device.loss_gradient(function, expected, actual, actual_gradient, device_stream)
+    }
+}
+
+pub struct MeanSquaredError {
+    loss: Loss,
+}
+
+impl MeanSquaredError {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            loss: Loss {
+                device: device.clone(),
+                function: LossFunction::MeanSquaredError,
+            },
+        }
+    }
+}
+
+impl BinaryOperator for MeanSquaredError {
+    fn forward(
+        &self,
+        input_1: &TensorWithGrad,
+        input_2: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        self.loss.forward(input_1, input_2)
+    }
+}
+
+/// The actual output is logits, there must be no Sigmoid before this loss.
+pub struct BinaryCrossEntropyWithLogits {
+    loss: Loss,
+}
+
+impl BinaryCrossEntropyWithLogits {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            loss: Loss {
+                device: device.clone(),
+                function: LossFunction::BinaryCrossEntropyWithLogits,
+            },
+        }
+    }
+}
+
+impl BinaryOperator for BinaryCrossEntropyWithLogits {
+    fn forward(
+        &self,
+        input_1: &TensorWithGrad,
+        input_2: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        self.loss.forward(input_1, input_2)
+    }
+}
+
+pub struct HuberLoss {
+    loss: Loss,
+}
+
+impl HuberLoss {
+    pub fn try_new(device: &Device, delta: f32) -> Result<Self, Error> {
+        let loss = Loss::try_new(device, LossFunction::Huber { delta })?;
+        Ok(Self { loss })
+    }
+}
+
+impl BinaryOperator for HuberLoss {
+    fn forward(
+        &self,
+        input_1: &TensorWithGrad,
+        input_2: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        self.loss.forward(input_1, input_2)
+    }
+}
+
+pub struct SmoothL1Loss {
+    loss: Loss,
+}
+
+impl SmoothL1Loss {
+    pub fn try_new(device: &Device, beta: f32) -> Result<Self, Error> {
+        let loss = Loss::try_new(device, LossFunction::SmoothL1 { beta })?;
+        Ok(Self { loss })
+    }
+}
+
+impl BinaryOperator for SmoothL1Loss {
+    fn forward(
+        &self,
+        input_1: &TensorWithGrad,
+        input_2: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        self.loss.forward(input_1, input_2)
+    }
+}
+
+/// The actual output is probabilities, usually from a Softmax created with Softmax::new.
+pub struct KlDivergence {
+    loss: Loss,
+}
+
+impl KlDivergence {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            loss: Loss {
+                device: device.clone(),
+                function: LossFunction::KlDivergence,
+            },
+        }
+    }
+}
+
+impl BinaryOperator for KlDivergence {
+    fn forward(
+        &self,
+        input_1: &TensorWithGrad,
+        input_2: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        self.loss.forward(input_1, input_2)
+    }
+}
+
+pub struct CosineEmbeddingLoss {
+    loss: Loss,
+}
+
+impl CosineEmbeddingLoss {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            loss: Loss {
+                device: device.clone(),
+                function: LossFunction::CosineEmbedding,
+            },
+        }
+    }
+}
+
+impl BinaryOperator for CosineEmbeddingLoss {
+    fn forward(
+        &self,
+        input_1: &TensorWithGrad,
+        input_2: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        self.loss.forward(input_1, input_2)
+    }
+}
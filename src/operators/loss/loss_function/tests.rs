@@ -0,0 +1,117 @@
+use crate::{
+    new_tensor_with_grad, stream::StreamTrait, BinaryOperator, Device, HuberLoss, LossFunction,
+    MeanSquaredError, SmoothL1Loss,
+};
+
+fn assert_close(actual: &[f32], expected: &[f32], tolerance: f32) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected.iter()) {
+        assert!(
+            (actual - expected).abs() < tolerance,
+            "actual {}, expected {}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn loss_values() {
+    let expected = [0.0, 1.0, 0.5, 0.5];
+    let actual = [0.5, -1.0, 0.25, 0.75];
+    assert_close(
+        &[LossFunction::MeanSquaredError.loss(2, &expected, &actual)],
+        &[(0.25 + 4.0 + 0.0625 + 0.0625) / 4.0],
+        1e-6,
+    );
+    assert_close(
+        &[LossFunction::Huber { delta: 1.0 }.loss(2, &expected, &actual)],
+        &[(0.125 + 1.5 + 0.03125 + 0.03125) / 4.0],
+        1e-6,
+    );
+    assert_close(
+        &[LossFunction::SmoothL1 { beta: 0.5 }.loss(2, &expected, &actual)],
+        &[(0.25 + 1.75 + 0.0625 + 0.0625) / 4.0],
+        1e-6,
+    );
+    let logits = [0.0, 2.0];
+    let labels = [1.0, 0.0];
+    let sigmoid = 1.0 / (1.0 + f32::exp(-2.0));
+    assert_close(
+        &[LossFunction::BinaryCrossEntropyWithLogits.loss(2, &labels, &logits)],
+        &[(-f32::ln(0.5) - f32::ln(1.0 - sigmoid)) / 2.0],
+        1e-6,
+    );
+    let p = [0.5, 0.5, 1.0, 0.0];
+    let q = [0.25, 0.75, 0.5, 0.5];
+    assert_close(
+        &[LossFunction::KlDivergence.loss(2, &p, &q)],
+        &[(0.5 * f32::ln(2.0) + 0.5 * f32::ln(2.0 / 3.0) + f32::ln(2.0)) / 2.0],
+        1e-5,
+    );
+    assert_close(
+        &[LossFunction::CosineEmbedding.loss(2, &[1.0, 0.0, 1.0, 1.0], &[2.0, 0.0, -1.0, -1.0])],
+        &[(0.0 + 2.0) / 2.0],
+        1e-5,
+    );
+}
+
+#[test]
+fn loss_gradients_match_finite_differences() {
+    let functions = [
+        LossFunction::MeanSquaredError,
+        LossFunction::BinaryCrossEntropyWithLogits,
+        LossFunction::Huber { delta: 0.3 },
+        LossFunction::SmoothL1 { beta: 0.3 },
+        LossFunction::KlDivergence,
+        LossFunction::CosineEmbedding,
+    ];
+    let expected = [0.1, 0.6, 0.3, 0.2, 0.5, 0.3];
+    let actual = [0.3, 0.5, 0.2, 0.6, 0.1, 0.3];
+    let cols = 3;
+    let h = 1e-3;
+    for function in functions {
+        let mut gradient = vec![0.0; actual.len()];
+        function.loss_gradient(cols, &expected, &actual, &mut gradient);
+        let numerical_gradient: Vec<f32> = (0..actual.len())
+            .map(|i| {
+                let mut plus = actual;
+                let mut minus = actual;
+                plus[i] += h;
+                minus[i] -= h;
+                (function.loss(cols, &expected, &plus) - function.loss(cols, &expected, &minus))
+                    / (2.0 * h)
+            })
+            .collect();
+        assert_close(&gradient, &numerical_gradient, 1e-2);
+    }
+}
+
+#[test]
+fn mean_squared_error_gradient() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let expected =
+        new_tensor_with_grad!(device, 1, 4, vec![1.0, 2.0, 3.0, 4.0], &[], false, false).unwrap();
+    let actual =
+        new_tensor_with_grad!(device, 1, 4, vec![2.0, 2.0, 1.0, 4.0], &[], true, false).unwrap();
+    let operator = MeanSquaredError::new(&device);
+    let loss = operator.forward(&expected, &actual).unwrap();
+    loss.forward(&device, &device_stream).unwrap();
+    loss.compute_gradient(&device, &device_stream).unwrap();
+    device_stream.wait_for().unwrap();
+    assert_close(&loss.tensor().get_values().unwrap(), &[1.25], 1e-6);
+    assert_close(
+        &actual.gradient().get_values().unwrap(),
+        &[0.5, 0.0, -1.0, 0.0],
+        1e-6,
+    );
+}
+
+#[test]
+fn invalid_parameters_are_rejected() {
+    let device = Device::default();
+    assert!(HuberLoss::try_new(&device, 0.0).is_err());
+    assert!(SmoothL1Loss::try_new(&device, -1.0).is_err());
+    assert!(HuberLoss::try_new(&device, 1.0).is_ok());
+}
@@ -2,3 +2,9 @@ mod reduce_sum_square;
 pub use reduce_sum_square::*;
 mod softmax_cross_entropy_loss;
 pub use softmax_cross_entropy_loss::*;
+mod sparse_softmax_cross_entropy_loss;
+pub use sparse_softmax_cross_entropy_loss::*;
+mod loss_function;
+pub use loss_function::*;
+mod goodness;
+pub use goodness::*;
@@ -0,0 +1,329 @@
+use crate::{
+    devices::Device,
+    error, gradient_instruction, loss_instruction, new_tensor_with_grad,
+    opcode::OpCode,
+    stream::DeviceStream,
+    tensor::{Error, ErrorEnum, Tensor},
+    BinaryOperator, DeviceTrait, ExecutableOperator, OperatorAttributes, TensorWithGrad, EPSILON,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// See https://onnx.ai/onnx/operators/onnx__SoftmaxCrossEntropyLoss.html
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Reduction {
+    /// Weighted mean over the rows that are not ignored.
+    #[default]
+    Mean,
+    Sum,
+    /// One loss per row.
+    None,
+}
+
+/// Attributes of SparseSoftmaxCrossEntropyLoss.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SparseCrossEntropyAttributes {
+    /// Rows with this target do not contribute to the loss nor to the gradient.
+    pub ignore_index: Option<i32>,
+    pub reduction: Reduction,
+    /// One weight per class. The weight of the target class scales the loss of a row.
+    pub class_weights: Option<Vec<f32>>,
+    /// The target distribution is (1 - label_smoothing) * one_hot + label_smoothing / classes.
+    /// See:
+    /// Rethinking the Inception Architecture for Computer Vision
+    /// https://arxiv.org/abs/1512.00567
+    pub label_smoothing: f32,
+}
+
+impl SparseCrossEntropyAttributes {
+    pub fn from_attributes(attributes: &OperatorAttributes) -> Result<&Self, Error> {
+        match attributes {
+            OperatorAttributes::SparseCrossEntropy(attributes) => Ok(attributes),
+            _ => Err(error!(ErrorEnum::UnsupportedOperation)),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), Error> {
+        let valid_smoothing = (0.0..1.0).contains(&self.label_smoothing);
+        let valid_weights = match &self.class_weights {
+            Some(weights) => weights.iter().all(|weight| *weight >= 0.0),
+            None => true,
+        };
+        match valid_smoothing && valid_weights {
+            true => Ok(()),
+            false => Err(error!(ErrorEnum::IncorrectOperatorConfiguration)),
+        }
+    }
+
+    /// Size of the loss for a batch of rows.
+    pub fn loss_size(&self, rows: usize) -> [usize; 2] {
+        match self.reduction {
+            Reduction::None => [rows, 1],
+            Reduction::Mean | Reduction::Sum => [1, 1],
+        }
+    }
+
+    /// Class and weight of each row, None if the row is ignored.
+    fn targets(&self, classes: usize, targets: &[f32]) -> Result<Vec<Option<(usize, f32)>>, Error> {
+        if let Some(weights) = &self.class_weights {
+            if weights.len() != classes {
+                return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+            }
+        }
+        targets
+            .iter()
+            .map(|target| {
+                if self.ignore_index == Some(*target as i32) && target.fract() == 0.0 {
+                    return Ok(None);
+                }
+                let class = *target as usize;
+                if *target < 0.0 || target.fract() != 0.0 || class >= classes {
+                    return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+                }
+                let weight = match &self.class_weights {
+                    Some(weights) => weights[class],
+                    None => 1.0,
+                };
+                Ok(Some((class, weight)))
+            })
+            .collect()
+    }
+
+    /// Sum of the weights for Mean, 1 otherwise.
+    fn normalizer(&self, targets: &[Option<(usize, f32)>]) -> f32 {
+        match self.reduction {
+            Reduction::Mean => targets.iter().flatten().map(|(_, weight)| weight).sum(),
+            Reduction::Sum | Reduction::None => 1.0,
+        }
+    }
+
+    /// Probability of class `col` in the smoothed target distribution of `class`.
+    fn smoothed_target(&self, class: usize, col: usize, classes: usize) -> f32 {
+        let uniform = self.label_smoothing / classes as f32;
+        match col == class {
+            true => 1.0 - self.label_smoothing + uniform,
+            false => uniform,
+        }
+    }
+
+    /// loss_i = - w(t_i) * Σ_j q_ij * log(p_ij)
+    /// where t_i is the target class of row i and q_i is its smoothed target distribution.
+    pub fn loss(
+        &self,
+        classes: usize,
+        targets: &[f32],
+        probabilities: &[f32],
+        loss: &mut [f32],
+    ) -> Result<(), Error> {
+        let targets = self.targets(classes, targets)?;
+        let normalizer = self.normalizer(&targets);
+        let row_losses = targets
+            .iter()
+            .zip(probabilities.chunks(classes))
+            .map(|(target, row)| match target {
+                Some((class, weight)) => {
+                    let cross_entropy: f32 = row
+                        .iter()
+                        .enumerate()
+                        .map(|(col, p)| {
+                            self.smoothed_target(*class, col, classes) * f32::ln(p + EPSILON)
+                        })
+                        .sum();
+                    -weight * cross_entropy
+                }
+                None => 0.0,
+            });
+        match self.reduction {
+            Reduction::None => {
+                for (output, row_loss) in loss.iter_mut().zip(row_losses) {
+                    *output = row_loss;
+                }
+            }
+            Reduction::Sum | Reduction::Mean => {
+                let sum: f32 = row_losses.sum();
+                loss[0] = match normalizer > 0.0 {
+                    true => sum / normalizer,
+                    false => 0.0,
+                };
+            }
+        }
+        Ok(())
+    }
+
+    /// The derivative with respect to the logits (before the softmax) is
+    /// w(t_i) * (p_ij - q_ij) / normalizer.
+    /// Ignored rows have a zero gradient.
+    pub fn loss_gradient(
+        &self,
+        classes: usize,
+        targets: &[f32],
+        probabilities: &[f32],
+        gradient: &mut [f32],
+    ) -> Result<(), Error> {
+        let targets = self.targets(classes, targets)?;
+        let normalizer = self.normalizer(&targets);
+        for ((target, row), gradient_row) in targets
+            .iter()
+            .zip(probabilities.chunks(classes))
+            .zip(gradient.chunks_mut(classes))
+        {
+            match target {
+                Some((class, weight)) if normalizer > 0.0 => {
+                    for (col, (gradient, p)) in gradient_row.iter_mut().zip(row.iter()).enumerate()
+                    {
+                        let q = self.smoothed_target(*class, col, classes);
+                        *gradient = weight * (p - q) / normalizer;
+                    }
+                }
+                _ => gradient_row.fill(0.0),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_shapes(
+    targets: &Tensor,
+    actual: &Tensor,
+    output: &Tensor,
+    output_size: [usize; 2],
+) -> Result<(), Error> {
+    if targets.cols() != 1
+        || targets.rows() != actual.rows()
+        || output.rows() != output_size[0]
+        || output.cols() != output_size[1]
+    {
+        return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+    }
+    Ok(())
+}
+
+/// Like SoftmaxCrossEntropyLoss, but the expected output is a tensor with one column
+/// that contains a class id per row instead of one-hot encoded rows.
+/// Class ids are stored as f32, see into_token_id_rows.
+/// Like SoftmaxCrossEntropyLoss, the actual output is the output of a Softmax
+/// created with Softmax::new_with_next_is_cross_entropy_loss.
+/// See https://onnx.ai/onnx/operators/onnx__SoftmaxCrossEntropyLoss.html
+#[derive(Clone)]
+pub struct SparseSoftmaxCrossEntropyLoss {
+    device: Device,
+    attributes: SparseCrossEntropyAttributes,
+}
+
+impl SparseSoftmaxCrossEntropyLoss {
+    pub fn new(device: &Device) -> Self {
+        Self {
+            device: device.clone(),
+            attributes: Default::default(),
+        }
+    }
+
+    pub fn try_new_with_attributes(
+        device: &Device,
+        attributes: SparseCrossEntropyAttributes,
+    ) -> Result<Self, Error> {
+        attributes.validate()?;
+        Ok(Self {
+            device: device.clone(),
+            attributes,
+        })
+    }
+}
+
+impl ExecutableOperator for SparseSoftmaxCrossEntropyLoss {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = SparseCrossEntropyAttributes::from_attributes(attributes)?;
+        let targets = inputs[0];
+        let actual = inputs[1];
+        let loss = outputs[0];
+        check_shapes(targets, actual, loss, attributes.loss_size(actual.rows()))?;
+        device.sparse_cross_entropy_loss(attributes, targets, actual, loss, device_stream)
+    }
+}
+
+impl BinaryOperator for SparseSoftmaxCrossEntropyLoss {
+    fn forward(
+        &self,
+        input_1: &TensorWithGrad,
+        input_2: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        let targets: &Tensor = &input_1.tensor();
+        let actual: &Tensor = &input_2.tensor();
+        if targets.cols() != 1 || targets.rows() != actual.rows() {
+            return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+        }
+        let [rows, cols] = self.attributes.loss_size(actual.rows());
+        let output = new_tensor_with_grad!(
+            self.device,
+            rows,
+            cols,
+            vec![0.0; rows * cols],
+            &[input_1, input_2],
+            true,
+            false
+        )?;
+        let attributes = OperatorAttributes::SparseCrossEntropy(self.attributes.clone());
+
+        output.push_instruction(loss_instruction!(
+            OpCode::SparseSoftmaxCrossEntropyLoss,
+            attributes.clone(),
+            &[targets, actual],
+            &[&output.tensor()],
+        ));
+
+        // As with SoftmaxCrossEntropyLoss, the gradient is with respect to the logits.
+        let actual_gradient: &Tensor = &input_2.gradient();
+        if actual_gradient.requires_grad() {
+            output.push_instruction(gradient_instruction!(
+                OpCode::SparseSoftmaxCrossEntropyLossGradient,
+                attributes,
+                &[targets, actual],
+                &[actual_gradient],
+            ));
+        }
+
+        Ok(output)
+    }
+
+    /// One class id per row.
+    fn expected_output_size(&self, actual_output_size: &[usize]) -> Vec<usize> {
+        vec![actual_output_size[0], 1]
+    }
+}
+
+pub struct SparseSoftmaxCrossEntropyLossGradient {}
+
+impl ExecutableOperator for SparseSoftmaxCrossEntropyLossGradient {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = SparseCrossEntropyAttributes::from_attributes(attributes)?;
+        let targets = inputs[0];
+        let actual = inputs[1];
+        let actual_gradient = outputs[0];
+        check_shapes(
+            targets,
+            actual,
+            actual_gradient,
+            [actual.rows(), actual.cols()],
+        )?;
+        device.sparse_cross_entropy_loss_gradient(
+            attributes,
+            targets,
+            actual,
+            actual_gradient,
+            device_stream,
+        )
+    }
+}
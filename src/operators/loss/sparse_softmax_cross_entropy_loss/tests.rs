@@ -0,0 +1,145 @@
+use crate::{
+    new_tensor, new_tensor_with_grad, stream::StreamTrait, BinaryOperator, Device,
+    ExecutableOperator, OperatorAttributes, Reduction, SparseCrossEntropyAttributes,
+    SparseSoftmaxCrossEntropyLoss, SparseSoftmaxCrossEntropyLossGradient,
+};
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected.iter()) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "actual {}, expected {}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn mean_loss_skips_ignored_rows() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let attributes = SparseCrossEntropyAttributes {
+        ignore_index: Some(-100),
+        ..Default::default()
+    };
+    let targets = new_tensor!(device, 3, 1, vec![1.0, -100.0, 0.0]).unwrap();
+    let actual = new_tensor!(device, 3, 2, vec![0.2, 0.8, 0.5, 0.5, 0.4, 0.6]).unwrap();
+    let loss = new_tensor!(device, 1, 1, vec![0.0]).unwrap();
+    SparseSoftmaxCrossEntropyLoss::execute(
+        &OperatorAttributes::SparseCrossEntropy(attributes),
+        &[&targets, &actual],
+        &[&loss],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+    let expected = -(f32::ln(0.8) + f32::ln(0.4)) / 2.0;
+    assert_close(&loss.get_values().unwrap(), &[expected]);
+}
+
+#[test]
+fn none_reduction_with_class_weights_and_label_smoothing() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let attributes = SparseCrossEntropyAttributes {
+        reduction: Reduction::None,
+        class_weights: Some(vec![1.0, 3.0]),
+        label_smoothing: 0.2,
+        ..Default::default()
+    };
+    let targets = new_tensor!(device, 2, 1, vec![1.0, 0.0]).unwrap();
+    let actual = new_tensor!(device, 2, 2, vec![0.2, 0.8, 0.4, 0.6]).unwrap();
+    let loss = new_tensor!(device, 2, 1, vec![0.0; 2]).unwrap();
+    let attributes = OperatorAttributes::SparseCrossEntropy(attributes);
+    SparseSoftmaxCrossEntropyLoss::execute(
+        &attributes,
+        &[&targets, &actual],
+        &[&loss],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+    // Smoothed targets are [0.1, 0.9] and [0.9, 0.1].
+    let expected = [
+        -3.0 * (0.1 * f32::ln(0.2) + 0.9 * f32::ln(0.8)),
+        -(0.9 * f32::ln(0.4) + 0.1 * f32::ln(0.6)),
+    ];
+    assert_close(&loss.get_values().unwrap(), &expected);
+
+    let gradient = new_tensor!(device, 2, 2, vec![0.0; 4]).unwrap();
+    SparseSoftmaxCrossEntropyLossGradient::execute(
+        &attributes,
+        &[&targets, &actual],
+        &[&gradient],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+    assert_close(
+        &gradient.get_values().unwrap(),
+        &[3.0 * 0.1, 3.0 * -0.1, -0.5, 0.5],
+    );
+}
+
+#[test]
+fn invalid_targets_and_attributes_are_rejected() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let targets = new_tensor!(device, 1, 1, vec![2.0]).unwrap();
+    let actual = new_tensor!(device, 1, 2, vec![0.5, 0.5]).unwrap();
+    let loss = new_tensor!(device, 1, 1, vec![0.0]).unwrap();
+    let result = SparseSoftmaxCrossEntropyLoss::execute(
+        &OperatorAttributes::SparseCrossEntropy(Default::default()),
+        &[&targets, &actual],
+        &[&loss],
+        &device,
+        &device_stream,
+    );
+    assert!(result.is_err());
+
+    let attributes = SparseCrossEntropyAttributes {
+        label_smoothing: 1.0,
+        ..Default::default()
+    };
+    assert!(SparseSoftmaxCrossEntropyLoss::try_new_with_attributes(&device, attributes).is_err());
+}
+
+#[test]
+fn gradient_of_ignored_rows_is_zero() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let attributes = SparseCrossEntropyAttributes {
+        ignore_index: Some(0),
+        reduction: Reduction::Sum,
+        ..Default::default()
+    };
+    let operator =
+        SparseSoftmaxCrossEntropyLoss::try_new_with_attributes(&device, attributes).unwrap();
+    let targets = new_tensor_with_grad!(device, 2, 1, vec![0.0, 2.0], &[], false, false).unwrap();
+    let actual = new_tensor_with_grad!(
+        device,
+        2,
+        3,
+        vec![0.2, 0.3, 0.5, 0.1, 0.1, 0.8],
+        &[],
+        true,
+        false
+    )
+    .unwrap();
+    actual.gradient().set_values(vec![7.0; 6]).unwrap();
+    let loss = operator.forward(&targets, &actual).unwrap();
+    assert_eq!(*loss.tensor().size(), [1, 1]);
+    loss.forward(&device, &device_stream).unwrap();
+    loss.compute_gradient(&device, &device_stream).unwrap();
+    device_stream.wait_for().unwrap();
+    assert_close(&loss.tensor().get_values().unwrap(), &[-f32::ln(0.8)]);
+    assert_close(
+        &actual.gradient().get_values().unwrap(),
+        &[0.0, 0.0, 0.0, 0.1, 0.1, -0.2],
+    );
+}
@@ -10,11 +10,18 @@ mod attention;
 pub use attention::*;
 mod reduce;
 pub use reduce::*;
+mod convolution;
+pub use convolution::*;
+mod recurrent;
+pub use recurrent::*;
 pub mod analysis;
 pub mod opcode;
 pub mod statistics;
 
 use crate::{
+    analysis::{arg_max::ArgReduce, top_k::TopKAttributes},
+    elementwise::ActivationFunction,
+    statistics::normalization::NormalizationAttributes,
     stream::DeviceStream,
     tensor::{Error, Tensor},
     Device, TensorWithGrad,
@@ -30,6 +37,12 @@ pub trait BinaryOperator {
         input_1: &TensorWithGrad,
         input_2: &TensorWithGrad,
     ) -> Result<TensorWithGrad, Error>;
+
+    /// Size of the expected output (input_1) of a loss for an actual output (input_2)
+    /// of size actual_output_size. By default, they have the same size.
+    fn expected_output_size(&self, actual_output_size: &[usize]) -> Vec<usize> {
+        actual_output_size.to_vec()
+    }
 }
 
 pub trait TernaryOperator {
@@ -64,4 +77,12 @@ pub enum OperatorAttributes {
     ThreeBools(bool, bool, bool),
     String(String),
     Vec(Vec<usize>),
+    SlidingWindow(SlidingWindow),
+    ArgReduce(ArgReduce),
+    TopK(TopKAttributes),
+    Normalization(NormalizationAttributes),
+    Activation(ActivationFunction),
+    SparseCrossEntropy(SparseCrossEntropyAttributes),
+    Loss(LossFunction),
+    Gating(GatingAttributes),
 }
@@ -1,18 +1,32 @@
 use crate::{
-    analysis::min::Min,
+    analysis::{
+        arg_max::{ArgMax, ArgMin},
+        min::Min,
+        top_k::TopK,
+    },
     clip::Clip,
+    elementwise::{Activation, ActivationDerivative},
+    gather::{Gather, ScatterAdd},
     gelu::{Gelu, GeluDerivative},
     identity::Identity,
     pow::Pow,
     reduce_l2::ReduceL2,
     reduce_sum::ReduceSum,
-    statistics::{bernoulli::Bernoulli, standardization::Standardization},
+    statistics::{
+        batch_norm::{BatchStandardization, BatchStandardizationGradient},
+        bernoulli::Bernoulli,
+        group_norm::{GroupStandardization, GroupStandardizationGradient},
+        rms_norm::{RmsStandardization, RmsStandardizationGradient},
+        standardization::Standardization,
+    },
     stream::DeviceStream,
     tensor::{Error, Tensor},
     transpose::Transpose,
-    Add, ClipNorm, Concat, Device, Div, ExecutableOperator, Gemm, Mul, OperatorAttributes,
-    ReduceSumSquare, Reshape, ScalarAdd, ScalarMul, Sigmoid, Softmax, SoftmaxCrossEntropyLoss,
-    Sqrt, Sub, Unconcat,
+    Add, AveragePool, AveragePoolGradient, ClipGlobalNorm, ClipNorm, Col2Im, Concat, Device, Div,
+    ExecutableOperator, Gemm, Im2Col, Loss, LossGradient, MaxPool, MaxPoolGradient, Mul,
+    OperatorAttributes, ReduceSumSquare, Reshape, ScalarAdd, ScalarMul, Sigmoid, Softmax,
+    SoftmaxCrossEntropyLoss, SparseSoftmaxCrossEntropyLoss, SparseSoftmaxCrossEntropyLossGradient,
+    Sqrt, Sub, TopKGating, TopKGatingGradient, Unconcat,
 };
 
 #[derive(Clone, Debug)]
@@ -50,10 +64,40 @@ pub enum OpCode {
     ///     return x
     ClipNorm,
 
+    /// Not ONNX-compliant
+    /// Equivalent to:
+    /// ClipGlobalNorm(max_norm, x_1, ..., x_n)
+    ///   norm = Sqrt(Σ ReduceSumSquare(x_i))
+    ///   alpha = Min(1.0, max_norm / norm)
+    ///   x_i = ScalarMul(alpha, x_i)
+    ///   return norm, x_1, ..., x_n
+    ClipGlobalNorm,
+
     /// Not ONNX-compliant
     /// First stage of https://onnx.ai/onnx/operators/onnx__LayerNormalization.html
     Standardization,
 
+    /// Not ONNX-compliant
+    /// First stage of https://onnx.ai/onnx/operators/onnx__RMSNormalization.html
+    RmsStandardization,
+
+    /// Not ONNX-compliant
+    RmsStandardizationGradient,
+
+    /// Not ONNX-compliant
+    /// First stage of https://onnx.ai/onnx/operators/onnx__GroupNormalization.html
+    GroupStandardization,
+
+    /// Not ONNX-compliant
+    GroupStandardizationGradient,
+
+    /// Not ONNX-compliant
+    /// First stage of https://onnx.ai/onnx/operators/onnx__BatchNormalization.html
+    BatchStandardization,
+
+    /// Not ONNX-compliant
+    BatchStandardizationGradient,
+
     /// https://onnx.ai/onnx/operators/onnx__Transpose.html
     Transpose,
 
@@ -75,6 +119,17 @@ pub enum OpCode {
     /// https://onnx.ai/onnx/operators/onnx__Min.html
     Min,
 
+    /// https://onnx.ai/onnx/operators/onnx__ArgMax.html
+    ArgMax,
+
+    /// https://onnx.ai/onnx/operators/onnx__ArgMin.html
+    ArgMin,
+
+    /// Not ONNX-compliant
+    /// k is an attribute instead of an input.
+    /// https://onnx.ai/onnx/operators/onnx__TopK.html
+    TopK,
+
     /// https://onnx.ai/onnx/operators/onnx__Softmax.html
     Softmax,
 
@@ -90,6 +145,27 @@ pub enum OpCode {
     /// https://onnx.ai/onnx/operators/onnx__SoftmaxCrossEntropyLoss.html
     SoftmaxCrossEntropyLoss,
 
+    /// SoftmaxCrossEntropyLoss with class ids as expected output.
+    /// See https://onnx.ai/onnx/operators/onnx__SoftmaxCrossEntropyLoss.html
+    SparseSoftmaxCrossEntropyLoss,
+
+    /// Not ONNX-compliant
+    SparseSoftmaxCrossEntropyLossGradient,
+
+    /// Not ONNX-compliant
+    /// See LossFunction
+    Loss,
+
+    /// Not ONNX-compliant
+    LossGradient,
+
+    /// Not ONNX-compliant
+    /// See TopKGating
+    TopKGating,
+
+    /// Not ONNX-compliant
+    TopKGatingGradient,
+
     /// https://onnx.ai/onnx/operators/onnx__ReduceSumSquare.html
     ReduceSumSquare,
 
@@ -99,15 +175,46 @@ pub enum OpCode {
     Gelu,
     GeluDerivative,
 
-    /// TODO
-    /// https://onnx.ai/onnx/operators/onnx__Conv.html
-    /// Conv,
+    /// Not ONNX-compliant
+    /// Relu, LeakyRelu, Tanh, Elu, Softplus, Gelu or Silu, depending on the attributes.
+    /// See ActivationFunction.
+    Activation,
+
+    /// Not ONNX-compliant
+    ActivationDerivative,
+
+    /// Not ONNX-compliant
+    /// Conv is Im2Col followed by Gemm.
+    /// See https://onnx.ai/onnx/operators/onnx__Conv.html
+    Im2Col,
+
+    /// https://onnx.ai/onnx/operators/onnx__Col2Im.html
+    Col2Im,
+
+    /// https://onnx.ai/onnx/operators/onnx__MaxPool.html
+    MaxPool,
+
+    /// Not ONNX-compliant
+    MaxPoolGradient,
+
+    /// https://onnx.ai/onnx/operators/onnx__AveragePool.html
+    AveragePool,
+
+    /// Not ONNX-compliant
+    AveragePoolGradient,
 
     /// https://onnx.ai/onnx/operators/onnx__Concat.html
     Concat,
 
     /// Not ONNX-compliant
     Unconcat,
+
+    /// https://onnx.ai/onnx/operators/onnx__Gather.html
+    Gather,
+
+    /// Not ONNX-compliant
+    /// See https://onnx.ai/onnx/operators/onnx__ScatterND.html
+    ScatterAdd,
 }
 
 impl From<&OpCode> for String {
@@ -121,25 +228,53 @@ impl From<&OpCode> for String {
             OpCode::Mul => "Mul".into(),
             OpCode::Div => "Div".into(),
             OpCode::Min => "Min".into(),
+            OpCode::ArgMax => "ArgMax".into(),
+            OpCode::ArgMin => "ArgMin".into(),
+            OpCode::TopK => "TopK".into(),
             OpCode::ScalarMul => "ScalarMul".into(),
             OpCode::ScalarAdd => "ScalarAdd".into(),
             OpCode::Clip => "Clip".into(),
             OpCode::ClipNorm => "ClipNorm".into(),
+            OpCode::ClipGlobalNorm => "ClipGlobalNorm".into(),
             OpCode::ReduceL2 => "ReduceL2".into(),
             OpCode::Standardization => "Standardization".into(),
+            OpCode::RmsStandardization => "RmsStandardization".into(),
+            OpCode::RmsStandardizationGradient => "RmsStandardizationGradient".into(),
+            OpCode::GroupStandardization => "GroupStandardization".into(),
+            OpCode::GroupStandardizationGradient => "GroupStandardizationGradient".into(),
+            OpCode::BatchStandardization => "BatchStandardization".into(),
+            OpCode::BatchStandardizationGradient => "BatchStandardizationGradient".into(),
             OpCode::Softmax => "Softmax".into(),
             OpCode::Sigmoid => "Sigmoid".into(),
             OpCode::Gelu => "Gelu".into(),
             OpCode::GeluDerivative => "GeluDerivative".into(),
+            OpCode::Activation => "Activation".into(),
+            OpCode::ActivationDerivative => "ActivationDerivative".into(),
             OpCode::Reshape => "Reshape".into(),
             OpCode::Concat => "Concat".into(),
             OpCode::Unconcat => "Unconcat".into(),
+            OpCode::Gather => "Gather".into(),
+            OpCode::ScatterAdd => "ScatterAdd".into(),
             OpCode::SoftmaxCrossEntropyLoss => "SoftmaxCrossEntropyLoss".into(),
+            OpCode::SparseSoftmaxCrossEntropyLoss => "SparseSoftmaxCrossEntropyLoss".into(),
+            OpCode::SparseSoftmaxCrossEntropyLossGradient => {
+                "SparseSoftmaxCrossEntropyLossGradient".into()
+            }
+            OpCode::Loss => "Loss".into(),
+            OpCode::LossGradient => "LossGradient".into(),
+            OpCode::TopKGating => "TopKGating".into(),
+            OpCode::TopKGatingGradient => "TopKGatingGradient".into(),
             OpCode::ReduceSumSquare => "ReduceSumSquare".into(),
             OpCode::Bernoulli => "Bernoulli".into(),
             OpCode::Sqrt => "Sqrt".into(),
             OpCode::Transpose => "Transpose".into(),
             OpCode::Pow => "Pow".into(),
+            OpCode::Im2Col => "Im2Col".into(),
+            OpCode::Col2Im => "Col2Im".into(),
+            OpCode::MaxPool => "MaxPool".into(),
+            OpCode::MaxPoolGradient => "MaxPoolGradient".into(),
+            OpCode::AveragePool => "AveragePool".into(),
+            OpCode::AveragePoolGradient => "AveragePoolGradient".into(),
         }
     }
 }
@@ -163,23 +298,92 @@ impl OpCode {
             OpCode::Sub => Sub::execute(attributes, inputs, outputs, device, device_stream),
             OpCode::Div => Div::execute(attributes, inputs, outputs, device, device_stream),
             OpCode::Min => Min::execute(attributes, inputs, outputs, device, device_stream),
+            OpCode::ArgMax => ArgMax::execute(attributes, inputs, outputs, device, device_stream),
+            OpCode::ArgMin => ArgMin::execute(attributes, inputs, outputs, device, device_stream),
+            OpCode::TopK => TopK::execute(attributes, inputs, outputs, device, device_stream),
             OpCode::Reshape => Reshape::execute(attributes, inputs, outputs, device, device_stream),
             OpCode::Concat => Concat::execute(attributes, inputs, outputs, device, device_stream),
             OpCode::Unconcat => {
                 Unconcat::execute(attributes, inputs, outputs, device, device_stream)
             }
+            OpCode::Gather => Gather::execute(attributes, inputs, outputs, device, device_stream),
+            OpCode::ScatterAdd => {
+                ScatterAdd::execute(attributes, inputs, outputs, device, device_stream)
+            }
             OpCode::Sigmoid => Sigmoid::execute(attributes, inputs, outputs, device, device_stream),
             OpCode::Gelu => Gelu::execute(attributes, inputs, outputs, device, device_stream),
             OpCode::GeluDerivative => {
                 GeluDerivative::execute(attributes, inputs, outputs, device, device_stream)
             }
+            OpCode::Activation => {
+                Activation::execute(attributes, inputs, outputs, device, device_stream)
+            }
+            OpCode::ActivationDerivative => {
+                ActivationDerivative::execute(attributes, inputs, outputs, device, device_stream)
+            }
             OpCode::Standardization => {
                 Standardization::execute(attributes, inputs, outputs, device, device_stream)
             }
+            OpCode::RmsStandardization => {
+                RmsStandardization::execute(attributes, inputs, outputs, device, device_stream)
+            }
+            OpCode::RmsStandardizationGradient => RmsStandardizationGradient::execute(
+                attributes,
+                inputs,
+                outputs,
+                device,
+                device_stream,
+            ),
+            OpCode::GroupStandardization => {
+                GroupStandardization::execute(attributes, inputs, outputs, device, device_stream)
+            }
+            OpCode::GroupStandardizationGradient => GroupStandardizationGradient::execute(
+                attributes,
+                inputs,
+                outputs,
+                device,
+                device_stream,
+            ),
+            OpCode::BatchStandardization => {
+                BatchStandardization::execute(attributes, inputs, outputs, device, device_stream)
+            }
+            OpCode::BatchStandardizationGradient => BatchStandardizationGradient::execute(
+                attributes,
+                inputs,
+                outputs,
+                device,
+                device_stream,
+            ),
             OpCode::Softmax => Softmax::execute(attributes, inputs, outputs, device, device_stream),
             OpCode::SoftmaxCrossEntropyLoss => {
                 SoftmaxCrossEntropyLoss::execute(attributes, inputs, outputs, device, device_stream)
             }
+            OpCode::SparseSoftmaxCrossEntropyLoss => SparseSoftmaxCrossEntropyLoss::execute(
+                attributes,
+                inputs,
+                outputs,
+                device,
+                device_stream,
+            ),
+            OpCode::SparseSoftmaxCrossEntropyLossGradient => {
+                SparseSoftmaxCrossEntropyLossGradient::execute(
+                    attributes,
+                    inputs,
+                    outputs,
+                    device,
+                    device_stream,
+                )
+            }
+            OpCode::Loss => Loss::execute(attributes, inputs, outputs, device, device_stream),
+            OpCode::LossGradient => {
+                LossGradient::execute(attributes, inputs, outputs, device, device_stream)
+            }
+            OpCode::TopKGating => {
+                TopKGating::execute(attributes, inputs, outputs, device, device_stream)
+            }
+            OpCode::TopKGatingGradient => {
+                TopKGatingGradient::execute(attributes, inputs, outputs, device, device_stream)
+            }
             OpCode::ReduceSumSquare => {
                 ReduceSumSquare::execute(attributes, inputs, outputs, device, device_stream)
             }
@@ -206,7 +410,22 @@ impl OpCode {
             OpCode::ClipNorm => {
                 ClipNorm::execute(attributes, inputs, outputs, device, device_stream)
             }
+            OpCode::ClipGlobalNorm => {
+                ClipGlobalNorm::execute(attributes, inputs, outputs, device, device_stream)
+            }
             OpCode::Pow => Pow::execute(attributes, inputs, outputs, device, device_stream),
+            OpCode::Im2Col => Im2Col::execute(attributes, inputs, outputs, device, device_stream),
+            OpCode::Col2Im => Col2Im::execute(attributes, inputs, outputs, device, device_stream),
+            OpCode::MaxPool => MaxPool::execute(attributes, inputs, outputs, device, device_stream),
+            OpCode::MaxPoolGradient => {
+                MaxPoolGradient::execute(attributes, inputs, outputs, device, device_stream)
+            }
+            OpCode::AveragePool => {
+                AveragePool::execute(attributes, inputs, outputs, device, device_stream)
+            }
+            OpCode::AveragePoolGradient => {
+                AveragePoolGradient::execute(attributes, inputs, outputs, device, device_stream)
+            }
         }
     }
 }
@@ -0,0 +1,99 @@
+use crate::{
+    elementwise::{Activation, ActivationFunction},
+    tensor::Error,
+    Add, BinaryOperator, Device, Mul, ScalarMul, Sigmoid, TensorWithGrad, UnaryOperator,
+};
+
+use super::{Gate, Unroll};
+
+/// GRU with one direction and linear_before_reset = 0.
+/// The input has one row per time step.
+/// The output has the hidden state of each time step in its rows.
+/// The gradient flows through all the time steps (backpropagation through time).
+///
+/// See https://onnx.ai/onnx/operators/onnx__GRU.html
+///
+/// See:
+/// Learning Phrase Representations using RNN Encoder-Decoder for Statistical Machine Translation
+/// https://arxiv.org/abs/1406.1078
+pub struct Gru {
+    device: Device,
+    input_size: usize,
+    update_gate: Gate,
+    reset_gate: Gate,
+    hidden_gate: Gate,
+    unroll: Unroll,
+    sigmoid: Sigmoid,
+    tanh: Activation,
+    mul: Mul,
+    add: Add,
+    negate: ScalarMul,
+}
+
+impl Gru {
+    pub fn try_new(
+        device: &Device,
+        sequence_length: usize,
+        input_size: usize,
+        hidden_size: usize,
+    ) -> Result<Self, Error> {
+        let gate = || Gate::try_new(device, sequence_length, input_size, hidden_size);
+        Ok(Self {
+            device: device.clone(),
+            input_size,
+            update_gate: gate()?,
+            reset_gate: gate()?,
+            hidden_gate: gate()?,
+            unroll: Unroll::try_new(device, sequence_length, hidden_size)?,
+            sigmoid: Sigmoid::new(device),
+            tanh: Activation::new(device, ActivationFunction::Tanh),
+            mul: Mul::new(device),
+            add: Add::new(device),
+            negate: ScalarMul::new(device, -1.0),
+        })
+    }
+
+    /// Hidden state with one row of zeros.
+    pub fn zero_state(&self) -> Result<TensorWithGrad, Error> {
+        self.unroll.zero_state(&self.device)
+    }
+
+    /// Returns the output and the hidden state after the last time step.
+    pub fn forward_with_state(
+        &self,
+        input: &TensorWithGrad,
+        hidden: &TensorWithGrad,
+    ) -> Result<(TensorWithGrad, TensorWithGrad), Error> {
+        self.unroll.check_sizes(input, self.input_size, &[hidden])?;
+        let gates = [&self.update_gate, &self.reset_gate, &self.hidden_gate];
+        let projections = self.unroll.project(&gates, input)?;
+        let mut hidden = hidden.clone();
+        let mut hidden_states = vec![];
+        for step in 0..self.unroll.sequence_length {
+            // z = σ(Xz + Hz), r = σ(Xr + Hr)
+            let z = self.unroll.gate(gates[0], &projections[0], step, &hidden)?;
+            let z = self.sigmoid.forward(&z)?;
+            let r = self.unroll.gate(gates[1], &projections[1], step, &hidden)?;
+            let r = self.sigmoid.forward(&r)?;
+            // n = tanh(Xh + (r * H) Rh)
+            let reset_hidden = self.mul.forward(&r, &hidden)?;
+            let n = self
+                .unroll
+                .gate(gates[2], &projections[2], step, &reset_hidden)?;
+            let n = self.tanh.forward(&n)?;
+            // H = (1 - z) * n + z * H = n + z * (H - n)
+            let difference = self.add.forward(&hidden, &self.negate.forward(&n)?)?;
+            hidden = self.add.forward(&n, &self.mul.forward(&z, &difference)?)?;
+            hidden_states.push(hidden.clone());
+        }
+        let output = self.unroll.stack(&hidden_states)?;
+        Ok((output, hidden))
+    }
+}
+
+impl UnaryOperator for Gru {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let (output, _) = self.forward_with_state(input, &self.zero_state()?)?;
+        Ok(output)
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    elementwise::{Activation, ActivationFunction},
+    tensor::Error,
+    Add, BinaryOperator, Device, Mul, Sigmoid, TensorWithGrad, UnaryOperator,
+};
+
+use super::{Gate, Unroll};
+
+/// Hidden state and cell state of a LSTM, each with one row.
+#[derive(Clone)]
+pub struct LstmState {
+    pub hidden: TensorWithGrad,
+    pub cell: TensorWithGrad,
+}
+
+/// LSTM with one direction and no peepholes.
+/// The input has one row per time step.
+/// The output has the hidden state of each time step in its rows.
+/// The gradient flows through all the time steps (backpropagation through time).
+///
+/// See https://onnx.ai/onnx/operators/onnx__LSTM.html
+///
+/// See:
+/// Long Short-Term Memory
+/// https://www.bioinf.jku.at/publications/older/2604.pdf
+pub struct Lstm {
+    device: Device,
+    input_size: usize,
+    input_gate: Gate,
+    forget_gate: Gate,
+    cell_gate: Gate,
+    output_gate: Gate,
+    unroll: Unroll,
+    sigmoid: Sigmoid,
+    tanh: Activation,
+    mul: Mul,
+    add: Add,
+}
+
+impl Lstm {
+    pub fn try_new(
+        device: &Device,
+        sequence_length: usize,
+        input_size: usize,
+        hidden_size: usize,
+    ) -> Result<Self, Error> {
+        let gate = || Gate::try_new(device, sequence_length, input_size, hidden_size);
+        Ok(Self {
+            device: device.clone(),
+            input_size,
+            input_gate: gate()?,
+            forget_gate: gate()?,
+            cell_gate: gate()?,
+            output_gate: gate()?,
+            unroll: Unroll::try_new(device, sequence_length, hidden_size)?,
+            sigmoid: Sigmoid::new(device),
+            tanh: Activation::new(device, ActivationFunction::Tanh),
+            mul: Mul::new(device),
+            add: Add::new(device),
+        })
+    }
+
+    pub fn zero_state(&self) -> Result<LstmState, Error> {
+        Ok(LstmState {
+            hidden: self.unroll.zero_state(&self.device)?,
+            cell: self.unroll.zero_state(&self.device)?,
+        })
+    }
+
+    /// Returns the output and the state after the last time step.
+    pub fn forward_with_state(
+        &self,
+        input: &TensorWithGrad,
+        state: &LstmState,
+    ) -> Result<(TensorWithGrad, LstmState), Error> {
+        self.unroll
+            .check_sizes(input, self.input_size, &[&state.hidden, &state.cell])?;
+        let gates = [
+            &self.input_gate,
+            &self.forget_gate,
+            &self.cell_gate,
+            &self.output_gate,
+        ];
+        let projections = self.unroll.project(&gates, input)?;
+        let mut state = state.clone();
+        let mut hidden_states = vec![];
+        for step in 0..self.unroll.sequence_length {
+            let [i, f, c, o] = [0, 1, 2, 3].map(|gate| {
+                self.unroll
+                    .gate(gates[gate], &projections[gate], step, &state.hidden)
+            });
+            // i = σ(Xi + Hi), f = σ(Xf + Hf), c' = tanh(Xc + Hc), o = σ(Xo + Ho)
+            let i = self.sigmoid.forward(&i?)?;
+            let f = self.sigmoid.forward(&f?)?;
+            let c = self.tanh.forward(&c?)?;
+            let o = self.sigmoid.forward(&o?)?;
+            // C = f * C + i * c'
+            let cell = self.add.forward(
+                &self.mul.forward(&f, &state.cell)?,
+                &self.mul.forward(&i, &c)?,
+            )?;
+            // H = o * tanh(C)
+            let hidden = self.mul.forward(&o, &self.tanh.forward(&cell)?)?;
+            hidden_states.push(hidden.clone());
+            state = LstmState { hidden, cell };
+        }
+        let output = self.unroll.stack(&hidden_states)?;
+        Ok((output, state))
+    }
+}
+
+impl UnaryOperator for Lstm {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let (output, _) = self.forward_with_state(input, &self.zero_state()?)?;
+        Ok(output)
+    }
+}
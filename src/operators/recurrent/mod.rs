@@ -0,0 +1,142 @@
+mod lstm;
+pub use lstm::*;
+mod gru;
+pub use gru::*;
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    error,
+    gather::Gather,
+    new_tensor_with_grad,
+    tensor::{Error, ErrorEnum, Tensor},
+    Add, BinaryOperator, Concat, Device, Linear, NaryOperator, Reshape, TensorWithGrad,
+    UnaryOperator, WeightsInitialization,
+};
+
+/// Pre-activation of a gate of a recurrent cell.
+/// The input projection is computed once for the whole sequence.
+/// Like in ONNX, the input projection and the recurrent projection have their own biases.
+struct Gate {
+    input: Linear,
+    recurrent: Linear,
+}
+
+impl Gate {
+    fn try_new(
+        device: &Device,
+        sequence_length: usize,
+        input_size: usize,
+        hidden_size: usize,
+    ) -> Result<Self, Error> {
+        let input = Linear::new(
+            device,
+            hidden_size,
+            input_size,
+            WeightsInitialization::Kaiming,
+            sequence_length,
+        )?;
+        let recurrent = Linear::new(
+            device,
+            hidden_size,
+            hidden_size,
+            WeightsInitialization::Kaiming,
+            1,
+        )?;
+        Ok(Self { input, recurrent })
+    }
+}
+
+/// Operators shared by the time steps of a recurrent layer.
+/// The time steps are the rows of the input.
+struct Unroll {
+    sequence_length: usize,
+    hidden_size: usize,
+    /// Row index of each time step, for Gather.
+    steps: Vec<TensorWithGrad>,
+    gather: Gather,
+    add: Add,
+    concat: Concat,
+    reshape: Reshape,
+}
+
+impl Unroll {
+    fn try_new(device: &Device, sequence_length: usize, hidden_size: usize) -> Result<Self, Error> {
+        let steps = (0..sequence_length)
+            .map(|step| new_tensor_with_grad!(device, 1, 1, vec![step as f32], &[], false, false))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self {
+            sequence_length,
+            hidden_size,
+            steps,
+            gather: Gather::new(device),
+            add: Add::new(device),
+            concat: Concat::new(device),
+            reshape: Reshape::new(
+                device,
+                vec![1, sequence_length * hidden_size],
+                vec![sequence_length, hidden_size],
+            ),
+        })
+    }
+
+    fn zero_state(&self, device: &Device) -> Result<TensorWithGrad, Error> {
+        let hidden_size = self.hidden_size;
+        new_tensor_with_grad!(
+            device,
+            1,
+            hidden_size,
+            vec![0.0; hidden_size],
+            &[],
+            false,
+            false
+        )
+    }
+
+    fn check_sizes(
+        &self,
+        input: &TensorWithGrad,
+        input_size: usize,
+        states: &[&TensorWithGrad],
+    ) -> Result<(), Error> {
+        let input: &Tensor = &input.tensor();
+        let valid_input = *input.size() == [self.sequence_length, input_size];
+        let valid_states = states
+            .iter()
+            .all(|state| *state.tensor().size() == [1, self.hidden_size]);
+        match valid_input && valid_states {
+            true => Ok(()),
+            false => Err(error!(ErrorEnum::IncompatibleTensorShapes)),
+        }
+    }
+
+    /// Project the whole sequence with the input projection of each gate.
+    fn project(
+        &self,
+        gates: &[&Gate],
+        input: &TensorWithGrad,
+    ) -> Result<Vec<TensorWithGrad>, Error> {
+        gates.iter().map(|gate| gate.input.forward(input)).collect()
+    }
+
+    /// projection[step] + recurrent(hidden)
+    fn gate(
+        &self,
+        gate: &Gate,
+        projection: &TensorWithGrad,
+        step: usize,
+        hidden: &TensorWithGrad,
+    ) -> Result<TensorWithGrad, Error> {
+        let input = self.gather.forward(projection, &self.steps[step])?;
+        let recurrent = gate.recurrent.forward(hidden)?;
+        self.add.forward(&input, &recurrent)
+    }
+
+    /// Stack the hidden states of the time steps in the rows of the output.
+    fn stack(&self, hidden_states: &[TensorWithGrad]) -> Result<TensorWithGrad, Error> {
+        let hidden_states: Vec<&TensorWithGrad> = hidden_states.iter().collect();
+        let concatenated = self.concat.forward(&hidden_states)?;
+        self.reshape.forward(&concatenated)
+    }
+}
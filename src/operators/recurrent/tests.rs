@@ -0,0 +1,160 @@
+use std::collections::HashSet;
+
+use crate::{
+    new_tensor_with_grad, stream::StreamTrait, Device, Gru, Lstm, TensorWithGrad, UnaryOperator,
+};
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + f32::exp(-x))
+}
+
+/// All the weights are 0.1 and all the biases are 0.
+/// So every hidden unit has the same value and the pre-activation of every gate
+/// is 0.1 * Σ x + 0.1 * hidden_size * h.
+fn set_weights(device: &Device) {
+    for parameter in device.parameter_tensors().iter() {
+        if parameter.has_tag("weights") {
+            let len = parameter.tensor().len();
+            parameter.tensor().set_values(vec![0.1; len]).unwrap();
+        }
+    }
+}
+
+const INPUT: [f32; 6] = [1.0, 0.0, 0.5, 0.5, -1.0, 1.0];
+
+fn input(device: &Device) -> TensorWithGrad {
+    new_tensor_with_grad!(device, 3, 2, INPUT.to_vec(), &[], true, false).unwrap()
+}
+
+fn run(device: &Device, output: &TensorWithGrad) -> Vec<f32> {
+    let device_stream = device.new_stream().unwrap();
+    for tensor in output.get_tape().iter() {
+        tensor.forward(device, &device_stream).unwrap();
+    }
+    device_stream.wait_for().unwrap();
+    output.tensor().get_values().unwrap()
+}
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.iter().zip(expected.iter()) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "actual {}, expected {}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn lstm_forward() {
+    let device = Device::default();
+    let hidden_size = 3;
+    let lstm = Lstm::try_new(&device, 3, 2, hidden_size).unwrap();
+    set_weights(&device);
+    let output = lstm.forward(&input(&device)).unwrap();
+    assert_eq!(*output.tensor().size(), [3, hidden_size]);
+
+    let (mut h, mut c) = (0.0, 0.0);
+    let mut expected = vec![];
+    for x in INPUT.chunks(2) {
+        let pre = 0.1 * (x[0] + x[1]) + 0.1 * hidden_size as f32 * h;
+        c = sigmoid(pre) * c + sigmoid(pre) * f32::tanh(pre);
+        h = sigmoid(pre) * f32::tanh(c);
+        expected.extend(vec![h; hidden_size]);
+    }
+    assert_close(&run(&device, &output), &expected);
+}
+
+#[test]
+fn gru_forward() {
+    let device = Device::default();
+    let hidden_size = 3;
+    let gru = Gru::try_new(&device, 3, 2, hidden_size).unwrap();
+    set_weights(&device);
+    let output = gru.forward(&input(&device)).unwrap();
+    assert_eq!(*output.tensor().size(), [3, hidden_size]);
+
+    let mut h = 0.0;
+    let mut expected = vec![];
+    for x in INPUT.chunks(2) {
+        let projected = 0.1 * (x[0] + x[1]);
+        let z = sigmoid(projected + 0.1 * hidden_size as f32 * h);
+        let r = z;
+        let n = f32::tanh(projected + 0.1 * hidden_size as f32 * r * h);
+        h = (1.0 - z) * n + z * h;
+        expected.extend(vec![h; hidden_size]);
+    }
+    assert_close(&run(&device, &output), &expected);
+}
+
+#[test]
+fn final_state_is_last_output_row() {
+    let device = Device::default();
+    let lstm = Lstm::try_new(&device, 3, 2, 4).unwrap();
+    let (output, state) = lstm
+        .forward_with_state(&input(&device), &lstm.zero_state().unwrap())
+        .unwrap();
+    let output = run(&device, &output);
+    let hidden = run(&device, &state.hidden);
+    assert_close(&hidden, &output[8..12]);
+
+    let wrong_state = lstm.zero_state().unwrap();
+    let wrong_input = new_tensor_with_grad!(device, 2, 2, vec![0.0; 4], &[], false, false).unwrap();
+    assert!(lstm.forward_with_state(&wrong_input, &wrong_state).is_err());
+}
+
+#[test]
+fn gradient_is_unrolled_through_time() {
+    let device = Device::default();
+    let sequence_length = 32;
+    let gru = Gru::try_new(&device, sequence_length, 2, 3).unwrap();
+    let values = (0..sequence_length * 2)
+        .map(|i| f32::sin(i as f32 * 0.37))
+        .collect::<Vec<_>>();
+    let input = new_tensor_with_grad!(device, sequence_length, 2, values.clone(), &[], true, false)
+        .unwrap();
+    let output = gru.forward(&input).unwrap();
+    let tape = output.get_tape();
+    let names: HashSet<usize> = tape.iter().map(|tensor| tensor.tensor().name()).collect();
+    assert_eq!(names.len(), tape.len());
+    assert_eq!(output.get_backward_tape().len(), tape.len());
+
+    // The objective is the sum of the hidden states of every time step.
+    run(&device, &output);
+    let device_stream = device.new_stream().unwrap();
+    let len = output.tensor().len();
+    output.gradient().set_values(vec![1.0; len]).unwrap();
+    for tensor in output.get_backward_tape().iter() {
+        tensor.compute_gradient(&device, &device_stream).unwrap();
+    }
+    device_stream.wait_for().unwrap();
+    let input_gradient = input.gradient().get_values().unwrap();
+
+    // The gradient of an input row goes through the hidden states of every later time step.
+    let objective = |values: &[f32]| -> f32 {
+        input.tensor().set_values(values.to_vec()).unwrap();
+        run(&device, &output).iter().sum()
+    };
+    let h = 1e-2;
+    for row in [0, 1, sequence_length / 2, sequence_length - 1] {
+        for col in 0..2 {
+            let index = row * 2 + col;
+            let mut plus = values.clone();
+            plus[index] += h;
+            let mut minus = values.clone();
+            minus[index] -= h;
+            let expected = (objective(&plus) - objective(&minus)) / (2.0 * h);
+            let actual = input_gradient[index];
+            assert!(
+                (actual - expected).abs() < 1e-2 * expected.abs().max(1.0),
+                "row {}, col {}, actual {}, expected {}",
+                row,
+                col,
+                actual,
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{
+    gradient_instruction, inference_instruction, new_tensor, new_tensor_with_grad,
+    opcode::OpCode,
+    statistics::normalization::{ColumnAffine, NormalizationAttributes},
+    stream::DeviceStream,
+    tensor::{Error, Tensor},
+    Device, DeviceTrait, ExecutableOperator, OperatorAttributes, TensorWithGrad, UnaryOperator,
+};
+
+/// Each column is standardized over the rows, which are the examples of a batch.
+/// In training, the statistics of the batch are used and running statistics are updated.
+/// Otherwise, the running statistics are used.
+/// See:
+/// Batch Normalization: Accelerating Deep Network Training by Reducing Internal Covariate Shift
+/// https://arxiv.org/abs/1502.03167
+/// https://onnx.ai/onnx/operators/onnx__BatchNormalization.html
+pub struct BatchNormalization {
+    standardization: BatchStandardization,
+    affine: ColumnAffine,
+}
+
+impl BatchNormalization {
+    pub fn try_new(
+        device: &Device,
+        rows: usize,
+        cols: usize,
+        epsilon: f32,
+        momentum: f32,
+    ) -> Result<Self, Error> {
+        let op = Self {
+            standardization: BatchStandardization::try_new(device, cols, epsilon, momentum)?,
+            affine: ColumnAffine::try_new(device, rows, cols, true, "BatchNormalization")?,
+        };
+        Ok(op)
+    }
+
+    pub fn set_training(&self, training: bool) -> Result<(), Error> {
+        self.standardization.set_training(training)
+    }
+
+    pub fn running_mean(&self) -> &Tensor {
+        &self.standardization.running_mean
+    }
+
+    pub fn running_var(&self) -> &Tensor {
+        &self.standardization.running_var
+    }
+}
+
+impl UnaryOperator for BatchNormalization {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let standardized = self.standardization.forward(input)?;
+        self.affine.forward(&standardized)
+    }
+}
+
+/// First stage of BatchNormalization.
+/// Training is a 1x1 tensor so that the mode can change without new instructions.
+pub struct BatchStandardization {
+    device: Device,
+    attributes: NormalizationAttributes,
+    training: Tensor,
+    running_mean: Tensor,
+    running_var: Tensor,
+}
+
+impl BatchStandardization {
+    pub fn try_new(
+        device: &Device,
+        cols: usize,
+        epsilon: f32,
+        momentum: f32,
+    ) -> Result<Self, Error> {
+        let op = Self {
+            device: device.clone(),
+            attributes: NormalizationAttributes {
+                epsilon,
+                momentum,
+                ..Default::default()
+            },
+            training: new_tensor!(device, 1, 1, vec![1.0])?,
+            running_mean: new_tensor!(device, 1, cols, vec![0.0; cols])?,
+            running_var: new_tensor!(device, 1, cols, vec![1.0; cols])?,
+        };
+        Ok(op)
+    }
+
+    pub fn set_training(&self, training: bool) -> Result<(), Error> {
+        let value = if training { 1.0 } else { 0.0 };
+        self.training.set_values(vec![value])
+    }
+}
+
+impl UnaryOperator for BatchStandardization {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let input_t: &Tensor = &input.tensor();
+        let rows = input_t.rows();
+        let cols = input_t.cols();
+        let output = new_tensor_with_grad!(
+            self.device,
+            rows,
+            cols,
+            vec![0.0; rows * cols],
+            &[input],
+            true,
+            false,
+        )?;
+        let attributes = OperatorAttributes::Normalization(self.attributes.clone());
+
+        output.push_instruction(inference_instruction!(
+            OpCode::BatchStandardization,
+            attributes.clone(),
+            &[&self.training, input_t],
+            &[&output.tensor(), &self.running_mean, &self.running_var],
+        ));
+
+        if input.gradient().requires_grad() {
+            let input_gradient = new_tensor!(self.device, rows, cols, vec![0.0; rows * cols])?;
+            output.push_instruction(gradient_instruction!(
+                OpCode::BatchStandardizationGradient,
+                attributes,
+                &[
+                    &self.training,
+                    input_t,
+                    &self.running_var,
+                    &output.gradient()
+                ],
+                &[&input_gradient],
+            ));
+            output.push_instruction(gradient_instruction!(
+                OpCode::Add,
+                OperatorAttributes::None,
+                &[&input_gradient, &input.gradient()],
+                &[&input.gradient()],
+            ));
+        }
+
+        Ok(output)
+    }
+}
+
+impl ExecutableOperator for BatchStandardization {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = NormalizationAttributes::from_attributes(attributes)?;
+        let training = inputs[0];
+        let input = inputs[1];
+        let output = outputs[0];
+        let running_mean = outputs[1];
+        let running_var = outputs[2];
+        device.batch_standardization(
+            attributes,
+            training,
+            input,
+            running_mean,
+            running_var,
+            output,
+            device_stream,
+        )
+    }
+}
+
+pub struct BatchStandardizationGradient {}
+
+impl ExecutableOperator for BatchStandardizationGradient {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = NormalizationAttributes::from_attributes(attributes)?;
+        let training = inputs[0];
+        let input = inputs[1];
+        let running_var = inputs[2];
+        let output_gradient = inputs[3];
+        let input_gradient = outputs[0];
+        device.batch_standardization_gradient(
+            attributes,
+            training,
+            input,
+            running_var,
+            output_gradient,
+            input_gradient,
+            device_stream,
+        )
+    }
+}
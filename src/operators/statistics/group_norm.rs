@@ -0,0 +1,126 @@
+use crate::{
+    error,
+    opcode::OpCode,
+    statistics::normalization::{standardize, ColumnAffine, NormalizationAttributes},
+    stream::DeviceStream,
+    tensor::{Error, ErrorEnum, Tensor},
+    Device, DeviceTrait, ExecutableOperator, OperatorAttributes, TensorWithGrad, UnaryOperator,
+};
+
+/// The columns of each row are split in groups.
+/// With 1 group, this is a LayerNormalization with a gain and a bias per column.
+/// See:
+/// Group Normalization
+/// https://arxiv.org/abs/1803.08494
+/// https://onnx.ai/onnx/operators/onnx__GroupNormalization.html
+pub struct GroupNormalization {
+    standardization: GroupStandardization,
+    affine: ColumnAffine,
+}
+
+impl GroupNormalization {
+    pub fn try_new(
+        device: &Device,
+        rows: usize,
+        cols: usize,
+        groups: usize,
+        epsilon: f32,
+    ) -> Result<Self, Error> {
+        let op = Self {
+            standardization: GroupStandardization::try_new(device, cols, groups, epsilon)?,
+            affine: ColumnAffine::try_new(device, rows, cols, true, "GroupNormalization")?,
+        };
+        Ok(op)
+    }
+}
+
+impl UnaryOperator for GroupNormalization {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let standardized = self.standardization.forward(input)?;
+        self.affine.forward(&standardized)
+    }
+}
+
+/// First stage of GroupNormalization.
+pub struct GroupStandardization {
+    device: Device,
+    cols: usize,
+    attributes: NormalizationAttributes,
+}
+
+impl GroupStandardization {
+    pub fn try_new(
+        device: &Device,
+        cols: usize,
+        groups: usize,
+        epsilon: f32,
+    ) -> Result<Self, Error> {
+        if groups == 0 || !cols.is_multiple_of(groups) {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        let op = Self {
+            device: device.clone(),
+            cols,
+            attributes: NormalizationAttributes {
+                epsilon,
+                groups,
+                ..Default::default()
+            },
+        };
+        Ok(op)
+    }
+}
+
+impl UnaryOperator for GroupStandardization {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        if input.tensor().cols() != self.cols {
+            return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+        }
+        standardize(
+            &self.device,
+            &self.attributes,
+            OpCode::GroupStandardization,
+            OpCode::GroupStandardizationGradient,
+            input,
+        )
+    }
+}
+
+impl ExecutableOperator for GroupStandardization {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = NormalizationAttributes::from_attributes(attributes)?;
+        let input = inputs[0];
+        let output = outputs[0];
+        device.group_standardization(attributes, input, output, device_stream)
+    }
+}
+
+pub struct GroupStandardizationGradient {}
+
+impl ExecutableOperator for GroupStandardizationGradient {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = NormalizationAttributes::from_attributes(attributes)?;
+        let input = inputs[0];
+        let output_gradient = inputs[1];
+        let input_gradient = outputs[0];
+        device.group_standardization_gradient(
+            attributes,
+            input,
+            output_gradient,
+            input_gradient,
+            device_stream,
+        )
+    }
+}
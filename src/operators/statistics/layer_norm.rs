@@ -1,6 +1,6 @@
 use crate::{
     new_tensor_with_grad, tensor::Error, Add, BinaryOperator, Device, Mul, TensorWithGrad,
-    UnaryOperator,
+    UnaryOperator, EPSILON,
 };
 
 use super::standardization::Standardization;
@@ -19,11 +19,40 @@ pub struct LayerNormalization {
 
 impl LayerNormalization {
     pub fn try_new(device: &Device, rows: usize, cols: usize) -> Result<Self, Error> {
-        let gain =
-            new_tensor_with_grad!(device, rows, cols, vec![1.0; rows * cols], &[], true, true)?;
-        let bias =
-            new_tensor_with_grad!(device, rows, cols, vec![0.0; rows * cols], &[], true, true)?;
-        let standardization = Standardization::new(device);
+        Self::try_new_with_epsilon(device, rows, cols, EPSILON)
+    }
+
+    pub fn try_new_with_epsilon(
+        device: &Device,
+        rows: usize,
+        cols: usize,
+        epsilon: f32,
+    ) -> Result<Self, Error> {
+        // Frozen when the device has a LoraConfig.
+        let trainable = device.lora_config().is_none();
+        let gain = new_tensor_with_grad!(
+            device,
+            rows,
+            cols,
+            vec![1.0; rows * cols],
+            &[],
+            trainable,
+            trainable
+        )?;
+        let bias = new_tensor_with_grad!(
+            device,
+            rows,
+            cols,
+            vec![0.0; rows * cols],
+            &[],
+            trainable,
+            trainable
+        )?;
+        gain.add_tag("LayerNormalization");
+        gain.add_tag("gain");
+        bias.add_tag("LayerNormalization");
+        bias.add_tag("biases");
+        let standardization = Standardization::new_with_epsilon(device, epsilon);
         let mul = Mul::new(device);
         let add = Add::new(device);
         let op = Self {
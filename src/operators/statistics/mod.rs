@@ -1,3 +1,7 @@
+pub mod batch_norm;
 pub mod bernoulli;
+pub mod group_norm;
 pub mod layer_norm;
+pub mod normalization;
+pub mod rms_norm;
 pub mod standardization;
@@ -0,0 +1,421 @@
+use crate::{
+    error, gradient_instruction, inference_instruction, new_tensor, new_tensor_with_grad,
+    opcode::OpCode,
+    tensor::{Error, ErrorEnum, Tensor},
+    Add, BinaryOperator, Device, MatMul, Mul, OperatorAttributes, TensorWithGrad, UnaryOperator,
+    EPSILON,
+};
+
+use super::{
+    batch_norm::BatchNormalization, group_norm::GroupNormalization, layer_norm::LayerNormalization,
+    rms_norm::RmsNormalization,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Attributes of the normalization operators.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NormalizationAttributes {
+    pub epsilon: f32,
+    /// Only used by BatchNormalization.
+    /// running_mean = momentum * running_mean + (1 - momentum) * mean
+    pub momentum: f32,
+    /// Only used by GroupNormalization.
+    pub groups: usize,
+}
+
+impl Default for NormalizationAttributes {
+    fn default() -> Self {
+        Self {
+            epsilon: EPSILON,
+            momentum: 0.9,
+            groups: 1,
+        }
+    }
+}
+
+impl NormalizationAttributes {
+    pub fn from_attributes(attributes: &OperatorAttributes) -> Result<&Self, Error> {
+        match attributes {
+            OperatorAttributes::Normalization(attributes) => Ok(attributes),
+            _ => Err(error!(ErrorEnum::UnsupportedOperation)),
+        }
+    }
+
+    /// Each row is split in groups of columns.
+    /// Each group is standardized with its own mean and variance.
+    pub fn group_standardization(&self, cols: usize, input: &[f32], output: &mut [f32]) {
+        let group_len = cols / self.groups;
+        for (input, output) in input.chunks(group_len).zip(output.chunks_mut(group_len)) {
+            let (mean, stddev) = self.moments(input.iter().copied(), group_len, true);
+            for (x, y) in input.iter().zip(output.iter_mut()) {
+                *y = (x - mean) / stddev;
+            }
+        }
+    }
+
+    pub fn group_standardization_gradient(
+        &self,
+        cols: usize,
+        input: &[f32],
+        output_gradient: &[f32],
+        input_gradient: &mut [f32],
+    ) {
+        let group_len = cols / self.groups;
+        let chunks = input
+            .chunks(group_len)
+            .zip(output_gradient.chunks(group_len))
+            .zip(input_gradient.chunks_mut(group_len));
+        for ((input, output_gradient), input_gradient) in chunks {
+            let (mean, stddev) = self.moments(input.iter().copied(), group_len, true);
+            self.standardization_gradient(
+                input.iter().map(|x| (x - mean) / stddev),
+                output_gradient.iter().copied(),
+                input_gradient.iter_mut(),
+                group_len,
+                stddev,
+                true,
+            );
+        }
+    }
+
+    /// Each row is divided by its root mean square.
+    pub fn rms_standardization(&self, cols: usize, input: &[f32], output: &mut [f32]) {
+        for (input, output) in input.chunks(cols).zip(output.chunks_mut(cols)) {
+            let (_, rms) = self.moments(input.iter().copied(), cols, false);
+            for (x, y) in input.iter().zip(output.iter_mut()) {
+                *y = x / rms;
+            }
+        }
+    }
+
+    pub fn rms_standardization_gradient(
+        &self,
+        cols: usize,
+        input: &[f32],
+        output_gradient: &[f32],
+        input_gradient: &mut [f32],
+    ) {
+        let chunks = input
+            .chunks(cols)
+            .zip(output_gradient.chunks(cols))
+            .zip(input_gradient.chunks_mut(cols));
+        for ((input, output_gradient), input_gradient) in chunks {
+            let (_, rms) = self.moments(input.iter().copied(), cols, false);
+            self.standardization_gradient(
+                input.iter().map(|x| x / rms),
+                output_gradient.iter().copied(),
+                input_gradient.iter_mut(),
+                cols,
+                rms,
+                false,
+            );
+        }
+    }
+
+    /// Each column is standardized with the mean and variance of the rows.
+    /// In training, the statistics of the rows are used and the running statistics are updated.
+    /// Otherwise, the running statistics are used.
+    #[allow(clippy::too_many_arguments)]
+    pub fn batch_standardization(
+        &self,
+        rows: usize,
+        cols: usize,
+        training: bool,
+        input: &[f32],
+        running_mean: &mut [f32],
+        running_var: &mut [f32],
+        output: &mut [f32],
+    ) {
+        for col in 0..cols {
+            let column = (0..rows).map(|row| input[row * cols + col]);
+            let (mean, stddev) = match training {
+                true => {
+                    let (mean, stddev) = self.moments(column, rows, true);
+                    let var = stddev * stddev - self.epsilon;
+                    running_mean[col] =
+                        self.momentum * running_mean[col] + (1.0 - self.momentum) * mean;
+                    running_var[col] =
+                        self.momentum * running_var[col] + (1.0 - self.momentum) * var;
+                    (mean, stddev)
+                }
+                false => (running_mean[col], (running_var[col] + self.epsilon).sqrt()),
+            };
+            for row in 0..rows {
+                let index = row * cols + col;
+                output[index] = (input[index] - mean) / stddev;
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn batch_standardization_gradient(
+        &self,
+        rows: usize,
+        cols: usize,
+        training: bool,
+        input: &[f32],
+        running_var: &[f32],
+        output_gradient: &[f32],
+        input_gradient: &mut [f32],
+    ) {
+        for col in 0..cols {
+            let column = (0..rows).map(|row| input[row * cols + col]);
+            let column_gradient = (0..rows).map(|row| output_gradient[row * cols + col]);
+            match training {
+                true => {
+                    let (mean, stddev) = self.moments(column.clone(), rows, true);
+                    let mut gradient = vec![0.0; rows];
+                    self.standardization_gradient(
+                        column.map(|x| (x - mean) / stddev),
+                        column_gradient,
+                        gradient.iter_mut(),
+                        rows,
+                        stddev,
+                        true,
+                    );
+                    for (row, gradient) in gradient.into_iter().enumerate() {
+                        input_gradient[row * cols + col] = gradient;
+                    }
+                }
+                false => {
+                    let stddev = (running_var[col] + self.epsilon).sqrt();
+                    for (row, gradient) in column_gradient.enumerate() {
+                        input_gradient[row * cols + col] = gradient / stddev;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the mean and sqrt(variance + epsilon).
+    /// Without centering, the mean is 0 and the variance is the mean square.
+    fn moments(
+        &self,
+        values: impl Iterator<Item = f32> + Clone,
+        len: usize,
+        center: bool,
+    ) -> (f32, f32) {
+        let len = len as f32;
+        let mean = match center {
+            true => values.clone().sum::<f32>() / len,
+            false => 0.0,
+        };
+        let var = values.map(|x| (x - mean) * (x - mean)).sum::<f32>() / len;
+        (mean, (var + self.epsilon).sqrt())
+    }
+
+    /// With y = (x - mean) / stddev:
+    /// dx = (dy - mean(dy) - y * mean(dy * y)) / stddev
+    /// Without centering, the term mean(dy) is absent.
+    fn standardization_gradient<'a>(
+        &self,
+        output: impl Iterator<Item = f32> + Clone,
+        output_gradient: impl Iterator<Item = f32> + Clone,
+        input_gradient: impl Iterator<Item = &'a mut f32>,
+        len: usize,
+        stddev: f32,
+        center: bool,
+    ) {
+        let len = len as f32;
+        let mean_output_gradient = match center {
+            true => output_gradient.clone().sum::<f32>() / len,
+            false => 0.0,
+        };
+        let mean_product = output
+            .clone()
+            .zip(output_gradient.clone())
+            .map(|(y, dy)| y * dy)
+            .sum::<f32>()
+            / len;
+        for ((y, dy), dx) in output.zip(output_gradient).zip(input_gradient) {
+            *dx = (dy - mean_output_gradient - y * mean_product) / stddev;
+        }
+    }
+}
+
+/// Push the instructions of a standardization.
+/// The gradient operator computes the gradient of the input from the input
+/// and the gradient of the output.
+pub(crate) fn standardize(
+    device: &Device,
+    attributes: &NormalizationAttributes,
+    opcode: OpCode,
+    gradient_opcode: OpCode,
+    input: &TensorWithGrad,
+) -> Result<TensorWithGrad, Error> {
+    let input_t: &Tensor = &input.tensor();
+    let rows = input_t.rows();
+    let cols = input_t.cols();
+    let output = new_tensor_with_grad!(
+        device,
+        rows,
+        cols,
+        vec![0.0; rows * cols],
+        &[input],
+        true,
+        false,
+    )?;
+    let attributes = OperatorAttributes::Normalization(attributes.clone());
+
+    output.push_instruction(inference_instruction!(
+        opcode,
+        attributes.clone(),
+        &[input_t],
+        &[&output.tensor()],
+    ));
+
+    if input.gradient().requires_grad() {
+        let input_gradient = new_tensor!(device, rows, cols, vec![0.0; rows * cols])?;
+        output.push_instruction(gradient_instruction!(
+            gradient_opcode,
+            attributes,
+            &[input_t, &output.gradient()],
+            &[&input_gradient],
+        ));
+        output.push_instruction(gradient_instruction!(
+            OpCode::Add,
+            OperatorAttributes::None,
+            &[&input_gradient, &input.gradient()],
+            &[&input.gradient()],
+        ));
+    }
+
+    Ok(output)
+}
+
+/// A gain and an optional bias per column, broadcast to every row.
+pub struct ColumnAffine {
+    gain: TensorWithGrad,
+    bias: Option<TensorWithGrad>,
+    ones: TensorWithGrad,
+    matmul: MatMul,
+    mul: Mul,
+    add: Add,
+}
+
+impl ColumnAffine {
+    pub fn try_new(
+        device: &Device,
+        rows: usize,
+        cols: usize,
+        with_bias: bool,
+        tag: &str,
+    ) -> Result<Self, Error> {
+        // Frozen when the device has a LoraConfig.
+        let trainable = device.lora_config().is_none();
+        let gain =
+            new_tensor_with_grad!(device, 1, cols, vec![1.0; cols], &[], trainable, trainable)?;
+        gain.add_tag(tag);
+        gain.add_tag("gain");
+        let bias = match with_bias {
+            true => {
+                let bias = new_tensor_with_grad!(
+                    device,
+                    1,
+                    cols,
+                    vec![0.0; cols],
+                    &[],
+                    trainable,
+                    trainable
+                )?;
+                bias.add_tag(tag);
+                bias.add_tag("biases");
+                Some(bias)
+            }
+            false => None,
+        };
+        let ones = new_tensor_with_grad!(device, rows, 1, vec![1.0; rows], &[], false, false)?;
+        let op = Self {
+            gain,
+            bias,
+            ones,
+            matmul: MatMul::new(device, false),
+            mul: Mul::new(device),
+            add: Add::new(device),
+        };
+        Ok(op)
+    }
+}
+
+impl UnaryOperator for ColumnAffine {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let gain = self.matmul.forward(&self.ones, &self.gain)?;
+        let with_gain = self.mul.forward(&gain, input)?;
+        match &self.bias {
+            Some(bias) => {
+                let bias = self.matmul.forward(&self.ones, bias)?;
+                self.add.forward(&with_gain, &bias)
+            }
+            None => Ok(with_gain),
+        }
+    }
+}
+
+/// Normalization layers of a Transformer.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Normalization {
+    LayerNormalization { epsilon: f32 },
+    RmsNormalization { epsilon: f32 },
+    BatchNormalization { epsilon: f32, momentum: f32 },
+    GroupNormalization { groups: usize, epsilon: f32 },
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Normalization::LayerNormalization { epsilon: EPSILON }
+    }
+}
+
+impl Normalization {
+    /// Whether each row is normalized with its own statistics only.
+    /// BatchNormalization mixes the rows, so a position would see the next ones
+    /// and the Transformer rejects it.
+    pub fn is_row_wise(&self) -> bool {
+        !matches!(self, Normalization::BatchNormalization { .. })
+    }
+
+    pub fn try_new_operator(
+        &self,
+        device: &Device,
+        rows: usize,
+        cols: usize,
+    ) -> Result<NormalizationOperator, Error> {
+        let op = match *self {
+            Normalization::LayerNormalization { epsilon } => NormalizationOperator::Layer(
+                LayerNormalization::try_new_with_epsilon(device, rows, cols, epsilon)?,
+            ),
+            Normalization::RmsNormalization { epsilon } => {
+                NormalizationOperator::Rms(RmsNormalization::try_new(device, rows, cols, epsilon)?)
+            }
+            Normalization::BatchNormalization { epsilon, momentum } => {
+                NormalizationOperator::Batch(BatchNormalization::try_new(
+                    device, rows, cols, epsilon, momentum,
+                )?)
+            }
+            Normalization::GroupNormalization { groups, epsilon } => NormalizationOperator::Group(
+                GroupNormalization::try_new(device, rows, cols, groups, epsilon)?,
+            ),
+        };
+        Ok(op)
+    }
+}
+
+pub enum NormalizationOperator {
+    Layer(LayerNormalization),
+    Rms(RmsNormalization),
+    Batch(BatchNormalization),
+    Group(GroupNormalization),
+}
+
+impl UnaryOperator for NormalizationOperator {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        match self {
+            NormalizationOperator::Layer(op) => op.forward(input),
+            NormalizationOperator::Rms(op) => op.forward(input),
+            NormalizationOperator::Batch(op) => op.forward(input),
+            NormalizationOperator::Group(op) => op.forward(input),
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use more_asserts::assert_le;
+
+use crate::{
+    new_tensor, new_tensor_with_grad,
+    statistics::{
+        batch_norm::{BatchNormalization, BatchStandardization},
+        normalization::{Normalization, NormalizationAttributes},
+    },
+    stream::StreamTrait,
+    transformer::{Transformer, TransformerConfig},
+    Device, ExecutableOperator, OperatorAttributes, UnaryOperator,
+};
+
+fn input_values() -> Vec<f32> {
+    vec![
+        //
+        1.0, -2.0, 3.0, 0.5, //
+        -1.5, 4.0, 2.0, -3.0, //
+        0.25, 1.0, -0.5, 2.5, //
+    ]
+}
+
+fn output_gradient_values() -> Vec<f32> {
+    vec![
+        //
+        0.3, -0.7, 1.1, 0.2, //
+        -0.4, 0.9, 0.1, -1.2, //
+        0.6, -0.2, 0.8, 0.5, //
+    ]
+}
+
+/// Compare an analytic gradient with central differences of sum(output_gradient * f(input)).
+fn assert_gradient(
+    forward: impl Fn(&[f32], &mut [f32]),
+    backward: impl Fn(&[f32], &[f32], &mut [f32]),
+) {
+    let input = input_values();
+    let output_gradient = output_gradient_values();
+    let mut input_gradient = vec![0.0; input.len()];
+    backward(&input, &output_gradient, &mut input_gradient);
+
+    let loss = |input: &[f32]| -> f32 {
+        let mut output = vec![0.0; input.len()];
+        forward(input, &mut output);
+        output
+            .iter()
+            .zip(output_gradient.iter())
+            .map(|(y, dy)| y * dy)
+            .sum()
+    };
+    let h = 1e-2;
+    for i in 0..input.len() {
+        let mut plus = input.clone();
+        plus[i] += h;
+        let mut minus = input.clone();
+        minus[i] -= h;
+        let expected = (loss(&plus) - loss(&minus)) / (2.0 * h);
+        assert_le!((expected - input_gradient[i]).abs(), 1e-2);
+    }
+}
+
+#[test]
+fn rms_standardization_has_unit_root_mean_square() {
+    let attributes = NormalizationAttributes::default();
+    let input = input_values();
+    let mut output = vec![0.0; input.len()];
+    attributes.rms_standardization(4, &input, &mut output);
+    for row in output.chunks(4) {
+        let mean_square = row.iter().map(|x| x * x).sum::<f32>() / 4.0;
+        assert_le!((mean_square - 1.0).abs(), 1e-5);
+    }
+}
+
+#[test]
+fn rms_standardization_gradient() {
+    let attributes = NormalizationAttributes::default();
+    assert_gradient(
+        |input, output| attributes.rms_standardization(4, input, output),
+        |input, output_gradient, input_gradient| {
+            attributes.rms_standardization_gradient(4, input, output_gradient, input_gradient)
+        },
+    );
+}
+
+#[test]
+fn group_standardization_gradient() {
+    let attributes = NormalizationAttributes {
+        groups: 2,
+        ..Default::default()
+    };
+    assert_gradient(
+        |input, output| attributes.group_standardization(4, input, output),
+        |input, output_gradient, input_gradient| {
+            attributes.group_standardization_gradient(4, input, output_gradient, input_gradient)
+        },
+    );
+}
+
+#[test]
+fn batch_standardization_gradient() {
+    let attributes = NormalizationAttributes::default();
+    for training in [true, false] {
+        let running_var = vec![0.5, 2.0, 1.0, 4.0];
+        assert_gradient(
+            |input, output| {
+                let mut running_mean = vec![0.0; 4];
+                let mut running_var = running_var.clone();
+                attributes.batch_standardization(
+                    3,
+                    4,
+                    training,
+                    input,
+                    &mut running_mean,
+                    &mut running_var,
+                    output,
+                )
+            },
+            |input, output_gradient, input_gradient| {
+                attributes.batch_standardization_gradient(
+                    3,
+                    4,
+                    training,
+                    input,
+                    &running_var,
+                    output_gradient,
+                    input_gradient,
+                )
+            },
+        );
+    }
+}
+
+#[test]
+fn batch_standardization_uses_running_statistics_in_evaluation() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let attributes = OperatorAttributes::Normalization(NormalizationAttributes {
+        momentum: 0.5,
+        ..Default::default()
+    });
+    let training = new_tensor!(device, 1, 1, vec![1.0]).unwrap();
+    let input = new_tensor!(device, 2, 2, vec![1.0, 2.0, 3.0, 6.0]).unwrap();
+    let running_mean = new_tensor!(device, 1, 2, vec![0.0; 2]).unwrap();
+    let running_var = new_tensor!(device, 1, 2, vec![1.0; 2]).unwrap();
+    let output = new_tensor!(device, 2, 2, vec![0.0; 4]).unwrap();
+
+    BatchStandardization::execute(
+        &attributes,
+        &[&training, &input],
+        &[&output, &running_mean, &running_var],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+    // Batch mean is [2, 4] and batch variance is [1, 4].
+    assert_eq!(running_mean.get_values().unwrap(), vec![1.0, 2.0]);
+    assert_eq!(running_var.get_values().unwrap(), vec![1.0, 2.5]);
+
+    training.set_values(vec![0.0]).unwrap();
+    BatchStandardization::execute(
+        &attributes,
+        &[&training, &input],
+        &[&output, &running_mean, &running_var],
+        &device,
+        &device_stream,
+    )
+    .unwrap();
+    device_stream.wait_for().unwrap();
+    // Running statistics are not updated in evaluation.
+    assert_eq!(running_mean.get_values().unwrap(), vec![1.0, 2.0]);
+    let output = output.get_values().unwrap();
+    let expected = [0.0, 0.0, 2.0, 4.0 / 2.5_f32.sqrt()];
+    for (actual, expected) in output.iter().zip(expected.iter()) {
+        assert_le!((actual - expected).abs(), 1e-5);
+    }
+}
+
+#[test]
+fn batch_normalization_forward_in_evaluation() {
+    let device = Device::default();
+    let device_stream = device.new_stream().unwrap();
+    let op = BatchNormalization::try_new(&device, 2, 2, 0.0, 0.9).unwrap();
+    op.set_training(false).unwrap();
+    let input =
+        new_tensor_with_grad!(device, 2, 2, vec![1.0, 2.0, 3.0, 4.0], &[], true, false).unwrap();
+    let output = op.forward(&input).unwrap();
+    for tensor in output.get_tape().iter() {
+        for instruction in tensor.forward_instructions().iter() {
+            instruction.execute(&device, &device_stream).unwrap();
+        }
+    }
+    device_stream.wait_for().unwrap();
+    // Running mean is 0 and running variance is 1, gain is 1 and bias is 0.
+    assert_eq!(
+        output.tensor().get_values().unwrap(),
+        vec![1.0, 2.0, 3.0, 4.0]
+    );
+}
+
+#[test]
+fn normalization_operators_keep_the_input_shape() {
+    let device = Device::default();
+    let normalizations = [
+        Normalization::default(),
+        Normalization::RmsNormalization { epsilon: 1e-6 },
+        Normalization::BatchNormalization {
+            epsilon: 1e-5,
+            momentum: 0.9,
+        },
+        Normalization::GroupNormalization {
+            groups: 2,
+            epsilon: 1e-5,
+        },
+    ];
+    for normalization in normalizations {
+        let op = normalization.try_new_operator(&device, 3, 4).unwrap();
+        let input = new_tensor_with_grad!(device, 3, 4, input_values(), &[], true, false).unwrap();
+        let output = op.forward(&input).unwrap();
+        assert_eq!(output.tensor().rows(), 3);
+        assert_eq!(output.tensor().cols(), 4);
+    }
+}
+
+#[test]
+fn group_normalization_requires_divisible_columns() {
+    let device = Device::default();
+    let normalization = Normalization::GroupNormalization {
+        groups: 3,
+        epsilon: 1e-5,
+    };
+    assert!(normalization.try_new_operator(&device, 3, 4).is_err());
+}
+
+#[test]
+fn transformer_rejects_batch_normalization() {
+    let device = Device::default();
+    let batch_normalization = Normalization::BatchNormalization {
+        epsilon: 1e-5,
+        momentum: 0.9,
+    };
+    assert!(!batch_normalization.is_row_wise());
+    for normalization in [Normalization::default(), batch_normalization] {
+        let config = TransformerConfig {
+            normalization: normalization.clone(),
+            ..TransformerConfig::new(2)
+        };
+        let transformer = Transformer::try_new(&device, 3, 4, &config);
+        assert_eq!(transformer.is_ok(), normalization.is_row_wise());
+    }
+}
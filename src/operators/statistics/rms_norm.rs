@@ -0,0 +1,103 @@
+use crate::{
+    opcode::OpCode,
+    statistics::normalization::{standardize, ColumnAffine, NormalizationAttributes},
+    stream::DeviceStream,
+    tensor::{Error, Tensor},
+    Device, DeviceTrait, ExecutableOperator, OperatorAttributes, TensorWithGrad, UnaryOperator,
+};
+
+/// See:
+/// Root Mean Square Layer Normalization
+/// https://arxiv.org/abs/1910.07467
+/// https://onnx.ai/onnx/operators/onnx__RMSNormalization.html
+pub struct RmsNormalization {
+    standardization: RmsStandardization,
+    affine: ColumnAffine,
+}
+
+impl RmsNormalization {
+    pub fn try_new(device: &Device, rows: usize, cols: usize, epsilon: f32) -> Result<Self, Error> {
+        let op = Self {
+            standardization: RmsStandardization::new(device, epsilon),
+            affine: ColumnAffine::try_new(device, rows, cols, false, "RmsNormalization")?,
+        };
+        Ok(op)
+    }
+}
+
+impl UnaryOperator for RmsNormalization {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let standardized = self.standardization.forward(input)?;
+        self.affine.forward(&standardized)
+    }
+}
+
+/// First stage of RmsNormalization.
+/// Each row is divided by its root mean square.
+pub struct RmsStandardization {
+    device: Device,
+    attributes: NormalizationAttributes,
+}
+
+impl RmsStandardization {
+    pub fn new(device: &Device, epsilon: f32) -> Self {
+        Self {
+            device: device.clone(),
+            attributes: NormalizationAttributes {
+                epsilon,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl UnaryOperator for RmsStandardization {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        standardize(
+            &self.device,
+            &self.attributes,
+            OpCode::RmsStandardization,
+            OpCode::RmsStandardizationGradient,
+            input,
+        )
+    }
+}
+
+impl ExecutableOperator for RmsStandardization {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = NormalizationAttributes::from_attributes(attributes)?;
+        let input = inputs[0];
+        let output = outputs[0];
+        device.rms_standardization(attributes, input, output, device_stream)
+    }
+}
+
+pub struct RmsStandardizationGradient {}
+
+impl ExecutableOperator for RmsStandardizationGradient {
+    fn execute(
+        attributes: &OperatorAttributes,
+        inputs: &[&Tensor],
+        outputs: &[&Tensor],
+        device: &Device,
+        device_stream: &DeviceStream,
+    ) -> Result<(), Error> {
+        let attributes = NormalizationAttributes::from_attributes(attributes)?;
+        let input = inputs[0];
+        let output_gradient = inputs[1];
+        let input_gradient = outputs[0];
+        device.rms_standardization_gradient(
+            attributes,
+            input,
+            output_gradient,
+            input_gradient,
+            device_stream,
+        )
+    }
+}
@@ -1,19 +1,27 @@
 use crate::{
     gradient_instruction, inference_instruction, new_tensor_with_grad,
     opcode::OpCode,
+    statistics::normalization::NormalizationAttributes,
     stream::DeviceStream,
     tensor::{Error, Tensor},
     Device, DeviceTrait, ExecutableOperator, OperatorAttributes, TensorWithGrad, UnaryOperator,
+    EPSILON,
 };
 
 pub struct Standardization {
     device: Device,
+    epsilon: f32,
 }
 
 impl Standardization {
     pub fn new(device: &Device) -> Self {
+        Self::new_with_epsilon(device, EPSILON)
+    }
+
+    pub fn new_with_epsilon(device: &Device, epsilon: f32) -> Self {
         Self {
             device: device.clone(),
+            epsilon,
         }
     }
 }
@@ -35,7 +43,10 @@ impl UnaryOperator for Standardization {
         )?;
         output.push_instruction(inference_instruction!(
             OpCode::Standardization,
-            OperatorAttributes::None,
+            OperatorAttributes::Normalization(NormalizationAttributes {
+                epsilon: self.epsilon,
+                ..Default::default()
+            }),
             &[&input.tensor()],
             &[&output.tensor()],
         ));
@@ -51,7 +62,7 @@ impl UnaryOperator for Standardization {
 
 impl ExecutableOperator for Standardization {
     fn execute(
-        _attributes: &OperatorAttributes,
+        attributes: &OperatorAttributes,
         inputs: &[&Tensor],
         outputs: &[&Tensor],
         device: &Device,
@@ -59,6 +70,10 @@ impl ExecutableOperator for Standardization {
     ) -> Result<(), Error> {
         let input = inputs[0];
         let output = outputs[0];
-        device.standardization(input, output, device_stream)
+        let epsilon = match attributes {
+            OperatorAttributes::None => EPSILON,
+            _ => NormalizationAttributes::from_attributes(attributes)?.epsilon,
+        };
+        device.standardization(epsilon, input, output, device_stream)
     }
 }
@@ -1 +1,84 @@
+use crate::{
+    neural_program::NeuralProgram, new_tensor_with_grad, schedulers::DefaultStreamScheduler,
+    tensor::Error, Device, Dropout, GradientDescent, Linear, Model, NeuralMachine, ReduceSumSquare,
+    TensorWithGrad, UnaryModel, UnaryOperator, WeightsInitialization,
+};
 
+struct DropoutModel {
+    linear: Linear,
+    dropout: Dropout,
+}
+
+impl UnaryModel for DropoutModel {}
+
+impl Model for DropoutModel {
+    fn input_size(&self) -> Vec<usize> {
+        vec![1, 8]
+    }
+    fn output_size(&self) -> Vec<usize> {
+        vec![1, 8]
+    }
+}
+
+impl UnaryOperator for DropoutModel {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let state = self.linear.forward(input)?;
+        self.dropout.forward(&state)
+    }
+}
+
+fn infer(
+    neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+    input: &TensorWithGrad,
+) -> Vec<f32> {
+    let output = neural_machine.infer(input).unwrap();
+    let values = output.tensor().get_values().unwrap();
+    values
+}
+
+#[test]
+fn evaluation_mode_disables_dropout() {
+    let device = Device::default();
+    device.set_seed(7);
+    let model = DropoutModel {
+        linear: Linear::new(&device, 8, 8, WeightsInitialization::Kaiming, 1).unwrap(),
+        dropout: Dropout::try_new(&device, 1, 8, 0.5).unwrap(),
+    };
+    let program = NeuralProgram::try_new(
+        &device,
+        &model,
+        &ReduceSumSquare::new(&device),
+        &GradientDescent::new(0.1),
+        None,
+        &[],
+    )
+    .unwrap();
+    let mut neural_machine =
+        NeuralMachine::<f32, DefaultStreamScheduler>::try_new(&device, program, 16).unwrap();
+    let values = vec![1.0, -0.5, 0.25, 2.0, -1.0, 0.5, 1.5, -2.0];
+    let input = new_tensor_with_grad!(device, 1, 8, values, &[], false, false).unwrap();
+
+    neural_machine.set_training(false).unwrap();
+    assert!(!neural_machine.is_training());
+    let evaluation_output = infer(&mut neural_machine, &input);
+    assert!(evaluation_output.iter().all(|x| *x != 0.0));
+    assert_eq!(infer(&mut neural_machine, &input), evaluation_output);
+
+    // In training mode, each output is dropped or scaled by 1 / (1 - 0.5).
+    neural_machine.set_training(true).unwrap();
+    let mut dropped = 0;
+    for _ in 0..4 {
+        let training_output = infer(&mut neural_machine, &input);
+        for (actual, expected) in training_output.iter().zip(evaluation_output.iter()) {
+            if *actual == 0.0 {
+                dropped += 1;
+            } else {
+                assert!((actual - 2.0 * expected).abs() < 1e-5);
+            }
+        }
+    }
+    assert!(dropped > 0);
+
+    neural_machine.set_training(false).unwrap();
+    assert_eq!(infer(&mut neural_machine, &input), evaluation_output);
+}
@@ -1,5 +1,6 @@
 use crate::{
-    common_adam::optimize, tensor::Error, Device, Instruction, OptimizerTrait, TensorWithGrad,
+    common_adam::optimize, tensor::Error, Device, Instruction, OptimizerTrait, ParameterGroup,
+    TensorWithGrad,
 };
 
 /// See:
@@ -50,4 +51,18 @@ impl OptimizerTrait for Adam {
             tensors,
         )
     }
+
+    fn learning_rate(&self) -> f32 {
+        self.learning_rate
+    }
+
+    fn for_parameter_group(&self, group: &ParameterGroup) -> Option<Self> {
+        Some(Self {
+            learning_rate: group.learning_rate.unwrap_or(self.learning_rate),
+            beta1: self.beta1,
+            beta2: self.beta2,
+            epsilon: self.epsilon,
+            weight_decay: group.weight_decay.unwrap_or(self.weight_decay),
+        })
+    }
 }
@@ -1,6 +1,6 @@
 use crate::{
     new_tensor, opcode::OpCode, optimization_instruction, tensor::Error, Device, Instruction,
-    OperatorAttributes, OptimizerTrait, TensorWithGrad,
+    OperatorAttributes, OptimizerTrait, ParameterGroup, TensorWithGrad,
 };
 
 pub struct GradientDescent {
@@ -50,4 +50,14 @@ impl OptimizerTrait for GradientDescent {
 
         Ok(instructions)
     }
+
+    fn learning_rate(&self) -> f32 {
+        self.learning_rate
+    }
+
+    fn for_parameter_group(&self, group: &ParameterGroup) -> Option<Self> {
+        Some(Self {
+            learning_rate: group.learning_rate.unwrap_or(self.learning_rate),
+        })
+    }
 }
@@ -4,6 +4,8 @@ mod adam;
 pub use adam::*;
 pub mod adam_w;
 pub mod common_adam;
+mod parameter_group;
+pub use parameter_group::*;
 
 use crate::{tensor::Error, Device, Instruction, TensorWithGrad};
 
@@ -13,4 +15,17 @@ pub trait OptimizerTrait {
         device: &Device,
         tensors: &[TensorWithGrad],
     ) -> Result<Vec<Instruction>, Error>;
+
+    fn learning_rate(&self) -> f32;
+
+    /// Returns an optimizer that uses the hyperparameters of the parameter group.
+    /// Hyperparameters that are not set in the group are inherited from self.
+    /// The default implementation returns None for optimizers without per-group
+    /// hyperparameters. Their parameter groups must not override any hyperparameter.
+    fn for_parameter_group(&self, _group: &ParameterGroup) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
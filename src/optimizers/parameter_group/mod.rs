@@ -0,0 +1,54 @@
+use crate::TensorWithGrad;
+
+#[cfg(test)]
+mod tests;
+
+/// A parameter group overrides optimizer hyperparameters for the parameters
+/// that have all the tags of the group.
+///
+/// Parameters are tagged by the operator that creates them.
+/// For example, the biases of a Linear have the tags "Linear" and "biases",
+/// and the gains of a LayerNormalization have the tags "LayerNormalization" and "gain".
+/// Biases of every operator have the tag "biases".
+///
+/// See:
+/// Decoupled Weight Decay Regularization
+/// https://arxiv.org/abs/1711.05101
+#[derive(Clone, Debug, Default)]
+pub struct ParameterGroup {
+    pub tags: Vec<String>,
+    pub learning_rate: Option<f32>,
+    pub weight_decay: Option<f32>,
+}
+
+impl ParameterGroup {
+    pub fn new(tags: &[&str], learning_rate: Option<f32>, weight_decay: Option<f32>) -> Self {
+        Self {
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            learning_rate,
+            weight_decay,
+        }
+    }
+
+    pub fn contains(&self, tensor: &TensorWithGrad) -> bool {
+        self.tags.iter().all(|tag| tensor.has_tag(tag))
+    }
+}
+
+/// Split parameters in one list per parameter group.
+/// A parameter goes in the first group that contains it.
+/// The last list contains the parameters that are in no group.
+pub fn split_parameters(
+    parameter_groups: &[ParameterGroup],
+    parameters: &[TensorWithGrad],
+) -> Vec<Vec<TensorWithGrad>> {
+    let mut groups = vec![vec![]; parameter_groups.len() + 1];
+    for parameter in parameters.iter() {
+        let index = parameter_groups
+            .iter()
+            .position(|group| group.contains(parameter))
+            .unwrap_or(parameter_groups.len());
+        groups[index].push(parameter.clone());
+    }
+    groups
+}
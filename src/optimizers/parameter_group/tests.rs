@@ -0,0 +1,153 @@
+use crate::{
+    adam_w::AdamW, new_tensor_with_grad, split_parameters, stream::StreamTrait, tensor::Error,
+    Device, GradientDescent, Instruction, OptimizerTrait, ParameterGroup, TensorWithGrad,
+};
+
+fn tagged_parameter(device: &Device, value: f32, tags: &[&str]) -> TensorWithGrad {
+    let parameter = new_tensor_with_grad!(device, 1, 1, vec![value], &[], true, true).unwrap();
+    for tag in tags {
+        parameter.add_tag(tag);
+    }
+    parameter
+}
+
+/// Sets the gradients and executes one update of each optimizer on its parameters.
+fn update(
+    device: &Device,
+    updates: &[(&dyn OptimizerTrait, &TensorWithGrad)],
+    gradient: f32,
+) -> Vec<f32> {
+    let device_stream = device.new_stream().unwrap();
+    for (optimizer, parameter) in updates.iter() {
+        parameter.gradient().set_values(vec![gradient]).unwrap();
+        let instructions = optimizer.optimize(device, &[(*parameter).clone()]).unwrap();
+        for instruction in instructions.iter() {
+            instruction.execute(device, &device_stream).unwrap();
+        }
+    }
+    device_stream.wait_for().unwrap();
+    updates
+        .iter()
+        .map(|(_, parameter)| parameter.tensor().get_values().unwrap()[0])
+        .collect()
+}
+
+#[test]
+fn groups_contain_parameters_with_all_their_tags() {
+    let device = Device::default();
+    let weights = tagged_parameter(&device, 1.0, &["Linear", "weights"]);
+    let biases = tagged_parameter(&device, 1.0, &["Linear", "biases"]);
+    let table = tagged_parameter(&device, 1.0, &["Embedding", "weights"]);
+
+    let linear_weights = ParameterGroup::new(&["Linear", "weights"], None, None);
+    assert!(linear_weights.contains(&weights));
+    assert!(!linear_weights.contains(&biases));
+    assert!(!linear_weights.contains(&table));
+
+    // A group without tags contains every parameter.
+    let everything = ParameterGroup::new(&[], None, None);
+    assert!(everything.contains(&table));
+}
+
+#[test]
+fn unmatched_parameters_are_in_the_default_group() {
+    let device = Device::default();
+    let weights = tagged_parameter(&device, 1.0, &["Linear", "weights"]);
+    let biases = tagged_parameter(&device, 1.0, &["Linear", "biases"]);
+    let gain = tagged_parameter(&device, 1.0, &["LayerNormalization", "gain"]);
+    let table = tagged_parameter(&device, 1.0, &["Embedding", "weights"]);
+    let parameters = vec![weights, biases, gain, table];
+
+    // The biases are in the first group that contains them.
+    let groups = vec![
+        ParameterGroup::new(&["biases"], None, Some(0.0)),
+        ParameterGroup::new(&["Linear"], None, None),
+        ParameterGroup::new(&["gain"], None, Some(0.0)),
+    ];
+    let split = split_parameters(&groups, &parameters);
+    assert_eq!(split.len(), groups.len() + 1);
+    let names = |tensors: &[TensorWithGrad]| -> Vec<usize> {
+        tensors
+            .iter()
+            .map(|tensor| tensor.tensor().name())
+            .collect()
+    };
+    assert_eq!(names(&split[0]), names(&parameters[1..2]));
+    assert_eq!(names(&split[1]), names(&parameters[0..1]));
+    assert_eq!(names(&split[2]), names(&parameters[2..3]));
+    assert_eq!(names(&split[3]), names(&parameters[3..4]));
+
+    // Without groups, every parameter is in the default group.
+    let split = split_parameters(&[], &parameters);
+    assert_eq!(split.len(), 1);
+    assert_eq!(split[0].len(), parameters.len());
+}
+
+#[test]
+fn groups_override_the_learning_rate() {
+    let device = Device::default();
+    let default_parameter = tagged_parameter(&device, 1.0, &["Linear", "weights"]);
+    let group_parameter = tagged_parameter(&device, 1.0, &["Embedding", "weights"]);
+    let optimizer = GradientDescent::new(0.1);
+    let group = ParameterGroup::new(&["Embedding"], Some(0.5), None);
+    let group_optimizer = optimizer.for_parameter_group(&group).unwrap();
+    assert_eq!(group_optimizer.learning_rate(), 0.5);
+
+    let values = update(
+        &device,
+        &[
+            (&optimizer, &default_parameter),
+            (&group_optimizer, &group_parameter),
+        ],
+        1.0,
+    );
+    assert_eq!(values, vec![0.9, 0.5]);
+}
+
+#[test]
+fn groups_override_the_weight_decay() {
+    let device = Device::default();
+    let default_parameter = tagged_parameter(&device, 1.0, &["Linear", "weights"]);
+    let group_parameter = tagged_parameter(&device, 1.0, &["Linear", "biases"]);
+    let optimizer = AdamW::try_new(0.1, 0.9, 0.999, 1e-8, 0.5).unwrap();
+    let group = ParameterGroup::new(&["biases"], None, Some(0.0));
+    let group_optimizer = optimizer.for_parameter_group(&group).unwrap();
+    assert_eq!(group_optimizer.learning_rate(), 0.1);
+
+    // With a zero gradient, only the weight decay changes the parameters.
+    let values = update(
+        &device,
+        &[
+            (&optimizer, &default_parameter),
+            (&group_optimizer, &group_parameter),
+        ],
+        0.0,
+    );
+    assert!(values[0] < 1.0);
+    assert_eq!(values[1], 1.0);
+}
+
+/// An optimizer that does not implement for_parameter_group.
+struct ConstantGradientDescent;
+
+impl OptimizerTrait for ConstantGradientDescent {
+    fn optimize(
+        &self,
+        device: &Device,
+        tensors: &[TensorWithGrad],
+    ) -> Result<Vec<Instruction>, Error> {
+        GradientDescent::new(self.learning_rate()).optimize(device, tensors)
+    }
+
+    fn learning_rate(&self) -> f32 {
+        0.1
+    }
+}
+
+#[test]
+fn optimizers_without_hyperparameters_per_group() {
+    let group = ParameterGroup::new(&["biases"], None, None);
+    assert!(ConstantGradientDescent
+        .for_parameter_group(&group)
+        .is_none());
+}
@@ -1,10 +1,14 @@
-use crate::{tensor::Error, TokenizerTrait};
+use crate::{
+    push_replacement_character, SpecialToken, TokenizerTrait, BYTE_TOKENS, SPECIAL_TOKENS,
+};
 
+/// Each byte of the UTF-8 text is a token.
+/// The special tokens follow the byte tokens.
 #[derive(Default)]
 pub struct AsciiTokenizer {}
 
 impl TokenizerTrait for AsciiTokenizer {
-    fn encode(&mut self, text: &str) -> Vec<usize> {
+    fn encode(&self, text: &str) -> Vec<usize> {
         text.as_bytes()
             .to_owned()
             .into_iter()
@@ -12,16 +16,23 @@ impl TokenizerTrait for AsciiTokenizer {
             .collect()
     }
 
-    fn decode(&self, tokens: &[usize]) -> Result<String, Error> {
-        let output = tokens
-            .iter()
-            .map(|token| String::from(*token as u8 as char))
-            .collect::<Vec<_>>()
-            .join("");
-        Ok(output)
+    fn decode_bytes(&self, tokens: &[usize]) -> Vec<u8> {
+        let mut output = vec![];
+        for token in tokens {
+            if *token < BYTE_TOKENS {
+                output.push(*token as u8);
+            } else if self.as_special_token(*token).is_none() {
+                push_replacement_character(&mut output);
+            }
+        }
+        output
+    }
+
+    fn special_token(&self, special_token: SpecialToken) -> usize {
+        BYTE_TOKENS + special_token.index()
     }
 
     fn vocab_size(&self) -> usize {
-        256
+        BYTE_TOKENS + SPECIAL_TOKENS
     }
 }
@@ -1,18 +1,40 @@
-use std::{collections::BTreeMap, collections::HashMap, mem::swap};
+use std::{collections::HashMap, fs};
 
-use crate::{error, tensor::Error, tensor::ErrorEnum, TokenizerTrait};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error, push_replacement_character, tensor::Error, tensor::ErrorEnum, SpecialToken,
+    TokenizerTrait, BYTE_TOKENS, SPECIAL_TOKENS,
+};
 
 #[cfg(test)]
 mod tests;
 
+/// The byte tokens and the special tokens come before the merged tokens.
+const FIRST_MERGE_TOKEN: usize = BYTE_TOKENS + SPECIAL_TOKENS;
+
+/// Byte pair encoding.
+/// The merges are learned with train and are then applied in order by encode.
+/// The byte tokens are followed by the special tokens.
+/// The merge at index i produces the token BYTE_TOKENS + SPECIAL_TOKENS + i.
+///
+/// See:
+/// Neural Machine Translation of Rare Words with Subword Units
+/// https://arxiv.org/abs/1508.07909
 #[derive(Default)]
 pub struct BytePairEncoding {
-    vocab_size: usize,
-    // TODO add maximum vocabulary size.
-    byte_to_token: HashMap<u8, usize>,
-    token_to_byte: HashMap<usize, u8>,
-    token_pair_to_token: HashMap<(usize, usize), usize>,
-    token_to_token_pair: HashMap<usize, (usize, usize)>,
+    merges: Vec<(usize, usize)>,
+    pair_to_token: HashMap<(usize, usize), usize>,
+}
+
+/// Version of the saved merge tables.
+/// Files with another version are rejected.
+const SAVED_VERSION: usize = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SavedBytePairEncoding {
+    version: usize,
+    merges: Vec<(usize, usize)>,
 }
 
 fn get_pair(tokens: &[usize], i: usize) -> Option<(usize, usize)> {
@@ -26,126 +48,200 @@ fn get_pair(tokens: &[usize], i: usize) -> Option<(usize, usize)> {
     Some(pair)
 }
 
-impl TokenizerTrait for BytePairEncoding {
-    fn encode(&mut self, text: &str) -> Vec<usize> {
-        let mut tokens = vec![];
-        let mut tokens_tmp = vec![];
-        let mut next_token = 0;
-
-        let mut allocate_token = || -> usize {
-            let token = next_token;
-            next_token += 1;
-            token
-        };
-
-        // Encode bytes into tokens
-        for byte in text.bytes() {
-            let token = self.byte_to_token.entry(byte).or_insert_with(|| {
-                let token = allocate_token();
-                self.token_to_byte.insert(token, byte);
-                token
-            });
-            tokens.push(*token);
+/// Replace the occurrences of pair by token, from left to right.
+fn merge(tokens: &[usize], pair: (usize, usize), token: usize) -> Vec<usize> {
+    let mut merged = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if get_pair(tokens, i) == Some(pair) {
+            merged.push(token);
+            i += 2;
+        } else {
+            merged.push(tokens[i]);
+            i += 1;
         }
+    }
+    merged
+}
 
-        // Encode token pairs into tokens
-        let mut token_pair_counters = BTreeMap::<(usize, usize), usize>::default();
-        let mut has_repeated_pair = true;
-        while has_repeated_pair {
-            // Count pairs
-            token_pair_counters.clear();
-            let mut i = 0;
-            while i < tokens.len() {
-                match get_pair(&tokens, i) {
-                    Some(pair) => {
-                        token_pair_counters
-                            .entry(pair)
-                            .and_modify(|counter| *counter += 1)
-                            .or_insert(1);
-                    }
-                    _ => {}
+/// Update the pair counters of tokens into the pair counters of merged_tokens.
+/// Only the pairs that touch a merged token change.
+fn update_counters(
+    token_pair_counters: &mut HashMap<(usize, usize), usize>,
+    tokens: &[usize],
+    merged_tokens: &[usize],
+    token: usize,
+) {
+    let mut changed = vec![false; tokens.len()];
+    let mut merged_changed = vec![false; merged_tokens.len()];
+    let mut i = 0;
+    for (k, merged_token) in merged_tokens.iter().enumerate() {
+        if *merged_token == token {
+            changed[i] = true;
+            changed[i + 1] = true;
+            merged_changed[k] = true;
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    for i in 0..tokens.len().saturating_sub(1) {
+        if changed[i] || changed[i + 1] {
+            let pair = (tokens[i], tokens[i + 1]);
+            if let Some(counter) = token_pair_counters.get_mut(&pair) {
+                *counter -= 1;
+                if *counter == 0 {
+                    token_pair_counters.remove(&pair);
                 }
-                i += 1;
             }
-            let max = token_pair_counters
-                .iter()
-                .filter(|item| item.1 > &1)
-                .map(|item| item.1)
-                .max();
+        }
+    }
+    for k in 0..merged_tokens.len().saturating_sub(1) {
+        if merged_changed[k] || merged_changed[k + 1] {
+            let pair = (merged_tokens[k], merged_tokens[k + 1]);
+            *token_pair_counters.entry(pair).or_insert(0) += 1;
+        }
+    }
+}
 
-            let expected_pair = max
-                .and_then(|max| token_pair_counters.iter().find(|item| item.1 == max))
-                .map(|item| item.0);
+impl BytePairEncoding {
+    fn try_from_merges(merges: Vec<(usize, usize)>) -> Result<Self, Error> {
+        let mut pair_to_token = HashMap::new();
+        for (i, pair) in merges.iter().enumerate() {
+            let token = FIRST_MERGE_TOKEN + i;
+            let is_special = |token: usize| (BYTE_TOKENS..FIRST_MERGE_TOKEN).contains(&token);
+            if is_special(pair.0)
+                || is_special(pair.1)
+                || pair.0 >= token
+                || pair.1 >= token
+                || pair_to_token.insert(*pair, token).is_some()
+            {
+                return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+            }
+        }
+        Ok(Self {
+            merges,
+            pair_to_token,
+        })
+    }
+
+    /// Learn merges on a corpus until the vocabulary has max_vocab_size tokens
+    /// or no pair of tokens is repeated.
+    /// The most frequent pair is merged first, ties are broken by the smallest pair.
+    pub fn train(&mut self, corpus: &str, max_vocab_size: usize) -> Result<(), Error> {
+        if max_vocab_size < FIRST_MERGE_TOKEN {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        let mut merges = vec![];
+        let mut tokens: Vec<usize> = corpus.bytes().map(usize::from).collect();
+        let mut token_pair_counters = HashMap::<(usize, usize), usize>::default();
+        for i in 0..tokens.len() {
+            if let Some(pair) = get_pair(&tokens, i) {
+                *token_pair_counters.entry(pair).or_insert(0) += 1;
+            }
+        }
+        while FIRST_MERGE_TOKEN + merges.len() < max_vocab_size {
+            let expected_pair = token_pair_counters
+                .iter()
+                .filter(|(_, counter)| **counter > 1)
+                .max_by(|(pair_1, counter_1), (pair_2, counter_2)| {
+                    counter_1.cmp(counter_2).then(pair_2.cmp(pair_1))
+                })
+                .map(|(pair, _)| *pair);
 
-            has_repeated_pair = expected_pair.is_some();
             match expected_pair {
                 Some(expected_pair) => {
-                    tokens_tmp.clear();
-                    let mut i = 0;
-                    while i < tokens.len() {
-                        match get_pair(&tokens, i) {
-                            Some(pair) => {
-                                if &pair == expected_pair {
-                                    let token = allocate_token();
-                                    tokens_tmp.push(token);
-                                    self.token_pair_to_token.insert(pair, token);
-                                    self.token_to_token_pair.insert(token, pair);
-                                    i += 1 + 1;
-                                } else {
-                                    tokens_tmp.push(tokens[i]);
-                                    i += 1;
-                                }
-                            }
-                            _ => {
-                                tokens_tmp.push(tokens[i]);
-                                i += 1;
-                            }
-                        }
-                    }
-                    swap(&mut tokens, &mut tokens_tmp);
+                    let token = FIRST_MERGE_TOKEN + merges.len();
+                    merges.push(expected_pair);
+                    let merged_tokens = merge(&tokens, expected_pair, token);
+                    update_counters(&mut token_pair_counters, &tokens, &merged_tokens, token);
+                    tokens = merged_tokens;
                 }
-                _ => {}
+                None => break,
             }
         }
+        *self = Self::try_from_merges(merges)?;
+        Ok(())
+    }
 
-        self.vocab_size = next_token;
+    /// Pairs of tokens merged by the encoder, in training order.
+    pub fn merges(&self) -> &[(usize, usize)] {
+        &self.merges
+    }
 
-        tokens
+    /// Write the merge table to a JSON file.
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        let saved = SavedBytePairEncoding {
+            version: SAVED_VERSION,
+            merges: self.merges.clone(),
+        };
+        let json =
+            serde_json::to_string(&saved).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+        fs::write(path, json).map_err(|_| error!(ErrorEnum::InputOutputError))
     }
 
-    fn decode(&self, tokens: &[usize]) -> Result<String, Error> {
-        // Decode tokens to pairs.
-        let mut tokens2 = tokens.to_owned();
-        let mut tokens_tmp = vec![];
-        let mut a_token_was_found = true;
-        while a_token_was_found {
-            tokens_tmp.clear();
-            a_token_was_found = false;
-            for token in tokens2.iter() {
-                match self.token_to_token_pair.get(token) {
-                    Some((token_1, token_2)) => {
-                        tokens_tmp.push(*token_1);
-                        tokens_tmp.push(*token_2);
-                        a_token_was_found = true;
-                    }
-                    _ => {
-                        tokens_tmp.push(*token);
-                    }
-                }
+    /// Read a merge table written by save.
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let json = fs::read_to_string(path).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+        let saved: SavedBytePairEncoding =
+            serde_json::from_str(&json).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+        if saved.version != SAVED_VERSION {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        Self::try_from_merges(saved.merges)
+    }
+
+    /// Append the bytes of a token.
+    fn push_bytes(&self, token: usize, output: &mut Vec<u8>) {
+        if token < BYTE_TOKENS {
+            output.push(token as u8);
+            return;
+        }
+        if token < FIRST_MERGE_TOKEN {
+            return;
+        }
+        match self.merges.get(token - FIRST_MERGE_TOKEN) {
+            Some((token_1, token_2)) => {
+                self.push_bytes(*token_1, output);
+                self.push_bytes(*token_2, output);
             }
-            swap(&mut tokens2, &mut tokens_tmp);
+            None => push_replacement_character(output),
         }
+    }
+}
 
-        // Decode tokens to bytes
+impl TokenizerTrait for BytePairEncoding {
+    /// Apply the merges in training order.
+    /// The pair with the earliest merge is merged first, which gives the same tokens as
+    /// applying every merge to the whole text, one after the other.
+    fn encode(&self, text: &str) -> Vec<usize> {
+        let mut tokens: Vec<usize> = text.bytes().map(usize::from).collect();
+        loop {
+            let earliest_merge = (0..tokens.len())
+                .filter_map(|i| get_pair(&tokens, i))
+                .filter_map(|pair| self.pair_to_token.get(&pair).map(|token| (*token, pair)))
+                .min();
+            match earliest_merge {
+                Some((token, pair)) => tokens = merge(&tokens, pair, token),
+                None => break,
+            }
+        }
+        tokens
+    }
+
+    fn decode_bytes(&self, tokens: &[usize]) -> Vec<u8> {
         let mut output = vec![];
-        for token in tokens2 {
-            let byte = self.token_to_byte.get(&token).unwrap_or(&b'?');
-            output.push(*byte);
+        for token in tokens {
+            self.push_bytes(*token, &mut output);
         }
-        String::from_utf8(output).map_err(|_| error!(ErrorEnum::UnsupportedOperation))
+        output
+    }
+
+    fn special_token(&self, special_token: SpecialToken) -> usize {
+        BYTE_TOKENS + special_token.index()
     }
 
     fn vocab_size(&self) -> usize {
-        self.vocab_size
+        FIRST_MERGE_TOKEN + self.merges.len()
     }
 }
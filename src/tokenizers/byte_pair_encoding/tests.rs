@@ -2,11 +2,17 @@ use std::{collections::HashMap, fs};
 
 use crate::{BytePairEncoding, TokenizerTrait};
 
+fn trained_tokenizer(text: &str) -> BytePairEncoding {
+    let mut tokenizer = BytePairEncoding::default();
+    tokenizer.train(text, usize::MAX).unwrap();
+    tokenizer
+}
+
 #[test]
 fn decode() {
     let file_path = "data/Mega_Man.txt";
     let text = fs::read_to_string(file_path).unwrap();
-    let mut tokenizer = BytePairEncoding::default();
+    let tokenizer = trained_tokenizer(&text);
     let tokens = tokenizer.encode(&text);
     let decoded_text = tokenizer.decode(&tokens).unwrap();
     assert_eq!(decoded_text, text);
@@ -23,16 +29,16 @@ fn text_length() {
 fn deterministic_tokens_length() {
     let file_path = "data/Mega_Man.txt";
     let text = fs::read_to_string(file_path).unwrap();
-    let mut tokenizer = BytePairEncoding::default();
+    let tokenizer = trained_tokenizer(&text);
     let tokens = tokenizer.encode(&text);
-    assert_eq!(tokens.len(), 44513,);
+    assert_eq!(tokens.len(), 12078,);
 }
 
 #[test]
 fn no_repeated_pairs() {
     let file_path = "data/Mega_Man.txt";
     let text = fs::read_to_string(file_path).unwrap();
-    let mut tokenizer = BytePairEncoding::default();
+    let tokenizer = trained_tokenizer(&text);
     let tokens = tokenizer.encode(&text);
     let mut token_pair_counters = HashMap::<(usize, usize), usize>::default();
     for i in 0..tokens.len() - 1 {
@@ -47,3 +53,47 @@ fn no_repeated_pairs() {
         assert_eq!(*counter, 1);
     }
 }
+
+#[test]
+fn encoding_does_not_change_the_vocabulary() {
+    let mut tokenizer = BytePairEncoding::default();
+    tokenizer
+        .train("the cat sat on the mat with the hat", 265)
+        .unwrap();
+    assert_eq!(tokenizer.vocab_size(), 265);
+
+    let prompt = "that cat";
+    let tokens = tokenizer.encode(prompt);
+    let _ = tokenizer.encode("something else entirely");
+    assert_eq!(tokenizer.vocab_size(), 265);
+    assert_eq!(tokenizer.encode(prompt), tokens);
+    assert_eq!(tokenizer.decode(&tokens).unwrap(), prompt);
+
+    // Bytes that were not in the corpus are still encoded.
+    assert_eq!(tokenizer.decode(&tokenizer.encode("zébu")).unwrap(), "zébu");
+    assert!(tokenizer.train("the cat", 260).is_err());
+}
+
+#[test]
+fn save_and_load_merges() {
+    let mut tokenizer = BytePairEncoding::default();
+    tokenizer
+        .train("low lower lowest newer newest wider widest", 270)
+        .unwrap();
+    let path = std::env::temp_dir().join("novigrad_byte_pair_encoding.json");
+    let path = path.to_str().unwrap();
+    tokenizer.save(path).unwrap();
+    let loaded = BytePairEncoding::load(path).unwrap();
+    assert_eq!(loaded.merges(), tokenizer.merges());
+    let text = "lowest widest";
+    assert_eq!(loaded.encode(text), tokenizer.encode(text));
+
+    fs::write(path, "{\"version\":2,\"merges\":[]}").unwrap();
+    assert!(BytePairEncoding::load(path).is_err());
+    fs::write(path, "{\"merges\":[]}").unwrap();
+    assert!(BytePairEncoding::load(path).is_err());
+
+    // 256 is a special token.
+    fs::write(path, "{\"version\":1,\"merges\":[[256,97]]}").unwrap();
+    assert!(BytePairEncoding::load(path).is_err());
+}
@@ -0,0 +1,239 @@
+use std::{collections::HashMap, fs};
+
+use fancy_regex::Regex;
+
+use crate::{
+    error, push_replacement_character, tensor::Error, tensor::ErrorEnum, SpecialToken,
+    TokenizerTrait, SPECIAL_TOKENS,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Splits the text into words, numbers, punctuation and whitespace before the merges.
+const PRE_TOKENIZATION_PATTERN: &str =
+    r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+
+/// Maps every byte to a printable character so that the tokens of vocab.json and merges.txt
+/// contain no whitespace or control characters.
+/// Printable bytes map to themselves, the other bytes map to 256, 257, ...
+fn bytes_to_unicode() -> Vec<char> {
+    let mut printable = vec![false; 256];
+    for byte in (b'!'..=b'~').chain(0xA1..=0xAC).chain(0xAE..=0xFF) {
+        printable[byte as usize] = true;
+    }
+    let mut byte_encoder = vec!['\0'; 256];
+    let mut n = 0;
+    for byte in 0..256 {
+        let code_point = if printable[byte] {
+            byte as u32
+        } else {
+            n += 1;
+            255 + n
+        };
+        byte_encoder[byte] = char::from_u32(code_point).unwrap_or_default();
+    }
+    byte_encoder
+}
+
+/// Byte-level byte pair encoding of GPT-2.
+/// The vocabulary and the merges are read from the vocab.json and merges.txt files
+/// of a pretrained checkpoint.
+/// Like in GPT-2, the special tokens that are not in vocab.json, i.e. all of them
+/// except <|endoftext|>, are <|endoftext|>, so that the vocabulary does not grow.
+///
+/// See:
+/// Language Models are Unsupervised Multitask Learners
+/// https://cdn.openai.com/better-language-models/language_models_are_unsupervised_multitask_learners.pdf
+pub struct Gpt2Tokenizer {
+    pattern: Regex,
+    byte_encoder: Vec<char>,
+    byte_decoder: HashMap<char, u8>,
+    encoder: HashMap<String, usize>,
+    decoder: HashMap<usize, String>,
+    merge_ranks: HashMap<(String, String), usize>,
+    special_tokens: [usize; SPECIAL_TOKENS],
+    vocab_size: usize,
+}
+
+impl Gpt2Tokenizer {
+    pub fn try_new(vocab_path: &str, merges_path: &str) -> Result<Self, Error> {
+        let vocab =
+            fs::read_to_string(vocab_path).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+        let merges =
+            fs::read_to_string(merges_path).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+        Self::try_from_str(&vocab, &merges)
+    }
+
+    /// Build the tokenizer from the contents of vocab.json and merges.txt.
+    pub fn try_from_str(vocab: &str, merges: &str) -> Result<Self, Error> {
+        let encoder: HashMap<String, usize> =
+            serde_json::from_str(vocab).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+        let decoder = encoder
+            .iter()
+            .map(|(token, id)| (*id, token.clone()))
+            .collect::<HashMap<_, _>>();
+        if decoder.len() != encoder.len() {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+
+        let mut merge_ranks = HashMap::new();
+        for line in merges
+            .lines()
+            .filter(|line| !line.starts_with("#version") && !line.is_empty())
+        {
+            let (first, second) = line
+                .split_once(' ')
+                .ok_or(error!(ErrorEnum::IncorrectOperatorConfiguration))?;
+            let rank = merge_ranks.len();
+            merge_ranks.insert((first.to_owned(), second.to_owned()), rank);
+        }
+
+        // Every byte and every merged pair must have an id, so that encode can not fail.
+        let byte_encoder = bytes_to_unicode();
+        let has_all_tokens = byte_encoder
+            .iter()
+            .map(|character| character.to_string())
+            .chain(
+                merge_ranks
+                    .keys()
+                    .map(|(first, second)| first.clone() + second),
+            )
+            .all(|token| encoder.contains_key(&token));
+        if !has_all_tokens {
+            return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+        }
+        let byte_decoder = byte_encoder
+            .iter()
+            .enumerate()
+            .map(|(byte, character)| (*character, byte as u8))
+            .collect();
+
+        // The vocabulary keeps the size of the checkpoint, so that the ids match its embeddings.
+        let vocab_size = encoder.values().max().map(|id| id + 1).unwrap_or_default();
+        let end_of_text = *encoder
+            .get(SpecialToken::Eos.text())
+            .ok_or(error!(ErrorEnum::IncorrectOperatorConfiguration))?;
+        let mut special_tokens = [end_of_text; SPECIAL_TOKENS];
+        for special_token in SpecialToken::ALL {
+            if let Some(id) = encoder.get(special_token.text()) {
+                special_tokens[special_token.index()] = *id;
+            }
+        }
+
+        let pattern = Regex::new(PRE_TOKENIZATION_PATTERN)
+            .map_err(|_| error!(ErrorEnum::IncorrectOperatorConfiguration))?;
+        Ok(Self {
+            pattern,
+            byte_encoder,
+            byte_decoder,
+            encoder,
+            decoder,
+            merge_ranks,
+            special_tokens,
+            vocab_size,
+        })
+    }
+
+    /// Split the text with the pre-tokenization pattern.
+    /// The words cover the whole text: the text that the pattern could not split,
+    /// for example when fancy_regex exceeds its backtrack limit, is kept as one word.
+    fn words<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let mut words = vec![];
+        let mut start = 0;
+        for found in self.pattern.find_iter(text) {
+            let found = match found {
+                Ok(found) => found,
+                Err(_) => break,
+            };
+            if found.start() > start {
+                words.push(&text[start..found.start()]);
+            }
+            words.push(found.as_str());
+            start = found.end();
+        }
+        if start < text.len() {
+            words.push(&text[start..]);
+        }
+        words
+    }
+
+    /// Merge the pair with the lowest rank until no pair of the word can be merged.
+    fn bpe(&self, word: &str) -> Vec<String> {
+        let mut symbols: Vec<String> = word.chars().map(String::from).collect();
+        loop {
+            let best_pair = symbols
+                .windows(2)
+                .filter_map(|pair| {
+                    self.merge_ranks
+                        .get(&(pair[0].clone(), pair[1].clone()))
+                        .map(|rank| (*rank, pair[0].clone(), pair[1].clone()))
+                })
+                .min();
+            let (_, first, second) = match best_pair {
+                Some(best_pair) => best_pair,
+                None => break,
+            };
+            let mut merged = Vec::with_capacity(symbols.len());
+            let mut i = 0;
+            while i < symbols.len() {
+                if i + 1 < symbols.len() && symbols[i] == first && symbols[i + 1] == second {
+                    merged.push(first.clone() + &second);
+                    i += 2;
+                } else {
+                    merged.push(symbols[i].clone());
+                    i += 1;
+                }
+            }
+            symbols = merged;
+        }
+        symbols
+    }
+}
+
+impl TokenizerTrait for Gpt2Tokenizer {
+    fn encode(&self, text: &str) -> Vec<usize> {
+        let mut tokens = vec![];
+        for word in self.words(text) {
+            let word: String = word
+                .bytes()
+                .map(|byte| self.byte_encoder[byte as usize])
+                .collect();
+            for symbol in self.bpe(&word) {
+                if let Some(token) = self.encoder.get(&symbol) {
+                    tokens.push(*token);
+                }
+            }
+        }
+        tokens
+    }
+
+    fn decode_bytes(&self, tokens: &[usize]) -> Vec<u8> {
+        let mut output = vec![];
+        for token in tokens {
+            if self.special_tokens.contains(token) {
+                continue;
+            }
+            match self.decoder.get(token) {
+                Some(symbol) => {
+                    for character in symbol.chars() {
+                        match self.byte_decoder.get(&character) {
+                            Some(byte) => output.push(*byte),
+                            None => push_replacement_character(&mut output),
+                        }
+                    }
+                }
+                None => push_replacement_character(&mut output),
+            }
+        }
+        output
+    }
+
+    fn special_token(&self, special_token: SpecialToken) -> usize {
+        self.special_tokens[special_token.index()]
+    }
+
+    fn vocab_size(&self) -> usize {
+        self.vocab_size
+    }
+}
@@ -0,0 +1,116 @@
+use fancy_regex::RegexBuilder;
+
+use crate::{Gpt2Tokenizer, SpecialToken, TokenizerTrait};
+
+use super::PRE_TOKENIZATION_PATTERN;
+
+// A vocabulary with the 256 bytes, 300 merges learned on data/Mega_Man.txt and <|endoftext|>,
+// in the format of the GPT-2 checkpoints.
+const VOCAB_PATH: &str = "data/gpt2_tokenizer/vocab.json";
+const MERGES_PATH: &str = "data/gpt2_tokenizer/merges.txt";
+
+fn tokenizer() -> Gpt2Tokenizer {
+    Gpt2Tokenizer::try_new(VOCAB_PATH, MERGES_PATH).unwrap()
+}
+
+#[test]
+fn encode_like_the_reference_tokenizer() {
+    let tokenizer = tokenizer();
+    // <|endoftext|> is in vocab.json and is every special token.
+    assert_eq!(tokenizer.vocab_size(), 557);
+    for special_token in SpecialToken::ALL {
+        assert_eq!(tokenizer.special_token(special_token), 556);
+    }
+    // Ids of the reference encoder of GPT-2 with the same vocab.json and merges.txt.
+    let expected_tokens: Vec<(&str, Vec<usize>)> = vec![
+        (
+            "Mega Man is a video game.",
+            vec![287, 281, 468, 274, 546, 353, 13],
+        ),
+        ("Hello world", vec![39, 444, 364, 282, 270, 553]),
+        (
+            "I'm sure they'll say it's 1987's best-selling game!",
+            vec![
+                40, 6, 76, 288, 84, 267, 273, 88, 6, 75, 75, 288, 64, 88, 479, 450, 367, 24, 23,
+                22, 450, 299, 405, 12, 82, 444, 75, 298, 353, 0,
+            ],
+        ),
+        (
+            "  leading spaces and trailing spaces   ",
+            vec![
+                220, 220, 278, 379, 298, 288, 79, 308, 262, 303, 304, 81, 64, 333, 298, 288, 79,
+                308, 262, 220, 220, 220,
+            ],
+        ),
+        (
+            "tabs\tand\nnew lines\n\n  indented",
+            vec![
+                83, 64, 65, 82, 197, 256, 67, 198, 77, 397, 547, 258, 262, 198, 198, 220, 294, 67,
+                453, 286,
+            ],
+        ),
+        (
+            "Numbers: 3.14159, 42 and 1,000,000.",
+            vec![
+                45, 84, 76, 394, 82, 25, 510, 13, 16, 19, 16, 20, 24, 11, 220, 19, 17, 303, 367,
+                11, 515, 15, 11, 515, 15, 13,
+            ],
+        ),
+        (
+            "Unicode: café, naïve, 東京, Ελληνικά and 🤖!",
+            vec![
+                52, 77, 305, 78, 313, 25, 315, 64, 69, 127, 102, 11, 341, 64, 127, 107, 285, 11,
+                220, 162, 251, 109, 160, 118, 105, 11, 220, 138, 243, 138, 119, 138, 119, 138, 115,
+                138, 121, 138, 117, 138, 118, 138, 105, 303, 220, 172, 253, 97, 244, 0,
+            ],
+        ),
+        (
+            "<|endoftext|>",
+            vec![27, 91, 401, 78, 69, 83, 68, 87, 83, 91, 29],
+        ),
+        ("", vec![]),
+        (
+            "what's up?!? ...",
+            vec![86, 71, 264, 450, 220, 417, 30, 0, 30, 220, 13, 13, 13],
+        ),
+        (
+            "Dr. Wily's robots -- Cut Man, Guts Man.",
+            vec![
+                35, 81, 13, 427, 333, 88, 450, 220, 322, 508, 82, 220, 12, 12, 339, 433, 281, 11,
+                414, 433, 82, 281, 13,
+            ],
+        ),
+    ];
+    for (text, expected) in expected_tokens.iter() {
+        let tokens = tokenizer.encode(text);
+        assert_eq!(&tokens, expected, "{:?}", text);
+        assert_eq!(&tokenizer.decode(&tokens).unwrap(), text);
+    }
+}
+
+#[test]
+fn incomplete_vocabulary() {
+    let vocab = r#"{"a": 0, "b": 1}"#;
+    assert!(Gpt2Tokenizer::try_from_str(vocab, "#version: 0.2\n").is_err());
+
+    let vocab = std::fs::read_to_string(VOCAB_PATH).unwrap();
+    let merges = "#version: 0.2\nz z\n";
+    assert!(Gpt2Tokenizer::try_from_str(&vocab, merges).is_err());
+}
+
+#[test]
+fn text_is_kept_when_the_pattern_fails() {
+    let mut tokenizer = tokenizer();
+    // The lookahead of the pattern backtracks, so the limit is exceeded on the whitespace.
+    tokenizer.pattern = RegexBuilder::new(PRE_TOKENIZATION_PATTERN)
+        .backtrack_limit(1)
+        .build()
+        .unwrap();
+    let text = "Mega Man   jumps";
+    assert!(tokenizer
+        .pattern
+        .find_iter(text)
+        .any(|found| found.is_err()));
+    let tokens = tokenizer.encode(text);
+    assert_eq!(tokenizer.decode(&tokens).unwrap(), text);
+}
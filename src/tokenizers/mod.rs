@@ -2,18 +2,111 @@ mod ascii_tokenizer;
 pub use ascii_tokenizer::*;
 mod byte_pair_encoding;
 pub use byte_pair_encoding::*;
+mod gpt2_tokenizer;
+pub use gpt2_tokenizer::*;
+mod streaming_decoder;
+pub use streaming_decoder::*;
+
+#[cfg(test)]
+mod tests;
 
 use crate::tensor::Error;
 
+/// Tokens that do not encode text.
+/// Every tokenizer reserves an id for each of them, see TokenizerTrait::special_token.
+/// Gpt2Tokenizer reserves one id, <|endoftext|>, for all of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SpecialToken {
+    /// Beginning of sequence
+    Bos,
+    /// End of sequence
+    Eos,
+    /// Padding
+    Pad,
+    /// Unknown
+    Unk,
+    /// Separator, for example between the rows of an ARC grid
+    Sep,
+}
+
+/// Number of special tokens.
+pub const SPECIAL_TOKENS: usize = 5;
+
+/// Number of tokens that encode a single byte.
+/// The token of a byte is its value.
+pub const BYTE_TOKENS: usize = 256;
+
+/// Unknown tokens are decoded as the replacement character U+FFFD.
+pub(crate) fn push_replacement_character(output: &mut Vec<u8>) {
+    let mut buffer = [0; 4];
+    output.extend_from_slice(
+        char::REPLACEMENT_CHARACTER
+            .encode_utf8(&mut buffer)
+            .as_bytes(),
+    );
+}
+
+impl SpecialToken {
+    pub const ALL: [SpecialToken; SPECIAL_TOKENS] = [
+        SpecialToken::Bos,
+        SpecialToken::Eos,
+        SpecialToken::Pad,
+        SpecialToken::Unk,
+        SpecialToken::Sep,
+    ];
+
+    /// Position of the token in SpecialToken::ALL.
+    pub fn index(&self) -> usize {
+        match self {
+            SpecialToken::Bos => 0,
+            SpecialToken::Eos => 1,
+            SpecialToken::Pad => 2,
+            SpecialToken::Unk => 3,
+            SpecialToken::Sep => 4,
+        }
+    }
+
+    /// Text of the token in vocabularies, with the GPT-2 end of text for Eos.
+    pub fn text(&self) -> &'static str {
+        match self {
+            SpecialToken::Bos => "<|startoftext|>",
+            SpecialToken::Eos => "<|endoftext|>",
+            SpecialToken::Pad => "<|pad|>",
+            SpecialToken::Unk => "<|unk|>",
+            SpecialToken::Sep => "<|sep|>",
+        }
+    }
+}
+
 pub trait TokenizerTrait {
     fn vocab_size(&self) -> usize;
-    fn encode(&mut self, text: &str) -> Vec<usize>;
-    fn decode(&self, tokens: &[usize]) -> Result<String, Error>;
+    fn encode(&self, text: &str) -> Vec<usize>;
+    /// Bytes of the tokens. Special tokens have no bytes and
+    /// unknown tokens are decoded as the replacement character U+FFFD.
+    fn decode_bytes(&self, tokens: &[usize]) -> Vec<u8>;
+    fn special_token(&self, special_token: SpecialToken) -> usize;
+
+    /// Decode the tokens into text.
+    /// Invalid UTF-8 sequences, for example the partial multibyte sequence of a token
+    /// that was cut during generation, are replaced by U+FFFD.
+    fn decode(&self, tokens: &[usize]) -> Result<String, Error> {
+        let bytes = self.decode_bytes(tokens);
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Special token of a token id, if any.
+    /// When special tokens share the id, the first one of SpecialToken::ALL.
+    fn as_special_token(&self, token: usize) -> Option<SpecialToken> {
+        SpecialToken::ALL
+            .into_iter()
+            .find(|special_token| self.special_token(*special_token) == token)
+    }
 }
 
 pub enum Tokenizer {
     BytePairEncoding(BytePairEncoding),
     AsciiTokenizer(AsciiTokenizer),
+    Gpt2Tokenizer(Box<Gpt2Tokenizer>),
 }
 
 impl TokenizerTrait for Tokenizer {
@@ -21,20 +114,31 @@ impl TokenizerTrait for Tokenizer {
         match self {
             Tokenizer::BytePairEncoding(object) => object.vocab_size(),
             Tokenizer::AsciiTokenizer(object) => object.vocab_size(),
+            Tokenizer::Gpt2Tokenizer(object) => object.vocab_size(),
         }
     }
 
-    fn encode(&mut self, text: &str) -> Vec<usize> {
+    fn encode(&self, text: &str) -> Vec<usize> {
         match self {
             Tokenizer::BytePairEncoding(object) => object.encode(text),
             Tokenizer::AsciiTokenizer(object) => object.encode(text),
+            Tokenizer::Gpt2Tokenizer(object) => object.encode(text),
         }
     }
 
-    fn decode(&self, tokens: &[usize]) -> Result<String, Error> {
+    fn decode_bytes(&self, tokens: &[usize]) -> Vec<u8> {
         match self {
-            Tokenizer::BytePairEncoding(object) => object.decode(tokens),
-            Tokenizer::AsciiTokenizer(object) => object.decode(tokens),
+            Tokenizer::BytePairEncoding(object) => object.decode_bytes(tokens),
+            Tokenizer::AsciiTokenizer(object) => object.decode_bytes(tokens),
+            Tokenizer::Gpt2Tokenizer(object) => object.decode_bytes(tokens),
+        }
+    }
+
+    fn special_token(&self, special_token: SpecialToken) -> usize {
+        match self {
+            Tokenizer::BytePairEncoding(object) => object.special_token(special_token),
+            Tokenizer::AsciiTokenizer(object) => object.special_token(special_token),
+            Tokenizer::Gpt2Tokenizer(object) => object.special_token(special_token),
         }
     }
 }
@@ -46,4 +150,8 @@ impl Tokenizer {
     pub fn ascii_tokenizer() -> Tokenizer {
         Tokenizer::AsciiTokenizer(AsciiTokenizer::default())
     }
+    pub fn gpt2_tokenizer(vocab_path: &str, merges_path: &str) -> Result<Tokenizer, Error> {
+        let tokenizer = Gpt2Tokenizer::try_new(vocab_path, merges_path)?;
+        Ok(Tokenizer::Gpt2Tokenizer(Box::new(tokenizer)))
+    }
 }
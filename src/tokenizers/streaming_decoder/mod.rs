@@ -0,0 +1,55 @@
+use std::str;
+
+use crate::TokenizerTrait;
+
+/// Decodes the tokens of a generation as they are produced.
+/// A token can end in the middle of a multibyte UTF-8 sequence. The bytes of an incomplete
+/// sequence are kept until the next tokens complete it. Invalid sequences are replaced by U+FFFD.
+#[derive(Default)]
+pub struct StreamingDecoder {
+    pending_bytes: Vec<u8>,
+}
+
+impl StreamingDecoder {
+    /// Returns the text that is complete after the new tokens.
+    pub fn decode(&mut self, tokenizer: &impl TokenizerTrait, tokens: &[usize]) -> String {
+        self.pending_bytes
+            .extend_from_slice(&tokenizer.decode_bytes(tokens));
+        let mut text = String::new();
+        let mut start = 0;
+        while start < self.pending_bytes.len() {
+            let bytes = &self.pending_bytes[start..];
+            match str::from_utf8(bytes) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    start = self.pending_bytes.len();
+                }
+                Err(error) => {
+                    let valid_up_to = error.valid_up_to();
+                    text.push_str(&String::from_utf8_lossy(&bytes[..valid_up_to]));
+                    match error.error_len() {
+                        Some(error_len) => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            start += valid_up_to + error_len;
+                        }
+                        // The sequence at the end is incomplete.
+                        None => {
+                            start += valid_up_to;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.pending_bytes.drain(..start);
+        text
+    }
+
+    /// Returns the bytes of an incomplete sequence at the end of the generation, if any,
+    /// as U+FFFD.
+    pub fn finish(&mut self) -> String {
+        let text = String::from_utf8_lossy(&self.pending_bytes).into_owned();
+        self.pending_bytes.clear();
+        text
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use crate::{SpecialToken, StreamingDecoder, Tokenizer, TokenizerTrait};
+
+fn tokenizers() -> Vec<Tokenizer> {
+    let mut byte_pair_encoding = Tokenizer::byte_pair_encoding();
+    if let Tokenizer::BytePairEncoding(object) = &mut byte_pair_encoding {
+        object
+            .train("the cat sat on the mat with the hat", 270)
+            .unwrap();
+    }
+    let gpt2_tokenizer = Tokenizer::gpt2_tokenizer(
+        "data/gpt2_tokenizer/vocab.json",
+        "data/gpt2_tokenizer/merges.txt",
+    )
+    .unwrap();
+    vec![
+        Tokenizer::ascii_tokenizer(),
+        byte_pair_encoding,
+        gpt2_tokenizer,
+    ]
+}
+
+#[test]
+fn special_tokens_are_reserved() {
+    let text = "the cat <|endoftext|> <|pad|>";
+    for tokenizer in tokenizers() {
+        let special_tokens: HashSet<usize> = SpecialToken::ALL
+            .iter()
+            .map(|special_token| tokenizer.special_token(*special_token))
+            .collect();
+        assert!(special_tokens
+            .iter()
+            .all(|token| *token < tokenizer.vocab_size()));
+        match tokenizer {
+            // Like in GPT-2, every special token is <|endoftext|>.
+            Tokenizer::Gpt2Tokenizer(_) => assert_eq!(special_tokens.len(), 1),
+            _ => {
+                assert_eq!(special_tokens.len(), SpecialToken::ALL.len());
+                for special_token in SpecialToken::ALL {
+                    let token = tokenizer.special_token(special_token);
+                    assert_eq!(tokenizer.as_special_token(token), Some(special_token));
+                }
+            }
+        }
+
+        // Special tokens are not produced by text and have no text.
+        let tokens = tokenizer.encode(text);
+        assert!(tokens.iter().all(|token| !special_tokens.contains(token)));
+        let bos = tokenizer.special_token(SpecialToken::Bos);
+        let pad = tokenizer.special_token(SpecialToken::Pad);
+        let tokens = [vec![bos], tokens, vec![pad, pad]].concat();
+        assert_eq!(tokenizer.decode(&tokens).unwrap(), text);
+    }
+}
+
+#[test]
+fn decoding_is_lossy() {
+    for tokenizer in tokenizers() {
+        let tokens = tokenizer.encode("東");
+        let partial = &tokens[..tokens.len() - 1];
+        assert_eq!(tokenizer.decode(partial).unwrap(), "\u{FFFD}");
+        let unknown = tokenizer.vocab_size();
+        let tokens = [tokenizer.encode("a"), vec![unknown]].concat();
+        assert_eq!(tokenizer.decode(&tokens).unwrap(), "a\u{FFFD}");
+    }
+}
+
+#[test]
+fn streaming_decoding_waits_for_complete_characters() {
+    let text = "café 東京 🤖!";
+    for tokenizer in tokenizers() {
+        let mut decoder = StreamingDecoder::default();
+        let mut decoded = String::new();
+        for token in tokenizer.encode(text) {
+            let new_text = decoder.decode(&tokenizer, &[token]);
+            assert!(!new_text.contains('\u{FFFD}'));
+            decoded.push_str(&new_text);
+        }
+        decoded.push_str(&decoder.finish());
+        assert_eq!(decoded, text);
+    }
+
+    let tokenizer = Tokenizer::ascii_tokenizer();
+    let mut decoder = StreamingDecoder::default();
+    let tokens = tokenizer.encode("東");
+    assert_eq!(decoder.decode(&tokenizer, &tokens[..1]), "");
+    assert_eq!(decoder.decode(&tokenizer, &tokens[1..2]), "");
+    assert_eq!(decoder.finish(), "\u{FFFD}");
+    // An invalid byte followed by an incomplete sequence.
+    assert_eq!(decoder.decode(&tokenizer, &[0xFF, 0xE6]), "\u{FFFD}");
+    assert_eq!(decoder.decode(&tokenizer, &[0x9D, 0xB1]), "東");
+}
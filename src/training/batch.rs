@@ -1,14 +1,15 @@
 use rand::prelude::SliceRandom;
-use rand::thread_rng;
+use rand::Rng;
 
 pub fn make_batches(
     indices: &[usize],
     shuffle_examples: bool,
     batch_size: usize,
+    rng: &mut impl Rng,
 ) -> Vec<Vec<usize>> {
     let mut indices = indices.to_owned();
     if shuffle_examples {
-        indices.shuffle(&mut thread_rng());
+        indices.shuffle(rng);
     }
 
     let mut batches: Vec<Vec<_>> = vec![];
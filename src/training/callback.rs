@@ -0,0 +1,70 @@
+use crate::{
+    schedulers::DefaultStreamScheduler, tensor::Error, MetricsRecord, NeuralMachine,
+    NeuralMachineTestOutput,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TrainingFlow {
+    Continue,
+    Stop,
+}
+
+/// Hooks called by train_model.
+/// Every callback is called for a hook. If any of them returns TrainingFlow::Stop,
+/// the training ends after the hook.
+/// The evaluations after the training also call on_evaluation, but they can not stop it.
+pub trait TrainingCallback {
+    fn on_epoch_start(
+        &mut self,
+        _neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        _epoch: usize,
+    ) -> Result<TrainingFlow, Error> {
+        Ok(TrainingFlow::Continue)
+    }
+
+    /// Called after each optimization step with the metrics of the step.
+    fn on_batch_end(
+        &mut self,
+        _neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        _record: &MetricsRecord,
+    ) -> Result<TrainingFlow, Error> {
+        Ok(TrainingFlow::Continue)
+    }
+
+    /// Called after each evaluation of the training examples or of the validation examples.
+    fn on_evaluation(
+        &mut self,
+        _neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        _record: &MetricsRecord,
+    ) -> Result<TrainingFlow, Error> {
+        Ok(TrainingFlow::Continue)
+    }
+
+    fn on_train_end(
+        &mut self,
+        _neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        _output: &NeuralMachineTestOutput,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Stops the training when a step loss is NaN or infinite.
+#[derive(Default)]
+pub struct StopOnNonFiniteLoss {}
+
+impl TrainingCallback for StopOnNonFiniteLoss {
+    fn on_batch_end(
+        &mut self,
+        _neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        record: &MetricsRecord,
+    ) -> Result<TrainingFlow, Error> {
+        match record.loss {
+            Some(loss) if !loss.is_finite() => {
+                println!("Stopping at step {}, loss is {}", record.step, loss);
+                Ok(TrainingFlow::Stop)
+            }
+            _ => Ok(TrainingFlow::Continue),
+        }
+    }
+}
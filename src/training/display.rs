@@ -1,5 +1,5 @@
 use crate::{
-    get_row_argmaxes,
+    get_expected_row_tokens, get_row_argmaxes, get_row_tokens,
     perplexity::get_perplexity,
     tensor::{Error, Tensor},
     Tokenizer, TokenizerTrait,
@@ -32,16 +32,16 @@ impl TensorPrinter for NextTokenPredictionPrinter {
         actual_output: &Tensor,
     ) -> Result<(), Error> {
         let last_row = expected_output.rows() - 1;
-        let input_tokens = get_row_argmaxes(input)?;
-        let expected_output_argmaxes = get_row_argmaxes(expected_output)?;
-        let expected_output_token = expected_output_argmaxes[last_row].to_owned();
+        let input_tokens = get_row_tokens(input)?;
+        let expected_output_tokens = get_expected_row_tokens(expected_output, actual_output)?;
+        let expected_output_token = expected_output_tokens[last_row].to_owned();
 
         let actual_output_argmaxes = get_row_argmaxes(actual_output)?;
         let actual_output_token = actual_output_argmaxes[last_row].to_owned();
 
         let next_token_perplexity = get_perplexity(actual_output, actual_output.rows() - 1)?;
 
-        let tokenizer: &mut Tokenizer = &mut self.tokenizer;
+        let tokenizer: &Tokenizer = &self.tokenizer;
         println!(
             "  input_text: {}",
             tokens_to_text(&input_tokens, tokenizer)?
@@ -78,7 +78,7 @@ impl TensorPrinter for NextTokenPredictionPrinter {
     }
 }
 
-fn tokens_to_text(input_tokens: &[usize], tokenizer: &mut Tokenizer) -> Result<String, Error> {
+fn tokens_to_text(input_tokens: &[usize], tokenizer: &Tokenizer) -> Result<String, Error> {
     let input_text = tokenizer.decode(input_tokens)?;
     Ok(input_text)
 }
@@ -152,8 +152,8 @@ impl TensorPrinter for BoardPrinter {
         expected_output: &Tensor,
         actual_output: &Tensor,
     ) -> Result<(), Error> {
-        let input_tokens = get_row_argmaxes(input)?;
-        let expected_output_tokens = get_row_argmaxes(expected_output)?;
+        let input_tokens = get_row_tokens(input)?;
+        let expected_output_tokens = get_expected_row_tokens(expected_output, actual_output)?;
         let actual_output_tokens = get_row_argmaxes(actual_output)?;
 
         let width = 7;
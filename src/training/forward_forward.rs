@@ -0,0 +1,358 @@
+use std::collections::HashSet;
+
+use rand::Rng;
+
+use crate::{
+    error, get_row_argmax, instruction,
+    neural_program::NeuralProgram,
+    new_tensor, new_tensor_with_grad,
+    opcode::OpCode,
+    schedulers::DefaultStreamScheduler,
+    tensor::{Error, ErrorEnum, Tensor},
+    BinaryOperator, Category, Device, GoodnessLoss, NeuralMachine, OperatorAttributes,
+    OptimizerTrait, Sigmoid, TensorWithGrad, UnaryOperator,
+};
+
+/// A model that can be trained layer by layer with the forward-forward algorithm.
+pub trait ForwardForwardModel {
+    /// Layers of the model, in order, with their input size.
+    /// The output size of a layer is the input size of the next layer.
+    fn layers(&self) -> Vec<(&dyn UnaryOperator, Vec<usize>)>;
+}
+
+/// Trains each layer with a local goodness objective.
+/// Each layer is compiled to its own neural machine, and gradients never flow
+/// from a layer to the previous layer.
+/// The input of a layer is the length-normalized output of the previous layer.
+///
+/// See:
+/// The Forward-Forward Algorithm: Some Preliminary Investigations
+/// https://arxiv.org/abs/2212.13345
+pub struct ForwardForwardTrainer {
+    layers: Vec<NeuralMachine<f32, DefaultStreamScheduler>>,
+    layer_inputs: Vec<TensorWithGrad>,
+    positive_label: TensorWithGrad,
+    negative_label: TensorWithGrad,
+}
+
+impl ForwardForwardTrainer {
+    pub fn try_new(
+        device: &Device,
+        model: &impl ForwardForwardModel,
+        optimizer: &impl OptimizerTrait,
+        threshold: f32,
+    ) -> Result<Self, Error> {
+        let maximum_device_streams = 16;
+        let mut layers = vec![];
+        let mut layer_inputs = vec![];
+        for (layer, input_size) in model.layers().into_iter() {
+            let program = compile_layer(device, layer, &input_size, optimizer, threshold)?;
+            let neural_machine = NeuralMachine::<f32, DefaultStreamScheduler>::try_new(
+                device,
+                program,
+                maximum_device_streams,
+            )?;
+            layers.push(neural_machine);
+            let len = input_size[0] * input_size[1];
+            layer_inputs.push(new_tensor_with_grad!(
+                device,
+                input_size[0],
+                input_size[1],
+                vec![0.0; len],
+                &[],
+                false,
+                false,
+            )?);
+        }
+        let trainer = Self {
+            layers,
+            layer_inputs,
+            positive_label: new_tensor_with_grad!(device, 1, 1, vec![1.0], &[], false, false)?,
+            negative_label: new_tensor_with_grad!(device, 1, 1, vec![0.0], &[], false, false)?,
+        };
+        Ok(trainer)
+    }
+
+    /// Update every layer with a positive example and a negative example.
+    /// Returns the total loss of the layers.
+    pub fn train(
+        &mut self,
+        positive_input: &TensorWithGrad,
+        negative_input: &TensorWithGrad,
+    ) -> Result<f32, Error> {
+        let positive_label = self.positive_label.clone();
+        let negative_label = self.negative_label.clone();
+        let positive_loss = self.train_layers(positive_input, &positive_label)?;
+        let negative_loss = self.train_layers(negative_input, &negative_label)?;
+        Ok(positive_loss + negative_loss)
+    }
+
+    /// Returns the sum of the goodness of all the layers.
+    pub fn goodness(&mut self, input: &TensorWithGrad) -> Result<f32, Error> {
+        let mut total_goodness = 0.0;
+        let mut layer_input = input.clone();
+        for index in 0..self.layers.len() {
+            let activations = self.layers[index].infer(&layer_input)?;
+            let activations = activations.tensor().get_values()?;
+            total_goodness += activations.iter().map(|x| x * x).sum::<f32>();
+            layer_input = self.next_layer_input(index, &activations)?;
+        }
+        Ok(total_goodness)
+    }
+
+    /// Returns the label with the highest goodness.
+    pub fn predict(
+        &mut self,
+        device: &Device,
+        input: &TensorWithGrad,
+        num_classes: usize,
+    ) -> Result<usize, Error> {
+        let mut best_label = 0;
+        let mut best_goodness = f32::NEG_INFINITY;
+        for label in 0..num_classes {
+            let labeled_input = overlay_label(device, input, label, num_classes)?;
+            let goodness = self.goodness(&labeled_input)?;
+            if goodness > best_goodness {
+                best_goodness = goodness;
+                best_label = label;
+            }
+        }
+        Ok(best_label)
+    }
+
+    fn train_layers(
+        &mut self,
+        input: &TensorWithGrad,
+        label: &TensorWithGrad,
+    ) -> Result<f32, Error> {
+        let mut total_loss = 0.0;
+        let mut layer_input = input.clone();
+        for index in 0..self.layers.len() {
+            let neural_machine = &mut self.layers[index];
+            let activations = neural_machine.infer(&layer_input)?;
+            let activations = activations.tensor().get_values()?;
+            let loss = neural_machine.loss(label)?;
+            let loss: &Tensor = &loss.tensor();
+            let loss: f32 = loss.try_into()?;
+            total_loss += loss;
+            neural_machine.compute_gradient()?;
+            neural_machine.optimize()?;
+            layer_input = self.next_layer_input(index, &activations)?;
+        }
+        Ok(total_loss)
+    }
+
+    fn next_layer_input(&self, index: usize, activations: &[f32]) -> Result<TensorWithGrad, Error> {
+        match self.layer_inputs.get(index + 1) {
+            Some(next_layer_input) => {
+                let norm = activations.iter().map(|x| x * x).sum::<f32>().sqrt();
+                let alpha = if norm == 0.0 { 0.0 } else { 1.0 / norm };
+                let values = activations.iter().map(|x| alpha * x).collect();
+                next_layer_input.tensor().set_values(values)?;
+                Ok(next_layer_input.clone())
+            }
+            None => Ok(self.layer_inputs[index].clone()),
+        }
+    }
+}
+
+pub struct ForwardForwardDetails<Model: ForwardForwardModel, Optimizer: OptimizerTrait> {
+    pub device: Device,
+    pub train_examples: Vec<(TensorWithGrad, TensorWithGrad)>,
+    pub test_examples: Vec<(TensorWithGrad, TensorWithGrad)>,
+    pub model: Model,
+    pub optimizer: Optimizer,
+    /// A layer is positive when the sum of its squared activations is above the threshold.
+    pub threshold: f32,
+    pub epochs: usize,
+    pub progress: usize,
+}
+
+pub struct ForwardForwardTestOutput {
+    pub initial_accuracy: f32,
+    pub final_accuracy: f32,
+}
+
+/// Train with positive and negative examples made from the train examples.
+/// The accuracy is the ratio of test examples whose label has the highest goodness.
+pub fn train_forward_forward(
+    details: ForwardForwardDetails<impl ForwardForwardModel, impl OptimizerTrait>,
+) -> Result<ForwardForwardTestOutput, Error> {
+    let device = &details.device;
+    let mut trainer = ForwardForwardTrainer::try_new(
+        device,
+        &details.model,
+        &details.optimizer,
+        details.threshold,
+    )?;
+    let initial_accuracy = forward_forward_accuracy(device, &mut trainer, &details.test_examples)?;
+    println!("Initial accuracy {}", initial_accuracy);
+
+    for epoch in 0..details.epochs {
+        let examples = make_positive_and_negative_examples(device, &details.train_examples)?;
+        let mut total_loss = 0.0;
+        for (positive_input, negative_input) in examples.iter() {
+            total_loss += trainer.train(positive_input, negative_input)?;
+        }
+        if epoch % details.progress == 0 {
+            println!("Epoch {} total_loss {}", epoch, total_loss);
+        }
+    }
+
+    let final_accuracy = forward_forward_accuracy(device, &mut trainer, &details.test_examples)?;
+    println!("Final accuracy {}", final_accuracy);
+    let output = ForwardForwardTestOutput {
+        initial_accuracy,
+        final_accuracy,
+    };
+    Ok(output)
+}
+
+fn forward_forward_accuracy(
+    device: &Device,
+    trainer: &mut ForwardForwardTrainer,
+    examples: &[(TensorWithGrad, TensorWithGrad)],
+) -> Result<f32, Error> {
+    if examples.is_empty() {
+        return Ok(0.0);
+    }
+    let mut correct_predictions = 0;
+    for (input, expected_output) in examples.iter() {
+        let expected_output: &Tensor = &expected_output.tensor();
+        let label = get_row_argmax(expected_output, 0)?;
+        let predicted_label = trainer.predict(device, input, expected_output.cols())?;
+        if predicted_label == label {
+            correct_predictions += 1;
+        }
+    }
+    Ok(correct_predictions as f32 / examples.len() as f32)
+}
+
+/// Compile the local program of one layer.
+/// The program updates only the parameters of the layer.
+fn compile_layer(
+    device: &Device,
+    layer: &dyn UnaryOperator,
+    input_size: &[usize],
+    optimizer: &impl OptimizerTrait,
+    threshold: f32,
+) -> Result<NeuralProgram, Error> {
+    let zero = new_tensor!(device, 1, 1, vec![0.0])?;
+    let input_len = input_size[0] * input_size[1];
+    let example_input = new_tensor_with_grad!(
+        device,
+        input_size[0],
+        input_size[1],
+        vec![0.0; input_len],
+        &[],
+        false,
+        false,
+    )?;
+    let example_output = new_tensor_with_grad!(device, 1, 1, vec![0.0], &[], false, false)?;
+    let sigmoid = Sigmoid::new(device);
+    let goodness_loss = GoodnessLoss::new(device, threshold);
+
+    let product = layer.forward(&example_input)?;
+    let machine_output = sigmoid.forward(&product)?;
+    let loss = goodness_loss.forward(&example_output, &machine_output)?;
+    let tape = loss.get_tape();
+
+    let mut instructions = vec![];
+
+    for tensor in tape.iter() {
+        instructions.extend(tensor.forward_instructions());
+    }
+
+    // Only the gradients of this layer are zeroed.
+    for tensor in tape.iter() {
+        let gradient: &Tensor = &tensor.gradient();
+        if gradient.requires_grad() {
+            instructions.push(instruction!(
+                OpCode::ScalarMul,
+                OperatorAttributes::None,
+                &[&zero, gradient],
+                &[gradient],
+                Category::Gradient,
+            ));
+        }
+    }
+
+    for tensor in loss.get_backward_tape().iter() {
+        instructions.extend(tensor.gradient_instructions());
+    }
+
+    let mut tensor_names = HashSet::new();
+    for instruction in instructions.iter() {
+        for tensor in instruction.inputs().iter() {
+            tensor_names.insert(tensor.name());
+        }
+    }
+    let parameter_tensors: Vec<TensorWithGrad> = device
+        .parameter_tensors()
+        .iter()
+        .filter(|parameter| tensor_names.contains(&parameter.tensor().name()))
+        .cloned()
+        .collect();
+    if parameter_tensors.is_empty() {
+        return Err(error!(ErrorEnum::IncorrectOperatorConfiguration));
+    }
+    instructions.extend(optimizer.optimize(device, &parameter_tensors)?);
+    for tensor in parameter_tensors.iter() {
+        instructions.push(instruction!(
+            OpCode::ScalarMul,
+            OperatorAttributes::None,
+            &[&zero, &tensor.gradient()],
+            &[&tensor.gradient()],
+            Category::Optimization,
+        ));
+    }
+
+    let program = NeuralProgram {
+        example_input,
+        example_output,
+        machine_output,
+        loss,
+        gradient_norm: None,
+        auxiliary_losses: vec![],
+        instructions,
+    };
+    Ok(program)
+}
+
+/// Flatten the input and append the one-hot encoded label.
+pub fn overlay_label(
+    device: &Device,
+    input: &TensorWithGrad,
+    label: usize,
+    num_classes: usize,
+) -> Result<TensorWithGrad, Error> {
+    let mut values = input.tensor().get_values()?;
+    let mut one_hot = vec![0.0; num_classes];
+    one_hot[label] = 1.0;
+    values.extend_from_slice(&one_hot);
+    let len = values.len();
+    new_tensor_with_grad!(device, 1, len, values, &[], false, false)
+}
+
+/// For each example, make a positive input with the expected label and
+/// a negative input with a random incorrect label.
+/// The expected label is the argmax of the first row of the expected output.
+pub fn make_positive_and_negative_examples(
+    device: &Device,
+    examples: &[(TensorWithGrad, TensorWithGrad)],
+) -> Result<Vec<(TensorWithGrad, TensorWithGrad)>, Error> {
+    let mut positive_and_negative_examples = vec![];
+    for (input, expected_output) in examples.iter() {
+        let expected_output: &Tensor = &expected_output.tensor();
+        let num_classes = expected_output.cols();
+        if num_classes < 2 {
+            return Err(error!(ErrorEnum::IncompatibleTensorShapes));
+        }
+        let label = get_row_argmax(expected_output, 0)?;
+        let incorrect_label = (label + device.rng().gen_range(1..num_classes)) % num_classes;
+        let positive_input = overlay_label(device, input, label, num_classes)?;
+        let negative_input = overlay_label(device, input, incorrect_label, num_classes)?;
+        positive_and_negative_examples.push((positive_input, negative_input));
+    }
+    Ok(positive_and_negative_examples)
+}
@@ -0,0 +1,170 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use serde::Serialize;
+
+use crate::{
+    error,
+    tensor::{Error, ErrorEnum},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsScope {
+    /// One optimization step, i.e. one batch.
+    Step,
+    /// One evaluation of all the training examples.
+    Epoch,
+    /// One evaluation of all the validation examples.
+    Validation,
+}
+
+impl From<&MetricsScope> for String {
+    fn from(value: &MetricsScope) -> String {
+        match value {
+            MetricsScope::Step => "step".into(),
+            MetricsScope::Epoch => "epoch".into(),
+            MetricsScope::Validation => "validation".into(),
+        }
+    }
+}
+
+/// Values recorded during training.
+/// Values that are not measured for a scope are None.
+#[derive(Clone, Debug, Serialize)]
+pub struct MetricsRecord {
+    pub scope: MetricsScope,
+    pub epoch: usize,
+    pub step: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loss: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_token_perplexity: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_token_accuracy: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gradient_norm: Option<f32>,
+    /// See NeuralMachine::auxiliary_loss.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auxiliary_loss: Option<f32>,
+    /// Learning rate of the optimizer.
+    /// Parameter groups with their own learning rate are not recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub learning_rate: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub step_time_micros: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_memory_used: Option<usize>,
+}
+
+impl MetricsRecord {
+    pub fn new(scope: MetricsScope, epoch: usize, step: usize) -> Self {
+        Self {
+            scope,
+            epoch,
+            step,
+            loss: None,
+            next_token_perplexity: None,
+            next_token_accuracy: None,
+            gradient_norm: None,
+            auxiliary_loss: None,
+            learning_rate: None,
+            step_time_micros: None,
+            device_memory_used: None,
+        }
+    }
+}
+
+pub trait MetricsSink {
+    fn record(&mut self, record: &MetricsRecord) -> Result<(), Error>;
+    fn flush(&mut self) -> Result<(), Error>;
+}
+
+fn create_file(path: &str) -> Result<BufWriter<File>, Error> {
+    let file = File::create(path).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+    Ok(BufWriter::new(file))
+}
+
+/// Writes one comma-separated line per record.
+/// Values that are None are written as empty cells.
+pub struct CsvMetricsSink {
+    writer: BufWriter<File>,
+}
+
+impl CsvMetricsSink {
+    pub fn try_new(path: &str) -> Result<Self, Error> {
+        let mut writer = create_file(path)?;
+        writeln!(
+            writer,
+            "scope,epoch,step,loss,next_token_perplexity,next_token_accuracy,gradient_norm,auxiliary_loss,learning_rate,step_time_micros,device_memory_used"
+        )
+        .map_err(|_| error!(ErrorEnum::InputOutputError))?;
+        Ok(Self { writer })
+    }
+}
+
+fn csv_cell<T: ToString>(value: &Option<T>) -> String {
+    match value {
+        Some(value) => value.to_string(),
+        None => "".into(),
+    }
+}
+
+impl MetricsSink for CsvMetricsSink {
+    fn record(&mut self, record: &MetricsRecord) -> Result<(), Error> {
+        let scope: String = (&record.scope).into();
+        writeln!(
+            self.writer,
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            scope,
+            record.epoch,
+            record.step,
+            csv_cell(&record.loss),
+            csv_cell(&record.next_token_perplexity),
+            csv_cell(&record.next_token_accuracy),
+            csv_cell(&record.gradient_norm),
+            csv_cell(&record.auxiliary_loss),
+            csv_cell(&record.learning_rate),
+            csv_cell(&record.step_time_micros),
+            csv_cell(&record.device_memory_used),
+        )
+        .map_err(|_| error!(ErrorEnum::InputOutputError))
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.writer
+            .flush()
+            .map_err(|_| error!(ErrorEnum::InputOutputError))
+    }
+}
+
+/// Writes one JSON object per line.
+/// See:
+/// JSON Lines
+/// https://jsonlines.org/
+pub struct JsonLinesMetricsSink {
+    writer: BufWriter<File>,
+}
+
+impl JsonLinesMetricsSink {
+    pub fn try_new(path: &str) -> Result<Self, Error> {
+        let writer = create_file(path)?;
+        Ok(Self { writer })
+    }
+}
+
+impl MetricsSink for JsonLinesMetricsSink {
+    fn record(&mut self, record: &MetricsRecord) -> Result<(), Error> {
+        let line =
+            serde_json::to_string(record).map_err(|_| error!(ErrorEnum::InputOutputError))?;
+        writeln!(self.writer, "{}", line).map_err(|_| error!(ErrorEnum::InputOutputError))
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.writer
+            .flush()
+            .map_err(|_| error!(ErrorEnum::InputOutputError))
+    }
+}
@@ -2,6 +2,12 @@
 pub mod tests;
 mod train;
 pub use train::*;
+mod metrics_sink;
+pub use metrics_sink::*;
+mod callback;
+pub use callback::*;
+mod forward_forward;
+pub use forward_forward::*;
 mod tensor_with_grad;
 pub use tensor_with_grad::*;
 pub mod batch;
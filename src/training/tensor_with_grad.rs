@@ -4,7 +4,7 @@ use crate::{tensor::Error, tensor::Tensor, Category, Instruction};
 use core::fmt::Debug;
 use std::fmt::Display;
 use std::sync::{Arc, RwLock};
-use std::{collections::LinkedList, ops::Deref};
+use std::{collections::HashSet, ops::Deref};
 
 #[derive(Clone, Debug)]
 pub struct TensorWithGrad {
@@ -12,6 +12,7 @@ pub struct TensorWithGrad {
     instructions: Arc<RwLock<Vec<Instruction>>>,
     tensor: Arc<RwLock<Tensor>>,
     gradient: Arc<RwLock<Tensor>>,
+    tags: Arc<RwLock<Vec<String>>>,
 }
 
 impl TensorWithGrad {
@@ -22,9 +23,23 @@ impl TensorWithGrad {
             instructions: Default::default(),
             tensor: Arc::new(RwLock::new(tensor)),
             gradient: Arc::new(RwLock::new(gradient)),
+            tags: Default::default(),
         }
     }
 
+    /// Tags are used to select parameters, for example in a ParameterGroup.
+    pub fn add_tag(&self, tag: &str) {
+        self.tags.write().unwrap().push(tag.to_owned())
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.read().unwrap().iter().any(|x| x == tag)
+    }
+
+    pub fn tags(&self) -> Vec<String> {
+        self.tags.read().unwrap().clone()
+    }
+
     pub fn push_instruction(&self, instruction: Instruction) {
         self.instructions.write().unwrap().push(instruction)
     }
@@ -57,25 +72,35 @@ impl TensorWithGrad {
         self.gradient.read().unwrap()
     }
 
+    /// Tensors that have forward instructions, each one after its inputs.
+    /// Each tensor is in the tape once, so that shared inputs,
+    /// like the hidden states of an unrolled recurrent layer, are not visited again.
     pub fn get_tape(&self) -> Vec<TensorWithGrad> {
         let mut tape = vec![];
-        let mut stack = LinkedList::new();
-        stack.push_back(self.clone());
-        while let Some(element) = stack.pop_back() {
+        let mut visited = HashSet::new();
+        let mut stack = vec![(self.clone(), false)];
+        while let Some((element, expanded)) = stack.pop() {
+            if expanded {
+                tape.push(element);
+                continue;
+            }
+            if element.forward_instructions().is_empty() || !visited.insert(element.tensor().name())
             {
-                let forward_instructions: Vec<Instruction> = element.forward_instructions();
-                if forward_instructions.is_empty() {
-                    continue;
-                }
-                let inputs = element.inputs.deref();
-                for input in inputs.deref().iter() {
-                    stack.push_back(input.clone());
-                }
+                continue;
+            }
+            stack.push((element.clone(), true));
+            for input in element.inputs.iter().rev() {
+                stack.push((input.clone(), false));
             }
-
-            tape.push(element);
         }
-        tape.into_iter().rev().collect()
+        tape
+    }
+
+    /// Tensors that have forward instructions, in the order in which
+    /// their gradient instructions are executed: the reverse of get_tape,
+    /// so that the gradient of a tensor is complete before it is propagated to its inputs.
+    pub fn get_backward_tape(&self) -> Vec<TensorWithGrad> {
+        self.get_tape().into_iter().rev().collect()
     }
 
     pub fn forward(&self, device: &Device, device_stream: &DeviceStream) -> Result<(), Error> {
@@ -1,5 +1,8 @@
+use std::{cell::RefCell, fs, rc::Rc, time::Duration};
+
 use more_asserts::assert_ge;
 use more_asserts::assert_le;
+use more_asserts::assert_lt;
 
 use crate::datasets::addition_perceptron::load_addition_perceptron;
 use crate::datasets::geoffroy_hinton_transformer::load_geoffroy_hinton_transformer;
@@ -8,12 +11,52 @@ use crate::datasets::mega_man_linear::load_mega_man_linear;
 use crate::datasets::mega_man_multi_head_attention::load_mega_man_multi_head_attention;
 use crate::datasets::simple::load_simple;
 use crate::datasets::DatasetDetails;
+use crate::datasets::{into_one_hot_encoded_rows, into_token_id_rows};
 use crate::display::TensorPrinter;
+use crate::evaluate;
+use crate::get_row_argmaxes;
+use crate::multi_layer_perceptron::MultiLayerPerceptronModel;
+use crate::neural_program::NeuralProgram;
+use crate::schedulers::DefaultStreamScheduler;
+use crate::stream::StreamTrait;
+use crate::tensor::Error;
+use crate::tensor::Tensor;
+use crate::train_forward_forward;
 use crate::train_model;
+use crate::train_on_batch;
+use crate::transformer::TransformerConfig;
+use crate::transformer_model::TransformerModel;
 use crate::BinaryOperator;
+use crate::CsvMetricsSink;
 use crate::Device;
+use crate::Embedding;
+use crate::FeedForward;
+use crate::ForwardForwardDetails;
+use crate::GradientDescent;
+use crate::JsonLinesMetricsSink;
+use crate::Linear;
+use crate::MetricsRecord;
+use crate::MetricsScope;
+use crate::MetricsSink;
+use crate::Model;
+use crate::NeuralMachine;
+use crate::NeuralMachineTestOutput;
+use crate::OnlineLearning;
 use crate::OptimizerTrait;
+use crate::Softmax;
+use crate::SparseCrossEntropyAttributes;
+use crate::SparseEmbedding;
+use crate::SparseSoftmaxCrossEntropyLoss;
+use crate::SpecialToken;
+use crate::Tokenizer;
+use crate::TokenizerTrait;
+use crate::TrainingCallback;
+use crate::TrainingFlow;
 use crate::UnaryModel;
+use crate::UnaryOperator;
+use crate::WeightsInitialization;
+use crate::{new_tensor_with_grad, TensorWithGrad};
+use crate::{Add, Sigmoid};
 
 fn test_model(
     details: DatasetDetails<
@@ -29,7 +72,7 @@ fn test_model(
         details.initial_metrics_min.total_next_token_perplexity;
     let expected_final_total_perplexity_max = details.final_metrics_max.total_next_token_perplexity;
     let maximum_incorrect_argmaxes = details.maximum_incorrect_predicted_next_tokens;
-    let training_output = train_model::<f32>(details).unwrap();
+    let training_output = train_model(details).unwrap();
 
     // Verify total loss
     assert_le!(
@@ -72,6 +115,24 @@ fn addition_perceptron() {
     test_model(details);
 }
 
+#[test]
+fn seeded_training_is_reproducible() {
+    let train = |seed| {
+        let device = Device::default();
+        device.set_seed(seed);
+        let mut details = load_simple(&device).unwrap();
+        details.epochs = 20;
+        let output = train_model(details).unwrap();
+        let parameters: Vec<Vec<f32>> = device
+            .parameter_tensors()
+            .iter()
+            .map(|parameter| parameter.tensor().get_values().unwrap())
+            .collect();
+        (output.final_metrics.total_loss, parameters)
+    };
+    assert_eq!(train(3), train(3));
+}
+
 #[test]
 fn simple() {
     let device = Device::default();
@@ -106,3 +167,592 @@ fn geoffroy_hinton_transformer() {
     let details = load_geoffroy_hinton_transformer(&device).unwrap();
     test_model(details);
 }
+
+#[test]
+fn metrics_sinks() {
+    let directory = std::env::temp_dir();
+    let csv_path = directory.join("novigrad_metrics_sinks.csv");
+    let json_lines_path = directory.join("novigrad_metrics_sinks.jsonl");
+    let csv_path = csv_path.to_str().unwrap();
+    let json_lines_path = json_lines_path.to_str().unwrap();
+
+    let mut record = MetricsRecord::new(MetricsScope::Step, 1, 7);
+    record.loss = Some(0.5);
+    record.learning_rate = Some(0.25);
+
+    let mut sinks: Vec<Box<dyn MetricsSink>> = vec![
+        Box::new(CsvMetricsSink::try_new(csv_path).unwrap()),
+        Box::new(JsonLinesMetricsSink::try_new(json_lines_path).unwrap()),
+    ];
+    for sink in sinks.iter_mut() {
+        sink.record(&record).unwrap();
+        sink.flush().unwrap();
+    }
+
+    let csv = fs::read_to_string(csv_path).unwrap();
+    let csv_lines: Vec<_> = csv.lines().collect();
+    assert_eq!(
+        csv_lines[0],
+        "scope,epoch,step,loss,next_token_perplexity,next_token_accuracy,gradient_norm,auxiliary_loss,learning_rate,step_time_micros,device_memory_used"
+    );
+    assert_eq!(csv_lines[1], "step,1,7,0.5,,,,,0.25,,");
+
+    let json_lines = fs::read_to_string(json_lines_path).unwrap();
+    assert_eq!(
+        json_lines,
+        "{\"scope\":\"step\",\"epoch\":1,\"step\":7,\"loss\":0.5,\"learning_rate\":0.25}\n"
+    );
+}
+
+#[derive(Default)]
+struct StopAfterSteps {
+    steps: usize,
+    evaluations: usize,
+}
+
+impl TrainingCallback for StopAfterSteps {
+    fn on_batch_end(
+        &mut self,
+        _neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        record: &MetricsRecord,
+    ) -> Result<TrainingFlow, Error> {
+        assert_eq!(record.step, self.steps);
+        self.steps += 1;
+        if self.steps == 3 {
+            Ok(TrainingFlow::Stop)
+        } else {
+            Ok(TrainingFlow::Continue)
+        }
+    }
+
+    fn on_evaluation(
+        &mut self,
+        _neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        _record: &MetricsRecord,
+    ) -> Result<TrainingFlow, Error> {
+        self.evaluations += 1;
+        Ok(TrainingFlow::Continue)
+    }
+
+    fn on_train_end(
+        &mut self,
+        _neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        _output: &NeuralMachineTestOutput,
+    ) -> Result<(), Error> {
+        assert_eq!(self.steps, 3);
+        // The evaluation before the first step and the final evaluation.
+        assert_eq!(self.evaluations, 2);
+        Ok(())
+    }
+}
+
+#[test]
+fn backward_tape_follows_every_consumer() {
+    let device = Device::default();
+    let input = new_tensor_with_grad!(device, 1, 2, vec![1.0, 2.0], &[], true, true).unwrap();
+    let shared = Sigmoid::new(&device).forward(&input).unwrap();
+    let deeper = Sigmoid::new(&device).forward(&shared).unwrap();
+    // The shared tensor is the last input, so a depth-first walk reaches it
+    // before the deeper tensor that also consumes it.
+    let output = BinaryOperator::forward(&Add::new(&device), &deeper, &shared).unwrap();
+
+    let names = |tape: Vec<TensorWithGrad>| -> Vec<usize> {
+        tape.iter().map(|tensor| tensor.tensor().name()).collect()
+    };
+    let output_name = output.tensor().name();
+    let deeper_name = deeper.tensor().name();
+    let shared_name = shared.tensor().name();
+    assert_eq!(
+        names(output.get_tape()),
+        vec![shared_name, deeper_name, output_name]
+    );
+    assert_eq!(
+        names(output.get_backward_tape()),
+        vec![output_name, deeper_name, shared_name]
+    );
+}
+
+#[test]
+fn callback_stops_training() {
+    let device = Device::default();
+    let mut details = load_simple(&device).unwrap();
+    details.callbacks = vec![Box::<StopAfterSteps>::default()];
+    train_model(details).unwrap();
+}
+
+/// Counts the steps and never stops the training.
+#[derive(Default)]
+struct CountSteps {
+    steps: usize,
+}
+
+impl TrainingCallback for CountSteps {
+    fn on_batch_end(
+        &mut self,
+        _neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        record: &MetricsRecord,
+    ) -> Result<TrainingFlow, Error> {
+        assert_eq!(record.step, self.steps);
+        self.steps += 1;
+        Ok(TrainingFlow::Continue)
+    }
+
+    fn on_train_end(
+        &mut self,
+        _neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        _output: &NeuralMachineTestOutput,
+    ) -> Result<(), Error> {
+        assert_eq!(self.steps, 3);
+        Ok(())
+    }
+}
+
+#[test]
+fn every_callback_is_called_before_stopping() {
+    let device = Device::default();
+    let mut details = load_simple(&device).unwrap();
+    details.callbacks = vec![
+        Box::<StopAfterSteps>::default(),
+        Box::<CountSteps>::default(),
+    ];
+    train_model(details).unwrap();
+}
+
+/// Keeps the records in memory.
+#[derive(Default)]
+struct RecordedMetrics {
+    records: Rc<RefCell<Vec<MetricsRecord>>>,
+}
+
+impl MetricsSink for RecordedMetrics {
+    fn record(&mut self, record: &MetricsRecord) -> Result<(), Error> {
+        self.records.borrow_mut().push(record.clone());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// The validation examples have the expected output of the other training example,
+/// so the validation loss increases when the training loss decreases.
+fn load_simple_with_early_stopping(
+    device: &Device,
+    restore_best_parameters: bool,
+    records: &Rc<RefCell<Vec<MetricsRecord>>>,
+) -> DatasetDetails<impl UnaryModel, impl BinaryOperator, impl OptimizerTrait, impl TensorPrinter> {
+    let mut details = load_simple(device).unwrap();
+    let (first_input, first_output) = details.train_examples[0].clone();
+    let (second_input, second_output) = details.train_examples[1].clone();
+    details.validation_examples = vec![(first_input, second_output), (second_input, first_output)];
+    details.progress = 10;
+    details.early_stopping_patience = Some(3);
+    details.restore_best_parameters = restore_best_parameters;
+    details.metrics_sinks = vec![Box::new(RecordedMetrics {
+        records: records.clone(),
+    })];
+    details
+}
+
+#[test]
+fn early_stopping_records_the_stop_epoch() {
+    let device = Device::default();
+    let records = Rc::new(RefCell::new(vec![]));
+    let details = load_simple_with_early_stopping(&device, false, &records);
+    let epochs = details.epochs;
+    train_model(details).unwrap();
+
+    let records = records.borrow();
+    let validation_epochs: Vec<usize> = records
+        .iter()
+        .filter(|record| record.scope == MetricsScope::Validation)
+        .map(|record| record.epoch)
+        .collect();
+    // The final validation is at the epoch of the early stop.
+    let stop_epoch = validation_epochs[validation_epochs.len() - 2];
+    assert_eq!(validation_epochs.last(), Some(&stop_epoch));
+    assert_lt!(stop_epoch, epochs);
+    let last_record = records.last().unwrap();
+    assert_eq!(last_record.scope, MetricsScope::Epoch);
+    assert_eq!(last_record.epoch, stop_epoch);
+}
+
+/// Compares the validation loss at the end of training with the best validation loss.
+struct BestValidationLossAtTrainEnd {
+    validation_examples: Vec<(TensorWithGrad, TensorWithGrad)>,
+    restore_best_parameters: bool,
+}
+
+impl TrainingCallback for BestValidationLossAtTrainEnd {
+    fn on_train_end(
+        &mut self,
+        neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+        output: &NeuralMachineTestOutput,
+    ) -> Result<(), Error> {
+        let mut total_loss = 0.0;
+        for (input, expected_output) in self.validation_examples.iter() {
+            neural_machine.infer(input)?;
+            let loss = neural_machine.loss(expected_output)?;
+            let loss: &Tensor = &loss.tensor();
+            let loss: f32 = loss.try_into()?;
+            total_loss += loss;
+        }
+        let best_validation_loss = output.best_validation_metrics.as_ref().unwrap().total_loss;
+        assert_eq!(
+            total_loss == best_validation_loss,
+            self.restore_best_parameters
+        );
+        Ok(())
+    }
+}
+
+#[test]
+fn early_stopping_restores_the_best_parameters() {
+    for restore_best_parameters in [false, true] {
+        let device = Device::default();
+        let records = Rc::new(RefCell::new(vec![]));
+        let mut details =
+            load_simple_with_early_stopping(&device, restore_best_parameters, &records);
+        details.callbacks = vec![Box::new(BestValidationLossAtTrainEnd {
+            validation_examples: details.validation_examples.clone(),
+            restore_best_parameters,
+        })];
+        train_model(details).unwrap();
+    }
+}
+
+/// Predicts the next token of each row from the token of the row.
+struct BigramModel {
+    input_shape: Vec<usize>,
+    output_shape: Vec<usize>,
+    embedding: Embedding,
+    linear: Linear,
+    softmax: Softmax,
+}
+
+impl BigramModel {
+    fn new(device: &Device, sequence_length: usize, vocab_size: usize) -> Self {
+        let n_embd = 16;
+        Self {
+            input_shape: vec![sequence_length, vocab_size],
+            output_shape: vec![sequence_length, vocab_size],
+            embedding: Embedding::new(device, vocab_size, n_embd).unwrap(),
+            linear: Linear::new(
+                device,
+                vocab_size,
+                n_embd,
+                WeightsInitialization::Kaiming,
+                sequence_length,
+            )
+            .unwrap(),
+            softmax: Softmax::new_with_next_is_cross_entropy_loss(device),
+        }
+    }
+}
+
+impl UnaryModel for BigramModel {}
+
+impl UnaryOperator for BigramModel {
+    fn forward(&self, input: &TensorWithGrad) -> Result<TensorWithGrad, Error> {
+        let state_0 = self.embedding.forward(input)?;
+        let state_1 = self.linear.forward(&state_0)?;
+        self.softmax.forward(&state_1)
+    }
+}
+
+impl Model for BigramModel {
+    fn input_size(&self) -> Vec<usize> {
+        self.input_shape.clone()
+    }
+    fn output_size(&self) -> Vec<usize> {
+        self.output_shape.clone()
+    }
+}
+
+/// A NeuralMachine that learns the next observed tokens of "quizzed",
+/// with the one-hot encoded input and the padding token.
+fn online_learning_machine(
+    device: &Device,
+) -> (
+    NeuralMachine<f32, DefaultStreamScheduler>,
+    TensorWithGrad,
+    Vec<usize>,
+    usize,
+) {
+    let tokenizer = Tokenizer::ascii_tokenizer();
+    let vocab_size = tokenizer.vocab_size();
+    let pad = tokenizer.special_token(SpecialToken::Pad);
+    let tokens = tokenizer.encode("quizzed");
+    let model = BigramModel::new(device, tokens.len(), vocab_size);
+    let loss_operator = SparseSoftmaxCrossEntropyLoss::try_new_with_attributes(
+        device,
+        SparseCrossEntropyAttributes {
+            ignore_index: Some(pad as i32),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let program = NeuralProgram::try_new(
+        device,
+        &model,
+        &loss_operator,
+        &GradientDescent::new(0.5),
+        None,
+        &[],
+    )
+    .unwrap();
+    let neural_machine =
+        NeuralMachine::<f32, DefaultStreamScheduler>::try_new(device, program, 16).unwrap();
+    let input = into_one_hot_encoded_rows(device, &tokens, vocab_size).unwrap();
+    (neural_machine, input, tokens, pad)
+}
+
+#[test]
+fn online_learning_optimizes_at_update_frequency() {
+    let device = Device::default();
+    let (mut neural_machine, input, tokens, pad) = online_learning_machine(&device);
+    // The next observed tokens. The next token of the last row is not observed yet.
+    let expected_output = into_token_id_rows(&device, &[&tokens[1..], &[pad]].concat()).unwrap();
+
+    neural_machine.infer(&input).unwrap();
+    assert_eq!(neural_machine.online_updates(), 0);
+
+    neural_machine.set_online_learning(Some(OnlineLearning {
+        maximum_updates: Some(2),
+        ..OnlineLearning::new(2, pad)
+    }));
+    let loss_before = neural_machine.loss(&expected_output).unwrap();
+    let loss_before: &Tensor = &loss_before.tensor();
+    let loss_before: f32 = loss_before.try_into().unwrap();
+    for _ in 0..6 {
+        neural_machine.infer(&input).unwrap();
+    }
+    assert_eq!(neural_machine.online_updates(), 2);
+
+    neural_machine.set_online_learning(Some(OnlineLearning::new(1, pad)));
+    neural_machine.set_training(false).unwrap();
+    neural_machine.infer(&input).unwrap();
+    assert_eq!(neural_machine.online_updates(), 2);
+    let loss_after = neural_machine.loss(&expected_output).unwrap();
+    let loss_after: &Tensor = &loss_after.tensor();
+    let loss_after: f32 = loss_after.try_into().unwrap();
+    assert_lt!(loss_after, loss_before);
+}
+
+#[test]
+fn online_learning_skips_examples_while_rate_limited() {
+    let device = Device::default();
+    let (mut neural_machine, input, _, pad) = online_learning_machine(&device);
+    neural_machine.set_online_learning(Some(OnlineLearning {
+        minimum_update_interval: Duration::from_secs(3600),
+        ..OnlineLearning::new(2, pad)
+    }));
+    let gradients = || -> Vec<Vec<f32>> {
+        device
+            .parameter_tensors()
+            .iter()
+            .map(|parameter| parameter.gradient().get_values().unwrap())
+            .collect()
+    };
+
+    // The first optimization is not rate limited.
+    for _ in 0..2 {
+        neural_machine.infer(&input).unwrap();
+    }
+    assert_eq!(neural_machine.online_updates(), 1);
+
+    // The gradient of update_frequency examples is kept until the interval has elapsed.
+    for _ in 0..2 {
+        neural_machine.infer(&input).unwrap();
+    }
+    let accumulated_gradients = gradients();
+    assert!(accumulated_gradients
+        .iter()
+        .any(|gradient| gradient.iter().any(|x| *x != 0.0)));
+    for _ in 0..4 {
+        neural_machine.infer(&input).unwrap();
+    }
+    assert_eq!(neural_machine.online_updates(), 1);
+    assert_eq!(gradients(), accumulated_gradients);
+}
+
+#[test]
+fn evaluation_accepts_class_id_targets() {
+    let device = Device::default();
+    let tokenizer = Tokenizer::ascii_tokenizer();
+    let vocab_size = tokenizer.vocab_size();
+    let tokens = tokenizer.encode("quizzed");
+    let model = BigramModel::new(&device, tokens.len(), vocab_size);
+    let loss_operator = SparseSoftmaxCrossEntropyLoss::new(&device);
+    let program = NeuralProgram::try_new(
+        &device,
+        &model,
+        &loss_operator,
+        &GradientDescent::new(0.5),
+        None,
+        &[],
+    )
+    .unwrap();
+    let mut neural_machine =
+        NeuralMachine::<f32, DefaultStreamScheduler>::try_new(&device, program, 16).unwrap();
+    let input = into_one_hot_encoded_rows(&device, &tokens, vocab_size).unwrap();
+    let actual_output = neural_machine.infer(&input).unwrap();
+    let predicted_next_token = *get_row_argmaxes(&actual_output.tensor())
+        .unwrap()
+        .last()
+        .unwrap();
+
+    let inputs = [input];
+    for (next_token, expected_accuracy) in [
+        (predicted_next_token, 1.0),
+        ((predicted_next_token + 1) % vocab_size, 0.0),
+    ] {
+        let expected_output =
+            into_token_id_rows(&device, &[&tokens[1..], &[next_token]].concat()).unwrap();
+        let (metrics, accuracy) =
+            evaluate(&mut neural_machine, &inputs, &[expected_output]).unwrap();
+        assert!(metrics.total_loss.is_finite());
+        assert_eq!(accuracy, expected_accuracy);
+    }
+}
+
+#[test]
+fn batch_loss_is_accumulated_on_the_device() {
+    let device = Device::default();
+    let details = load_simple(&device).unwrap();
+    let program = NeuralProgram::try_new(
+        &device,
+        &details.model,
+        &details.loss_operator,
+        &details.optimizer,
+        details.max_gradient_norm,
+        &details.parameter_groups,
+    )
+    .unwrap();
+    let mut neural_machine =
+        NeuralMachine::<f32, DefaultStreamScheduler>::try_new(&device, program, 16).unwrap();
+    let inputs: Vec<_> = details.train_examples.iter().map(|x| x.0.clone()).collect();
+    let outputs: Vec<_> = details.train_examples.iter().map(|x| x.1.clone()).collect();
+
+    let mut expected_total_loss = 0.0;
+    for (input, output) in inputs.iter().zip(outputs.iter()) {
+        neural_machine.infer(input).unwrap();
+        let loss = neural_machine.loss(output).unwrap();
+        let loss: &Tensor = &loss.tensor();
+        let loss: f32 = loss.try_into().unwrap();
+        expected_total_loss += loss;
+    }
+    train_on_batch(&mut neural_machine, &[0, 1], &inputs, &outputs).unwrap();
+    assert_eq!(neural_machine.total_loss().unwrap(), expected_total_loss);
+
+    // The next batch restarts the total loss.
+    neural_machine.infer(&inputs[1]).unwrap();
+    let loss = neural_machine.loss(&outputs[1]).unwrap();
+    let loss: &Tensor = &loss.tensor();
+    let expected_total_loss: f32 = loss.try_into().unwrap();
+    train_on_batch(&mut neural_machine, &[1], &inputs, &outputs).unwrap();
+    assert_eq!(neural_machine.total_loss().unwrap(), expected_total_loss);
+}
+
+#[test]
+fn forward_forward_learns_labels() {
+    let device = Device::default();
+    let num_classes = 4;
+    let mut examples = vec![];
+    for label in 0..num_classes {
+        let mut values = vec![0.0; num_classes];
+        values[label] = 1.0;
+        let input =
+            new_tensor_with_grad!(device, 1, num_classes, values.clone(), &[], false, false)
+                .unwrap();
+        let expected_output =
+            new_tensor_with_grad!(device, 1, num_classes, values, &[], false, false).unwrap();
+        examples.push((input, expected_output));
+    }
+    let examples: Vec<(TensorWithGrad, TensorWithGrad)> = examples;
+    let model = MultiLayerPerceptronModel::new(&device, &[2 * num_classes, 16]).unwrap();
+    let details = ForwardForwardDetails {
+        device: device.clone(),
+        train_examples: examples.clone(),
+        test_examples: examples,
+        model,
+        optimizer: GradientDescent::new(0.5),
+        threshold: 2.0,
+        epochs: 100,
+        progress: 10,
+    };
+    let output = train_forward_forward(details).unwrap();
+    assert_eq!(output.final_accuracy, 1.0);
+}
+
+#[test]
+fn tied_embedding_accumulates_both_gradients() {
+    let device = Device::default();
+    let (vocab_size, n_embd, token) = (3, 2, 1);
+    let embedding = SparseEmbedding::new(&device, vocab_size, n_embd).unwrap();
+    let linear = Linear::try_new_tied(&device, &embedding, 1).unwrap();
+    assert_eq!(device.parameter_tensors().len(), 2);
+
+    let input = into_token_id_rows(&device, &[token]).unwrap();
+    let output = linear.forward(&embedding.forward(&input).unwrap()).unwrap();
+    assert_eq!(*output.tensor().size(), [1, vocab_size]);
+
+    let device_stream = device.new_stream().unwrap();
+    for tensor in output.get_tape().iter() {
+        tensor.forward(&device, &device_stream).unwrap();
+    }
+    output.gradient().set_values(vec![1.0; vocab_size]).unwrap();
+    for tensor in output.get_backward_tape().iter() {
+        tensor.compute_gradient(&device, &device_stream).unwrap();
+    }
+    device_stream.wait_for().unwrap();
+
+    // The table T has shape [vocab_size, n_embd] and h_j = T[token, j].
+    // d Σ y / d T[v, j] = h_j + (v == token) * Σ_v' T[v', j]
+    let table = embedding.embedding_table();
+    let values = table.tensor().get_values().unwrap();
+    let gradient = table.gradient().get_values().unwrap();
+    for j in 0..n_embd {
+        let column_sum: f32 = (0..vocab_size).map(|v| values[v * n_embd + j]).sum();
+        for v in 0..vocab_size {
+            let mut expected = values[token * n_embd + j];
+            if v == token {
+                expected += column_sum;
+            }
+            assert_lt!((gradient[v * n_embd + j] - expected).abs(), 1e-5);
+        }
+    }
+}
+
+#[test]
+fn mixture_of_experts_auxiliary_loss_is_reported() {
+    let device = Device::default();
+    let (layers, context_length, vocab_size) = (2, 4, 8);
+    let config = TransformerConfig {
+        feed_forward: FeedForward::MixtureOfExperts {
+            num_experts: 3,
+            top_k: 1,
+            load_balancing_coefficient: 0.01,
+        },
+        ..TransformerConfig::new(2)
+    };
+    let model =
+        TransformerModel::new(&device, layers, 4, context_length, vocab_size, &config).unwrap();
+    let program = NeuralProgram::try_new(
+        &device,
+        &model,
+        &SparseSoftmaxCrossEntropyLoss::new(&device),
+        &GradientDescent::new(0.5),
+        None,
+        &[],
+    )
+    .unwrap();
+    assert_eq!(program.auxiliary_losses.len(), layers);
+    let mut neural_machine =
+        NeuralMachine::<f32, DefaultStreamScheduler>::try_new(&device, program, 16).unwrap();
+    let input = into_token_id_rows(&device, &[1, 2, 3, 4]).unwrap();
+    neural_machine.infer(&input).unwrap();
+    let auxiliary_loss = neural_machine.auxiliary_loss().unwrap().unwrap();
+    assert_lt!(0.0, auxiliary_loss);
+}
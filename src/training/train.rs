@@ -8,7 +8,8 @@ use crate::{
     perplexity::get_perplexity,
     schedulers::DefaultStreamScheduler,
     tensor::{Error, Tensor},
-    BinaryOperator, Device, NeuralMachine, OptimizerTrait, TensorWithGrad, UnaryModel,
+    BinaryOperator, Device, MetricsRecord, MetricsScope, MetricsSink, NeuralMachine,
+    OptimizerTrait, TensorWithGrad, TrainingCallback, TrainingFlow, UnaryModel,
 };
 
 fn print_device_mem_info(device: &Device) -> Result<(), Error> {
@@ -50,11 +51,13 @@ fn print_metrics(epoch: usize, metrics: &Metrics, previous_metrics: &Metrics) ->
 pub struct NeuralMachineTestOutput {
     pub initial_metrics: Metrics,
     pub final_metrics: Metrics,
+    /// Metrics of the best validation, if there are validation examples.
+    pub best_validation_metrics: Option<Metrics>,
     pub expected_argmax_values: Vec<usize>,
     pub actual_argmax_values: Vec<usize>,
 }
 
-pub fn train_model<T>(
+pub fn train_model(
     details: DatasetDetails<
         impl UnaryModel,
         impl BinaryOperator,
@@ -75,20 +78,26 @@ pub fn train_model<T>(
     let loss_operator = details.loss_operator;
     let maximum_device_streams = 16;
     let device = details.device;
-    let clipped_gradient_norm = details.clip_gradient_norm;
+    let max_gradient_norm = details.max_gradient_norm;
     let shuffle_examples = details.shuffle_examples;
     let batch_size = details.batch_size;
     let optimizer = details.optimizer;
+    let parameter_groups = details.parameter_groups;
     let mut printer = details.printer;
+    let mut metrics_sinks = details.metrics_sinks;
+    let mut callbacks = details.callbacks;
+    // Step metrics are only measured if something consumes them.
+    let record_steps = !metrics_sinks.is_empty() || !callbacks.is_empty();
 
     let program = NeuralProgram::try_new(
         &device,
         &model,
         &loss_operator,
         &optimizer,
-        clipped_gradient_norm,
+        max_gradient_norm,
+        &parameter_groups,
     )?;
-    let mut neural_machine = NeuralMachine::<T, DefaultStreamScheduler>::try_new(
+    let mut neural_machine = NeuralMachine::<f32, DefaultStreamScheduler>::try_new(
         &device,
         program,
         maximum_device_streams,
@@ -97,9 +106,23 @@ pub fn train_model<T>(
     let train_inputs: Vec<_> = train_examples.iter().map(|x| x.clone().0).collect();
     let train_outputs: Vec<_> = train_examples.iter().map(|x| x.clone().1).collect();
 
+    let validation_examples = &details.validation_examples;
+    let validation_inputs: Vec<_> = validation_examples.iter().map(|x| x.clone().0).collect();
+    let validation_outputs: Vec<_> = validation_examples.iter().map(|x| x.clone().1).collect();
+
     let epochs = details.epochs;
     let progress = details.progress;
-
+    let early_stopping_patience = details.early_stopping_patience;
+    let restore_best_parameters = details.restore_best_parameters;
+    let mut best_validation_metrics: Option<Metrics> = None;
+    let mut best_parameters: Option<Vec<Vec<f32>>> = None;
+    let mut validations_without_improvement = 0;
+    let mut step = 0;
+    // Epoch at which training stopped, epochs if it was not stopped.
+    let mut stop_epoch = epochs;
+
+    // Dropout and batch standardization behave as in inference during evaluations.
+    neural_machine.set_training(false)?;
     let (_, _) = print_results(
         0,
         &mut neural_machine,
@@ -110,25 +133,157 @@ pub fn train_model<T>(
 
     let indices = (0..train_examples.len()).collect::<Vec<_>>();
 
-    for epoch in 0..epochs {
-        let batches = make_batches(&indices, shuffle_examples, batch_size);
+    'epochs: for epoch in 0..epochs {
+        stop_epoch = epoch;
+        let mut flow = TrainingFlow::Continue;
+        for callback in callbacks.iter_mut() {
+            if callback.on_epoch_start(&mut neural_machine, epoch)? == TrainingFlow::Stop {
+                flow = TrainingFlow::Stop;
+            }
+        }
+        if flow == TrainingFlow::Stop {
+            break 'epochs;
+        }
+        let batches = make_batches(&indices, shuffle_examples, batch_size, &mut *device.rng());
         if epoch % progress == 0 {
-            let metrics = total_metrics(&mut neural_machine, &train_inputs, &train_outputs)?;
+            neural_machine.set_training(false)?;
+            let (metrics, next_token_accuracy) =
+                evaluate(&mut neural_machine, &train_inputs, &train_outputs)?;
             print_metrics(epoch, &metrics, &previous_metrics)?;
             print_device_mem_info(&device)?;
             if epoch == 0 {
                 initial_metrics = metrics.clone();
             }
             previous_metrics = metrics.clone();
+
+            let mut record = MetricsRecord::new(MetricsScope::Epoch, epoch, step);
+            record.loss = Some(metrics.total_loss);
+            record.next_token_perplexity = Some(metrics.total_next_token_perplexity);
+            record.next_token_accuracy = Some(next_token_accuracy);
+            record.learning_rate = Some(optimizer.learning_rate());
+            record.device_memory_used = Some(device.get_memory_info()?.used);
+            let flow = report_evaluation(
+                &mut neural_machine,
+                &mut metrics_sinks,
+                &mut callbacks,
+                &record,
+            )?;
+            if flow == TrainingFlow::Stop {
+                break 'epochs;
+            }
+
+            if !validation_examples.is_empty() {
+                let (improved, record) = validate(
+                    epoch,
+                    step,
+                    &mut neural_machine,
+                    &validation_inputs,
+                    &validation_outputs,
+                    &mut best_validation_metrics,
+                )?;
+                if improved {
+                    validations_without_improvement = 0;
+                    if restore_best_parameters {
+                        best_parameters = Some(get_parameters(&device)?);
+                    }
+                } else {
+                    validations_without_improvement += 1;
+                }
+                let flow = report_evaluation(
+                    &mut neural_machine,
+                    &mut metrics_sinks,
+                    &mut callbacks,
+                    &record,
+                )?;
+                if flow == TrainingFlow::Stop {
+                    break 'epochs;
+                }
+                if let Some(patience) = early_stopping_patience {
+                    if validations_without_improvement >= patience {
+                        println!("Early stopping at epoch {}", epoch);
+                        break 'epochs;
+                    }
+                }
+            }
+        }
+        neural_machine.set_training(true)?;
+        for batch in batches.iter() {
+            let start = SystemTime::now();
+            train_on_batch(&mut neural_machine, batch, &train_inputs, &train_outputs)?;
+            let end = SystemTime::now();
+            // Reading the step metrics waits for the device.
+            if !record_steps {
+                step += 1;
+                continue;
+            }
+            let step_time = end.duration_since(start).unwrap();
+            let mut record = MetricsRecord::new(MetricsScope::Step, epoch, step);
+            record.loss = Some(neural_machine.total_loss()?);
+            record.gradient_norm = neural_machine.gradient_norm()?;
+            record.auxiliary_loss = neural_machine.auxiliary_loss()?;
+            record.learning_rate = Some(optimizer.learning_rate());
+            record.step_time_micros = Some(step_time.as_micros());
+            record.device_memory_used = Some(device.get_memory_info()?.used);
+            record_metrics(&mut metrics_sinks, &record)?;
+            step += 1;
+            let mut flow = TrainingFlow::Continue;
+            for callback in callbacks.iter_mut() {
+                if callback.on_batch_end(&mut neural_machine, &record)? == TrainingFlow::Stop {
+                    flow = TrainingFlow::Stop;
+                }
+            }
+            if flow == TrainingFlow::Stop {
+                break 'epochs;
+            }
+        }
+        stop_epoch = epoch + 1;
+    }
+
+    neural_machine.set_training(false)?;
+    if !validation_examples.is_empty() {
+        let (improved, record) = validate(
+            stop_epoch,
+            step,
+            &mut neural_machine,
+            &validation_inputs,
+            &validation_outputs,
+            &mut best_validation_metrics,
+        )?;
+        // The training has ended, the flow of the final evaluations is ignored.
+        report_evaluation(
+            &mut neural_machine,
+            &mut metrics_sinks,
+            &mut callbacks,
+            &record,
+        )?;
+        if !improved {
+            if let Some(best_parameters) = best_parameters {
+                set_parameters(&device, best_parameters)?;
+            }
         }
-        train_on_batches(&mut neural_machine, &batches, &train_inputs, &train_outputs)?;
     }
-    let final_metrics = total_metrics(&mut neural_machine, &train_inputs, &train_outputs)?;
-    print_metrics(epochs, &final_metrics, &previous_metrics)?;
+    let (final_metrics, next_token_accuracy) =
+        evaluate(&mut neural_machine, &train_inputs, &train_outputs)?;
+    print_metrics(stop_epoch, &final_metrics, &previous_metrics)?;
+    let mut record = MetricsRecord::new(MetricsScope::Epoch, stop_epoch, step);
+    record.loss = Some(final_metrics.total_loss);
+    record.next_token_perplexity = Some(final_metrics.total_next_token_perplexity);
+    record.next_token_accuracy = Some(next_token_accuracy);
+    record.learning_rate = Some(optimizer.learning_rate());
+    record.device_memory_used = Some(device.get_memory_info()?.used);
+    report_evaluation(
+        &mut neural_machine,
+        &mut metrics_sinks,
+        &mut callbacks,
+        &record,
+    )?;
+    for sink in metrics_sinks.iter_mut() {
+        sink.flush()?;
+    }
     print_device_mem_info(&device)?;
 
     let (expected_argmax_values, actual_argmax_values) = print_results(
-        epochs,
+        stop_epoch,
         &mut neural_machine,
         &mut printer,
         &train_inputs,
@@ -138,6 +293,7 @@ pub fn train_model<T>(
     let output = NeuralMachineTestOutput {
         initial_metrics,
         final_metrics,
+        best_validation_metrics,
         expected_argmax_values,
         actual_argmax_values,
     };
@@ -158,6 +314,10 @@ pub fn train_model<T>(
         )?;
     }
 
+    for callback in callbacks.iter_mut() {
+        callback.on_train_end(&mut neural_machine, &output)?;
+    }
+
     Ok(output)
 }
 
@@ -184,8 +344,8 @@ fn print_results<T>(
         let actual_output = &actual_output.tensor();
 
         let expected_output = &outputs[i].tensor();
-        let expected_output_argmaxes = get_row_argmaxes(expected_output)?;
-        let expected_argmax = expected_output_argmaxes[last_row].to_owned();
+        let expected_output_tokens = get_expected_row_tokens(expected_output, actual_output)?;
+        let expected_argmax = expected_output_tokens[last_row].to_owned();
         expected_argmax_values.push(expected_argmax);
 
         let actual_output_argmaxes = get_row_argmaxes(actual_output)?;
@@ -205,6 +365,74 @@ fn print_results<T>(
     Ok((expected_argmax_values, actual_argmax_values))
 }
 
+fn record_metrics(
+    metrics_sinks: &mut [Box<dyn MetricsSink>],
+    record: &MetricsRecord,
+) -> Result<(), Error> {
+    for sink in metrics_sinks.iter_mut() {
+        sink.record(record)?;
+    }
+    Ok(())
+}
+
+/// Record an evaluation in the metrics sinks and call the callbacks.
+fn report_evaluation(
+    neural_machine: &mut NeuralMachine<f32, DefaultStreamScheduler>,
+    metrics_sinks: &mut [Box<dyn MetricsSink>],
+    callbacks: &mut [Box<dyn TrainingCallback>],
+    record: &MetricsRecord,
+) -> Result<TrainingFlow, Error> {
+    record_metrics(metrics_sinks, record)?;
+    let mut flow = TrainingFlow::Continue;
+    for callback in callbacks.iter_mut() {
+        if callback.on_evaluation(neural_machine, record)? == TrainingFlow::Stop {
+            flow = TrainingFlow::Stop;
+        }
+    }
+    Ok(flow)
+}
+
+/// Evaluate the validation examples and update the best validation metrics.
+/// Returns true if the validation loss improved.
+fn validate<T>(
+    epoch: usize,
+    step: usize,
+    neural_machine: &mut NeuralMachine<T, DefaultStreamScheduler>,
+    inputs: &[TensorWithGrad],
+    outputs: &[TensorWithGrad],
+    best_validation_metrics: &mut Option<Metrics>,
+) -> Result<(bool, MetricsRecord), Error> {
+    let (metrics, next_token_accuracy) = evaluate(neural_machine, inputs, outputs)?;
+    println!("Epoch {} validation_loss {}", epoch, metrics.total_loss);
+    let mut record = MetricsRecord::new(MetricsScope::Validation, epoch, step);
+    record.loss = Some(metrics.total_loss);
+    record.next_token_perplexity = Some(metrics.total_next_token_perplexity);
+    record.next_token_accuracy = Some(next_token_accuracy);
+    let improved = match best_validation_metrics {
+        Some(best) => metrics.total_loss < best.total_loss,
+        None => true,
+    };
+    if improved {
+        *best_validation_metrics = Some(metrics);
+    }
+    Ok((improved, record))
+}
+
+fn get_parameters(device: &Device) -> Result<Vec<Vec<f32>>, Error> {
+    device
+        .parameter_tensors()
+        .iter()
+        .map(|parameter| parameter.tensor().get_values())
+        .collect()
+}
+
+fn set_parameters(device: &Device, values: Vec<Vec<f32>>) -> Result<(), Error> {
+    for (parameter, values) in device.parameter_tensors().iter().zip(values) {
+        parameter.tensor().set_values(values)?;
+    }
+    Ok(())
+}
+
 pub fn get_row_argmaxes(tensor: &Tensor) -> Result<Vec<usize>, Error> {
     let values = tensor.get_values()?;
     let cols = tensor.cols();
@@ -223,6 +451,33 @@ pub fn get_row_argmaxes(tensor: &Tensor) -> Result<Vec<usize>, Error> {
     Ok(argmaxes)
 }
 
+/// The token of each row.
+/// The token of a row is its value if the tensor contains token ids, see into_token_id_rows,
+/// or the column of its maximum otherwise.
+pub fn get_row_tokens(tensor: &Tensor) -> Result<Vec<usize>, Error> {
+    match tensor.cols() {
+        1 => Ok(tensor
+            .get_values()?
+            .into_iter()
+            .map(|token| token as usize)
+            .collect()),
+        _ => get_row_argmaxes(tensor),
+    }
+}
+
+/// The expected token of each row.
+/// An expected output with one column contains class ids, see SparseSoftmaxCrossEntropyLoss,
+/// unless the actual output also has one column, like in a regression.
+pub fn get_expected_row_tokens(
+    expected_output: &Tensor,
+    actual_output: &Tensor,
+) -> Result<Vec<usize>, Error> {
+    match actual_output.cols() {
+        1 => get_row_argmaxes(expected_output),
+        _ => get_row_tokens(expected_output),
+    }
+}
+
 pub fn get_row_argmax(tensor: &Tensor, row: usize) -> Result<usize, Error> {
     let values = tensor.get_values()?;
     let cols = tensor.cols();
@@ -240,30 +495,54 @@ pub fn get_row_argmax(tensor: &Tensor, row: usize) -> Result<usize, Error> {
 pub fn train_on_batches<T>(
     neural_machine: &mut NeuralMachine<T, DefaultStreamScheduler>,
     batches: &[Vec<usize>],
-    inputs: &Vec<TensorWithGrad>,
-    outputs: &Vec<TensorWithGrad>,
+    inputs: &[TensorWithGrad],
+    outputs: &[TensorWithGrad],
 ) -> Result<(), Error> {
     for batch in batches {
-        for i in batch.iter() {
-            let input = &inputs[*i];
-            let output = &outputs[*i];
-            let _output = neural_machine.infer(input)?;
-            let _loss = neural_machine.loss(output)?;
-            neural_machine.compute_gradient()?;
-        }
-        neural_machine.optimize()?;
+        train_on_batch(neural_machine, batch, inputs, outputs)?;
     }
 
     Ok(())
 }
 
+/// Train on one batch.
+/// The total loss of the batch is accumulated on the device, see NeuralMachine::total_loss.
+pub fn train_on_batch<T>(
+    neural_machine: &mut NeuralMachine<T, DefaultStreamScheduler>,
+    batch: &[usize],
+    inputs: &[TensorWithGrad],
+    outputs: &[TensorWithGrad],
+) -> Result<(), Error> {
+    for (index, i) in batch.iter().enumerate() {
+        let input = &inputs[*i];
+        let output = &outputs[*i];
+        let _output = neural_machine.infer(input)?;
+        let _loss = neural_machine.loss(output)?;
+        neural_machine.accumulate_loss(index == 0)?;
+        neural_machine.compute_gradient()?;
+    }
+    neural_machine.optimize()?;
+    Ok(())
+}
+
 pub fn total_metrics<T>(
     neural_machine: &mut NeuralMachine<T, DefaultStreamScheduler>,
     inputs: &[TensorWithGrad],
     outputs: &[TensorWithGrad],
 ) -> Result<Metrics, Error> {
+    let (metrics, _) = evaluate(neural_machine, inputs, outputs)?;
+    Ok(metrics)
+}
+
+/// Returns the metrics and the next token accuracy.
+pub fn evaluate<T>(
+    neural_machine: &mut NeuralMachine<T, DefaultStreamScheduler>,
+    inputs: &[TensorWithGrad],
+    outputs: &[TensorWithGrad],
+) -> Result<(Metrics, f32), Error> {
     let mut total_loss = 0.0;
     let mut total_next_token_perplexity = 0.0;
+    let mut correct_next_tokens = 0;
     for i in 0..inputs.len() {
         let expected_output = &outputs[i];
         let actual_output = neural_machine.infer(&inputs[i])?;
@@ -276,15 +555,25 @@ pub fn total_metrics<T>(
 
         // Perplexity
         let actual_output = &actual_output.tensor();
-        let perplexity = get_perplexity(actual_output, actual_output.rows() - 1)?;
+        let last_row = actual_output.rows() - 1;
+        let perplexity = get_perplexity(actual_output, last_row)?;
         total_next_token_perplexity += perplexity;
+
+        // Accuracy
+        let expected_next_token =
+            get_expected_row_tokens(&expected_output.tensor(), actual_output)?[last_row];
+        let actual_next_token = get_row_argmax(actual_output, last_row)?;
+        if expected_next_token == actual_next_token {
+            correct_next_tokens += 1;
+        }
     }
 
     let metrics = Metrics {
         total_loss,
         total_next_token_perplexity,
     };
-    Ok(metrics)
+    let next_token_accuracy = correct_next_tokens as f32 / inputs.len() as f32;
+    Ok((metrics, next_token_accuracy))
 }
 
 pub fn time_it<F: Fn() -> T, T>(text: &str, f: F) -> T {